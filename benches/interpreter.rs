@@ -0,0 +1,84 @@
+// Benchmarks of every stage of running a program, run them with
+//
+//     cargo bench
+//
+// and compare against a baseline with --save-baseline/--baseline to measure changes.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use sapo::{evaluate_program, parse_program, Environment, Lexer};
+use std::hint::black_box;
+use std::io;
+
+/// Fibonacci numbers computed iteratively, the language has no loops so the iterations are unrolled
+fn fib() -> String {
+    let step = "c = a + b;\na = b;\nb = c;\n";
+    format!("a = 0;\nb = 1;\n{}b", step.repeat(40))
+}
+
+/// Arithmetic nested as deeply as the default limits allow
+fn nested() -> String {
+    let operators = ["+", "-", "*"];
+    let mut expression = String::from("1");
+    for i in 0..100 {
+        expression = format!("({} {} {})", i % 7 + 1, operators[i % 3], expression);
+    }
+    vec![expression; 20].join(";\n")
+}
+
+/// Many string values assigned and printed
+fn strings() -> String {
+    let mut program = String::new();
+    for i in 0..200 {
+        program.push_str(&format!(
+            "s{} = \"string number {}\";\nprint(s{});\n",
+            i % 10,
+            i,
+            i % 10
+        ));
+    }
+    program
+}
+
+fn programs() -> Vec<(&'static str, String)> {
+    vec![("fib", fib()), ("nested", nested()), ("strings", strings())]
+}
+
+fn lex(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lex");
+    for (name, source) in programs() {
+        group.bench_function(name, |b| b.iter(|| Lexer::new(black_box(&source)).count()));
+    }
+    group.finish();
+}
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    for (name, source) in programs() {
+        group.bench_function(name, |b| {
+            b.iter(|| parse_program(black_box(&source)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn evaluate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("evaluate");
+    for (name, source) in programs() {
+        let program = parse_program(&source).unwrap();
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || {
+                    let mut environment = Environment::new();
+                    environment.set_output(Box::new(io::sink()));
+                    environment
+                },
+                |mut environment| evaluate_program(black_box(&program), &mut environment).unwrap(),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, lex, parse, evaluate);
+criterion_main!(benches);
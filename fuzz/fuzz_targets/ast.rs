@@ -0,0 +1,70 @@
+#![no_main]
+
+// Generates syntactically plausible programs instead of random bytes, so that the
+// fuzzer spends its time past the lexer, and checks that formatting is stable.
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use sapo::{format_program, parse_program};
+
+const NAMES: [&str; 6] = ["x", "y", "sapo", "print", "args", "exit"];
+const UNARY: [&str; 2] = ["-", "!"];
+const BINARY: [&str; 10] = ["+", "-", "*", "/", "==", "!=", "<", "<=", ">", ">="];
+
+#[derive(Arbitrary, Debug)]
+enum Expr {
+    Integer(u32),
+    Boolean(bool),
+    String(Vec<u8>),
+    Variable(u8),
+    Grouping(Box<Expr>),
+    Unary(u8, Box<Expr>),
+    Binary(u8, Box<Expr>, Box<Expr>),
+    Assignment(u8, Box<Expr>),
+    Call(u8, Vec<Expr>),
+}
+
+impl Expr {
+    fn source(&self) -> String {
+        match self {
+            Expr::Integer(value) => value.to_string(),
+            Expr::Boolean(value) => value.to_string(),
+            // strings can't contain quotes
+            Expr::String(bytes) => {
+                format!("\"{}\"", String::from_utf8_lossy(bytes).replace('"', ""))
+            }
+            Expr::Variable(name) => name_of(*name).to_string(),
+            Expr::Grouping(expr) => format!("({})", expr.source()),
+            Expr::Unary(op, right) => {
+                format!("{}{}", UNARY[*op as usize % UNARY.len()], right.source())
+            }
+            Expr::Binary(op, left, right) => format!(
+                "{} {} {}",
+                left.source(),
+                BINARY[*op as usize % BINARY.len()],
+                right.source()
+            ),
+            Expr::Assignment(name, value) => format!("{} = {}", name_of(*name), value.source()),
+            Expr::Call(name, arguments) => {
+                let arguments: Vec<String> = arguments.iter().map(Expr::source).collect();
+                format!("{}({})", name_of(*name), arguments.join(", "))
+            }
+        }
+    }
+}
+
+fn name_of(index: u8) -> &'static str {
+    NAMES[index as usize % NAMES.len()]
+}
+
+fuzz_target!(|statements: Vec<Expr>| {
+    let source: Vec<String> = statements.iter().map(Expr::source).collect();
+    let program = match parse_program(&source.join(";\n")) {
+        Ok(program) => program,
+        // e.g. too large integers or assignments to operations
+        Err(_) => return,
+    };
+    let formatted = format_program(&program);
+    let reparsed = parse_program(&formatted).expect("formatted program doesn't parse");
+    assert_eq!(format_program(&reparsed), formatted);
+});
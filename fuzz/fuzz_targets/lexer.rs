@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sapo::cst::SyntaxTree;
+use sapo::Lexer;
+
+fuzz_target!(|input: &str| {
+    Lexer::new(input).count();
+    // the concrete syntax tree must be lossless
+    assert_eq!(SyntaxTree::new(input).to_string(), input);
+});
@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sapo::{check_warnings, parse_program};
+
+fuzz_target!(|input: &str| {
+    if let Ok(program) = parse_program(input) {
+        for &statement in &program.statements {
+            check_warnings(&program.ast, statement);
+        }
+    }
+});
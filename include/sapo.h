@@ -0,0 +1,57 @@
+/* C interface of sapo, available when the library is built with the capi feature:
+ *
+ *     cargo build --release --features capi
+ *
+ * Strings are UTF-8 and NUL terminated. Strings returned by sapo are owned by
+ * the caller and must be released with sapo_string_free. */
+
+#ifndef SAPO_H
+#define SAPO_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define SAPO_OK 0
+#define SAPO_PARSE_ERROR 1
+#define SAPO_EVAL_ERROR 2
+/* The program called exit() */
+#define SAPO_EXIT 3
+/* A pointer was null or a string was not valid UTF-8 */
+#define SAPO_INVALID_ARGUMENT 4
+/* sapo panicked, the interpreter should not be used anymore */
+#define SAPO_PANIC 5
+
+/* Variables persist between the programs evaluated by an interpreter */
+typedef struct SapoInterpreter SapoInterpreter;
+
+SapoInterpreter *sapo_interpreter_new(void);
+/* Creates an interpreter whose evaluations stop with an error when they
+ * evaluate more than max_steps expressions, use about more than max_memory
+ * bytes or take longer than timeout_ms milliseconds. 0 means no limit. */
+SapoInterpreter *sapo_interpreter_new_with_limits(uint64_t max_steps,
+                                                  size_t max_memory,
+                                                  uint64_t timeout_ms);
+void sapo_interpreter_free(SapoInterpreter *interpreter);
+
+/* Evaluates a program. On success *result is set to the value of its last
+ * expression, or to an empty string if the program is empty. On errors it is
+ * set to the message of the error, and if the program called exit() to the
+ * status code it passed. */
+int sapo_eval(SapoInterpreter *interpreter, const char *source, char **result);
+
+/* Parses a program without evaluating it. On success *result is set to its
+ * syntax tree as a JSON array with one element per expression, on errors to
+ * the message of the error. */
+int sapo_parse(const char *source, char **result);
+
+void sapo_string_free(char *string);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
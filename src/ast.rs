@@ -1,15 +1,471 @@
-use super::token::Token;
+use super::interner::Symbol;
+use super::token::{Span, Token, TokenType};
+use crate::ast_printer::print_ast_json;
+use crate::json::{self, Value};
+use crate::parsing::{BINARY_OPERATORS, UNARY_OPERATORS};
+use std::convert::TryFrom;
+use std::ops::Index;
+use std::sync::Arc;
 
-#[derive(Debug, PartialEq)]
+/// Index of an expression in the Ast it was added to, unique within the Ast and stable
+/// while it exists, so other passes can use it to attach information to the expression
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ExprId(u32);
+
+impl ExprId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Arena with the expressions of a syntax tree, which refer to their subexpressions by ExprId.
+/// Subexpressions are added before the expressions containing them.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Ast {
+    expressions: Vec<Expression>,
+    /// The source text of each expression
+    spans: Vec<Span>,
+}
+
+impl Ast {
+    pub fn new() -> Self {
+        Ast::default()
+    }
+
+    /// Adds the expression, spanning its token and its subexpressions
+    pub fn add(&mut self, expression: Expression) -> ExprId {
+        let span = expression
+            .children()
+            .iter()
+            .fold(expression.token().span, |span, &child| {
+                span.merge(self.span(child))
+            });
+        self.expressions.push(expression);
+        self.spans.push(span);
+        ExprId(self.expressions.len() as u32 - 1)
+    }
+
+    /// Adds the expression with the given span instead of computing it
+    pub(crate) fn add_with_span(&mut self, expression: Expression, span: Span) -> ExprId {
+        self.expressions.push(expression);
+        self.spans.push(span);
+        ExprId(self.expressions.len() as u32 - 1)
+    }
+
+    pub fn get(&self, id: ExprId) -> Option<&Expression> {
+        self.expressions.get(id.index())
+    }
+
+    /// Byte offsets of the source the expression was parsed from
+    pub fn span(&self, id: ExprId) -> Span {
+        self.spans[id.index()]
+    }
+
+    /// Extends the span of the expression to text that isn't part of its tokens,
+    /// e.g. the closing ')' of a call
+    pub(crate) fn cover(&mut self, id: ExprId, span: Span) {
+        self.spans[id.index()] = self.spans[id.index()].merge(span);
+    }
+
+    /// Removes the expressions added after the first len ones
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.expressions.truncate(len);
+        self.spans.truncate(len);
+    }
+
+    pub fn len(&self) -> usize {
+        self.expressions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.expressions.is_empty()
+    }
+}
+
+/// Constructors of expressions that make up their tokens, for building trees without
+/// parsing them, e.g. in tests or code generators. The tokens have a line of -1.
+impl Ast {
+    pub fn integer(&mut self, value: i32) -> ExprId {
+        let token = synthetic(TokenType::IntegerLiteral, &value.to_string());
+        self.add(Expression::IntegerLiteral { token, value })
+    }
+
+    pub fn boolean(&mut self, value: bool) -> ExprId {
+        let token = synthetic(TokenType::BooleanLiteral, &value.to_string());
+        self.add(Expression::BooleanLiteral { token, value })
+    }
+
+    pub fn string(&mut self, value: &str) -> ExprId {
+        let token = synthetic(TokenType::StringLiteral, value);
+        let value = Arc::clone(&token.lexeme);
+        self.add(Expression::StringLiteral { token, value })
+    }
+
+    pub fn variable(&mut self, name: &str) -> ExprId {
+        let token = synthetic(TokenType::Identifier, name);
+        let name = Symbol::intern(name);
+        self.add(Expression::Variable { token, name })
+    }
+
+    pub fn grouping(&mut self, expr: ExprId) -> ExprId {
+        let token = synthetic(TokenType::LeftParen, "(");
+        self.add(Expression::Grouping { token, expr })
+    }
+
+    /// operator is the type of the token of the operator, e.g. TokenType::Bang
+    pub fn unary(&mut self, operator: TokenType, right: ExprId) -> ExprId {
+        let token = synthetic_operator(operator);
+        self.add(Expression::UnaryExpression { token, right })
+    }
+
+    /// operator is the type of the token of the operator, e.g. TokenType::Plus
+    pub fn binary(&mut self, operator: TokenType, left: ExprId, right: ExprId) -> ExprId {
+        let token = synthetic_operator(operator);
+        self.add(Expression::BinaryExpression { token, left, right })
+    }
+
+    pub fn assignment(&mut self, name: &str, value: ExprId) -> ExprId {
+        let token = synthetic(TokenType::Assignment, "=");
+        let name = Symbol::intern(name);
+        self.add(Expression::Assignment { token, name, value })
+    }
+
+    pub fn tuple(&mut self, elements: Vec<ExprId>) -> ExprId {
+        let token = synthetic(TokenType::LeftParen, "(");
+        self.add(Expression::Tuple { token, elements })
+    }
+
+    pub fn multiple_assignment(&mut self, names: &[&str], value: ExprId) -> ExprId {
+        let token = synthetic(TokenType::Assignment, "=");
+        let names = names.iter().map(|&name| Symbol::intern(name)).collect();
+        self.add(Expression::MultipleAssignment {
+            token,
+            names,
+            value,
+        })
+    }
+
+    pub fn index(&mut self, target: ExprId, index: ExprId) -> ExprId {
+        let token = synthetic(TokenType::LeftBracket, "[");
+        self.add(Expression::Index {
+            token,
+            target,
+            index,
+        })
+    }
+
+    pub fn slice(&mut self, target: ExprId, start: Option<ExprId>, end: Option<ExprId>) -> ExprId {
+        let token = synthetic(TokenType::LeftBracket, "[");
+        self.add(Expression::Slice {
+            token,
+            target,
+            start,
+            end,
+        })
+    }
+
+    pub fn type_test(&mut self, value: ExprId, type_name: &str) -> ExprId {
+        let token = synthetic(TokenType::Is, "is");
+        let type_name = Symbol::intern(type_name);
+        self.add(Expression::TypeTest {
+            token,
+            value,
+            type_name,
+        })
+    }
+
+    pub fn call(&mut self, name: &str, arguments: Vec<ExprId>) -> ExprId {
+        let token = synthetic(TokenType::Identifier, name);
+        let name = Symbol::intern(name);
+        self.add(Expression::Call {
+            token,
+            name,
+            arguments,
+        })
+    }
+}
+
+fn synthetic(token_type: TokenType, lexeme: &str) -> Token {
+    Token::new(token_type, lexeme, -1)
+}
+
+fn synthetic_operator(operator: TokenType) -> Token {
+    let lexeme = operator.symbol().unwrap_or_default();
+    synthetic(operator, lexeme)
+}
+
+/// Reading of trees written by print_ast_json
+impl Ast {
+    /// Adds the expression serialized in json, with its subexpressions. The "line" and
+    /// "span" of nodes are optional, tokens without a line have a line of -1.
+    pub fn add_json(&mut self, json: &str) -> Result<ExprId, String> {
+        self.add_value(&json::parse(json)?)
+    }
+
+    fn add_value(&mut self, node: &Value) -> Result<ExprId, String> {
+        let node_type = match node.get("type") {
+            Some(Value::String(node_type)) => node_type.as_str(),
+            _ => return Err(String::from("expected an object with a \"type\"")),
+        };
+        let line = match node.get("line") {
+            Some(Value::Number(line)) => {
+                i32::try_from(*line).map_err(|_| format!("invalid line {}", line))?
+            }
+            Some(_) => return Err(String::from("\"line\" must be an integer")),
+            None => -1,
+        };
+        let span = match node.get("span") {
+            Some(span) => Some(span_field(span)?),
+            None => None,
+        };
+        // the tokens of leaves are the whole expression
+        let leaf = |token_type, lexeme: &str| {
+            let mut token = Token::new(token_type, lexeme, line);
+            token.span = span.unwrap_or_default();
+            token
+        };
+        let expression = match node_type {
+            "IntegerLiteral" => {
+                let value = match node.get("value") {
+                    Some(Value::Number(value)) => i32::try_from(*value)
+                        .map_err(|_| format!("integer {} is out of range", value))?,
+                    _ => return Err(field_error(node_type, "value", "an integer")),
+                };
+                let token = leaf(TokenType::IntegerLiteral, &value.to_string());
+                Expression::IntegerLiteral { token, value }
+            }
+            "BooleanLiteral" => {
+                let value = match node.get("value") {
+                    Some(Value::Bool(value)) => *value,
+                    _ => return Err(field_error(node_type, "value", "a boolean")),
+                };
+                let token = leaf(TokenType::BooleanLiteral, &value.to_string());
+                Expression::BooleanLiteral { token, value }
+            }
+            "StringLiteral" => {
+                let token = leaf(TokenType::StringLiteral, string_field(node, "value")?);
+                let value = Arc::clone(&token.lexeme);
+                Expression::StringLiteral { token, value }
+            }
+            "Variable" => {
+                let name = string_field(node, "name")?;
+                let token = leaf(TokenType::Identifier, name);
+                let name = Symbol::intern(name);
+                Expression::Variable { token, name }
+            }
+            "Grouping" => Expression::Grouping {
+                token: Token::new(TokenType::LeftParen, "(", line),
+                expr: self.add_field(node, "expression")?,
+            },
+            "Assignment" => Expression::Assignment {
+                token: Token::new(TokenType::Assignment, "=", line),
+                name: Symbol::intern(string_field(node, "name")?),
+                value: self.add_field(node, "value")?,
+            },
+            "Error" => {
+                let source = string_field(node, "source")?;
+                Expression::Error {
+                    token: leaf(TokenType::InvalidToken, source),
+                    source: source.to_string(),
+                }
+            }
+            "Call" => {
+                let name = string_field(node, "name")?;
+                let arguments = match node.get("arguments") {
+                    Some(Value::Array(arguments)) => arguments
+                        .iter()
+                        .map(|argument| self.add_value(argument))
+                        .collect::<Result<_, _>>()?,
+                    _ => return Err(field_error(node_type, "arguments", "an array")),
+                };
+                Expression::Call {
+                    token: Token::new(TokenType::Identifier, name, line),
+                    name: Symbol::intern(name),
+                    arguments,
+                }
+            }
+            "Tuple" => {
+                let elements = match node.get("elements") {
+                    Some(Value::Array(elements)) => elements
+                        .iter()
+                        .map(|element| self.add_value(element))
+                        .collect::<Result<_, _>>()?,
+                    _ => return Err(field_error(node_type, "elements", "an array")),
+                };
+                Expression::Tuple {
+                    token: Token::new(TokenType::LeftParen, "(", line),
+                    elements,
+                }
+            }
+            "MultipleAssignment" => {
+                let names = match node.get("names") {
+                    Some(Value::Array(names)) => names
+                        .iter()
+                        .map(|name| match name {
+                            Value::String(name) => Ok(Symbol::intern(name)),
+                            _ => Err(field_error(node_type, "names", "an array of strings")),
+                        })
+                        .collect::<Result<_, _>>()?,
+                    _ => return Err(field_error(node_type, "names", "an array of strings")),
+                };
+                Expression::MultipleAssignment {
+                    token: Token::new(TokenType::Assignment, "=", line),
+                    names,
+                    value: self.add_field(node, "value")?,
+                }
+            }
+            "Index" => Expression::Index {
+                token: Token::new(TokenType::LeftBracket, "[", line),
+                target: self.add_field(node, "target")?,
+                index: self.add_field(node, "index")?,
+            },
+            "Slice" => Expression::Slice {
+                token: Token::new(TokenType::LeftBracket, "[", line),
+                target: self.add_field(node, "target")?,
+                start: self.add_optional_field(node, "start")?,
+                end: self.add_optional_field(node, "end")?,
+            },
+            "TypeTest" => Expression::TypeTest {
+                token: Token::new(TokenType::Is, "is", line),
+                value: self.add_field(node, "value")?,
+                type_name: match node.get("type_name") {
+                    Some(Value::String(name)) => Symbol::intern(name),
+                    _ => return Err(field_error(node_type, "type_name", "a string")),
+                },
+            },
+            "Unary" => Expression::UnaryExpression {
+                token: operator_token(node, UNARY_OPERATORS.iter(), line)?,
+                right: self.add_field(node, "right")?,
+            },
+            "Binary" => Expression::BinaryExpression {
+                token: operator_token(
+                    node,
+                    BINARY_OPERATORS.iter().map(|(operator, _)| operator),
+                    line,
+                )?,
+                left: self.add_field(node, "left")?,
+                right: self.add_field(node, "right")?,
+            },
+            _ => return Err(format!("unknown node type \"{}\"", node_type)),
+        };
+        Ok(match span {
+            Some(span) => self.add_with_span(expression, span),
+            None => self.add(expression),
+        })
+    }
+
+    fn add_field(&mut self, node: &Value, field: &str) -> Result<ExprId, String> {
+        match node.get(field) {
+            Some(child) => self.add_value(child),
+            None => Err(format!("missing \"{}\"", field)),
+        }
+    }
+
+    /// A field that is missing or null if there is no such subexpression
+    fn add_optional_field(&mut self, node: &Value, field: &str) -> Result<Option<ExprId>, String> {
+        match node.get(field) {
+            Some(Value::Null) | None => Ok(None),
+            Some(child) => self.add_value(child).map(Some),
+        }
+    }
+}
+
+fn operator_token<'a>(
+    node: &Value,
+    mut operators: impl Iterator<Item = &'a TokenType>,
+    line: i32,
+) -> Result<Token, String> {
+    let symbol = string_field(node, "operator")?;
+    operators
+        .find(|operator| operator.symbol() == Some(symbol))
+        .map(|operator| Token::new(operator.clone(), symbol, line))
+        .ok_or_else(|| format!("unknown operator \"{}\"", symbol))
+}
+
+fn string_field<'a>(node: &'a Value, field: &str) -> Result<&'a str, String> {
+    match node.get(field) {
+        Some(Value::String(value)) => Ok(value),
+        _ => Err(format!("\"{}\" must be a string", field)),
+    }
+}
+
+fn span_field(span: &Value) -> Result<Span, String> {
+    let offset = |field| match span.get(field) {
+        Some(Value::Number(offset)) => usize::try_from(*offset).ok(),
+        _ => None,
+    };
+    match (offset("start"), offset("end")) {
+        (Some(start), Some(end)) => Ok(Span::new(start, end)),
+        _ => Err(String::from(
+            "\"span\" must have a \"start\" and an \"end\" offset",
+        )),
+    }
+}
+
+fn field_error(node_type: &str, field: &str, expected: &str) -> String {
+    format!("\"{}\" of {} must be {}", field, node_type, expected)
+}
+
+impl Index<ExprId> for Ast {
+    type Output = Expression;
+
+    fn index(&self, id: ExprId) -> &Expression {
+        &self.expressions[id.index()]
+    }
+}
+
+/// A program is a sequence of expressions separated by ';'
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Program {
+    pub ast: Ast,
+    /// The top-level expressions, in order
+    pub statements: Vec<ExprId>,
+}
+
+impl Program {
+    pub fn len(&self) -> usize {
+        self.statements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.statements.is_empty()
+    }
+
+    /// The program as a JSON array with the tree of each statement, see print_ast_json
+    pub fn to_json(&self) -> String {
+        let statements: Vec<String> = self
+            .statements
+            .iter()
+            .map(|&statement| print_ast_json(&self.ast, statement))
+            .collect();
+        format!("[{}]", statements.join(","))
+    }
+
+    /// Reads a program written by to_json, e.g. by a tool that transforms it
+    pub fn from_json(json: &str) -> Result<Program, String> {
+        let statements = match json::parse(json)? {
+            Value::Array(statements) => statements,
+            _ => return Err(String::from("expected an array of expressions")),
+        };
+        let mut program = Program::default();
+        for statement in &statements {
+            let id = program.ast.add_value(statement)?;
+            program.statements.push(id);
+        }
+        Ok(program)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub enum Expression {
     BinaryExpression {
         token: Token,
-        left: Box<Expression>,
-        right: Box<Expression>,
+        left: ExprId,
+        right: ExprId,
     },
     UnaryExpression {
         token: Token,
-        right: Box<Expression>,
+        right: ExprId,
     },
     IntegerLiteral {
         token: Token,
@@ -21,10 +477,246 @@ pub enum Expression {
     },
     StringLiteral {
         token: Token,
-        value: String,
+        value: Arc<str>,
     },
     Grouping {
         token: Token,
-        expr: Box<Expression>,
+        expr: ExprId,
+    },
+    Variable {
+        token: Token,
+        name: Symbol,
+    },
+    Assignment {
+        token: Token,
+        name: Symbol,
+        value: ExprId,
+    },
+    Call {
+        token: Token,
+        name: Symbol,
+        arguments: Vec<ExprId>,
+    },
+    /// (a, b) or a, b, token is the '(' or the first ','
+    Tuple {
+        token: Token,
+        elements: Vec<ExprId>,
     },
+    /// a, b = value, which assigns the values of a tuple or array to the names in order
+    MultipleAssignment {
+        token: Token,
+        names: Vec<Symbol>,
+        value: ExprId,
+    },
+    /// target[index], token is the '['
+    Index {
+        token: Token,
+        target: ExprId,
+        index: ExprId,
+    },
+    /// target[start:end], either bound can be left out
+    Slice {
+        token: Token,
+        target: ExprId,
+        start: Option<ExprId>,
+        end: Option<ExprId>,
+    },
+    /// value is Type, token is the 'is'
+    TypeTest {
+        token: Token,
+        value: ExprId,
+        type_name: Symbol,
+    },
+    /// A statement that couldn't be parsed, only found in programs parsed with
+    /// parse_program_with_recovery. token is where the error was found.
+    Error {
+        token: Token,
+        /// The text of the statement, without the ';' ending it
+        source: String,
+    },
+}
+
+impl Expression {
+    /// The token the expression was parsed from, e.g. the operator of a binary expression
+    pub fn token(&self) -> &Token {
+        match self {
+            Expression::BinaryExpression { token, .. }
+            | Expression::UnaryExpression { token, .. }
+            | Expression::IntegerLiteral { token, .. }
+            | Expression::BooleanLiteral { token, .. }
+            | Expression::StringLiteral { token, .. }
+            | Expression::Grouping { token, .. }
+            | Expression::Variable { token, .. }
+            | Expression::Assignment { token, .. }
+            | Expression::Call { token, .. }
+            | Expression::Tuple { token, .. }
+            | Expression::MultipleAssignment { token, .. }
+            | Expression::Index { token, .. }
+            | Expression::Slice { token, .. }
+            | Expression::TypeTest { token, .. }
+            | Expression::Error { token, .. } => token,
+        }
+    }
+
+    pub(crate) fn token_mut(&mut self) -> &mut Token {
+        match self {
+            Expression::BinaryExpression { token, .. }
+            | Expression::UnaryExpression { token, .. }
+            | Expression::IntegerLiteral { token, .. }
+            | Expression::BooleanLiteral { token, .. }
+            | Expression::StringLiteral { token, .. }
+            | Expression::Grouping { token, .. }
+            | Expression::Variable { token, .. }
+            | Expression::Assignment { token, .. }
+            | Expression::Call { token, .. }
+            | Expression::Tuple { token, .. }
+            | Expression::MultipleAssignment { token, .. }
+            | Expression::Index { token, .. }
+            | Expression::Slice { token, .. }
+            | Expression::TypeTest { token, .. }
+            | Expression::Error { token, .. } => token,
+        }
+    }
+
+    /// The expression with each subexpression replaced by what f returns for it,
+    /// f is called in evaluation order
+    pub(crate) fn map_children(mut self, mut f: impl FnMut(ExprId) -> ExprId) -> Expression {
+        match &mut self {
+            Expression::BinaryExpression { left, right, .. } => {
+                *left = f(*left);
+                *right = f(*right);
+            }
+            Expression::UnaryExpression { right, .. } => *right = f(*right),
+            Expression::Grouping { expr, .. } => *expr = f(*expr),
+            Expression::Assignment { value, .. } => *value = f(*value),
+            Expression::Call {
+                arguments: elements,
+                ..
+            }
+            | Expression::Tuple { elements, .. } => {
+                for element in elements {
+                    *element = f(*element);
+                }
+            }
+            Expression::MultipleAssignment { value, .. } | Expression::TypeTest { value, .. } => {
+                *value = f(*value)
+            }
+            Expression::Index { target, index, .. } => {
+                *target = f(*target);
+                *index = f(*index);
+            }
+            Expression::Slice {
+                target, start, end, ..
+            } => {
+                *target = f(*target);
+                *start = start.map(&mut f);
+                *end = end.map(&mut f);
+            }
+            Expression::IntegerLiteral { .. }
+            | Expression::BooleanLiteral { .. }
+            | Expression::StringLiteral { .. }
+            | Expression::Variable { .. }
+            | Expression::Error { .. } => {}
+        }
+        self
+    }
+
+    /// The direct subexpressions, in evaluation order
+    pub fn children(&self) -> Vec<ExprId> {
+        match self {
+            Expression::BinaryExpression { left, right, .. } => vec![*left, *right],
+            Expression::UnaryExpression { right, .. } => vec![*right],
+            Expression::Grouping { expr, .. } => vec![*expr],
+            Expression::Assignment { value, .. } => vec![*value],
+            Expression::Call { arguments, .. } => arguments.clone(),
+            Expression::Tuple { elements, .. } => elements.clone(),
+            Expression::MultipleAssignment { value, .. } | Expression::TypeTest { value, .. } => {
+                vec![*value]
+            }
+            Expression::Index { target, index, .. } => vec![*target, *index],
+            Expression::Slice {
+                target, start, end, ..
+            } => {
+                let bounds = start.iter().chain(end.iter()).copied();
+                std::iter::once(*target).chain(bounds).collect()
+            }
+            Expression::IntegerLiteral { .. }
+            | Expression::BooleanLiteral { .. }
+            | Expression::StringLiteral { .. }
+            | Expression::Variable { .. }
+            | Expression::Error { .. } => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_program, print_ast, unparse};
+
+    #[test]
+    fn construct_expressions() {
+        let mut ast = Ast::new();
+        let one = ast.integer(1);
+        let x = ast.variable("x");
+        let sum = ast.binary(TokenType::Plus, one, x);
+        let group = ast.grouping(sum);
+        let negated = ast.unary(TokenType::Minus, group);
+        let text = ast.string("sapo");
+        let call = ast.call("f", vec![negated, text]);
+        let assignment = ast.assignment("y", call);
+        assert_eq!(unparse(&ast, assignment), "y = f(-(1 + x), \"sapo\")");
+        assert_eq!(ast[sum].token().lexeme.as_ref(), "+");
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let source = "x = -f(1, \"ñ \\n\");\n(x + 2) * 3 >= 4 == !true;\ny[1:][-1] is String";
+        let program = parse_program(source).unwrap();
+        let json = program.to_json();
+        let read = Program::from_json(&json).unwrap();
+        assert_eq!(read.to_json(), json);
+        let trees = |program: &Program| -> Vec<String> {
+            program
+                .statements
+                .iter()
+                .map(|&statement| print_ast(&program.ast, statement))
+                .collect()
+        };
+        assert_eq!(trees(&read), trees(&program));
+    }
+
+    #[test]
+    fn read_json_without_positions() {
+        let mut ast = Ast::new();
+        let id = ast
+            .add_json(concat!(
+                r#"{"type":"Binary","operator":"*","left":{"type":"Variable","name":"x"},"#,
+                r#""right":{"type":"Grouping","expression":{"type":"Call","name":"f","arguments":[]}}}"#
+            ))
+            .unwrap();
+        assert_eq!(print_ast(&ast, id), "(* (Var x) (Group (Call f)))");
+        assert_eq!(ast[id].token().line, -1);
+    }
+
+    #[test]
+    fn read_invalid_json() {
+        let errors = [
+            ("{}", "expected an array of expressions"),
+            ("[1]", "expected an object with a \"type\""),
+            (r#"[{"type":"Loop"}]"#, "unknown node type \"Loop\""),
+            (
+                r#"[{"type":"IntegerLiteral","value":4294967296}]"#,
+                "integer 4294967296 is out of range",
+            ),
+            (
+                r#"[{"type":"Unary","operator":"+","right":{"type":"BooleanLiteral","value":true}}]"#,
+                "unknown operator \"+\"",
+            ),
+            (r#"[{"type":"Grouping"}]"#, "missing \"expression\""),
+        ];
+        for (json, error) in &errors {
+            assert_eq!(Program::from_json(json), Err(error.to_string()));
+        }
+    }
 }
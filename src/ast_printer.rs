@@ -1,13 +1,16 @@
-use super::ast::Expression;
+use super::ast::{Ast, ExprId, Expression};
+use crate::json;
+use crate::parsing::{precedence, TYPE_TEST_PRECEDENCE, UNARY_PRECEDENCE};
 
-pub fn print_ast(ast: Box<Expression>) -> String {
+/// Prints the expression id of ast as an S-expression, e.g. (+ (IntLit 1) (Var x))
+pub fn print_ast(ast: &Ast, id: ExprId) -> String {
     let mut buf = String::new();
-    print_expression(&*ast, &mut buf);
+    print_expression(ast, id, &mut buf);
     buf
 }
 
-fn print_expression(ast: &Expression, buf: &mut String) {
-    match &*ast {
+fn print_expression(ast: &Ast, id: ExprId, buf: &mut String) {
+    match &ast[id] {
         Expression::IntegerLiteral { token: _, value } => {
             buf.push_str(&format!("(IntLit {})", value))
         }
@@ -19,20 +22,637 @@ fn print_expression(ast: &Expression, buf: &mut String) {
         }
         Expression::Grouping { token: _, expr } => {
             buf.push_str("(Group ");
-            print_expression(&*expr, buf);
-            buf.push_str(")");
+            print_expression(ast, *expr, buf);
+            buf.push(')');
+        }
+        Expression::Variable { token: _, name } => buf.push_str(&format!("(Var {})", name)),
+        Expression::Assignment {
+            token: _,
+            name,
+            value,
+        } => {
+            buf.push_str(&format!("(= {} ", name));
+            print_expression(ast, *value, buf);
+            buf.push(')');
+        }
+        Expression::Call {
+            token: _,
+            name,
+            arguments,
+        } => {
+            buf.push_str(&format!("(Call {}", name));
+            for argument in arguments {
+                buf.push(' ');
+                print_expression(ast, *argument, buf);
+            }
+            buf.push(')');
         }
         Expression::UnaryExpression { token, right } => {
             buf.push_str(&format!("({} ", token.lexeme));
-            print_expression(&*right, buf);
-            buf.push_str(")");
+            print_expression(ast, *right, buf);
+            buf.push(')');
         }
         Expression::BinaryExpression { token, right, left } => {
             buf.push_str(&format!("({} ", token.lexeme));
-            print_expression(&*left, buf);
-            buf.push_str(" ");
-            print_expression(&*right, buf);
-            buf.push_str(")");
+            print_expression(ast, *left, buf);
+            buf.push(' ');
+            print_expression(ast, *right, buf);
+            buf.push(')');
+        }
+        Expression::Tuple { token: _, elements } => {
+            buf.push_str("(Tuple");
+            for element in elements {
+                buf.push(' ');
+                print_expression(ast, *element, buf);
+            }
+            buf.push(')');
+        }
+        Expression::MultipleAssignment {
+            token: _,
+            names,
+            value,
+        } => {
+            let names: Vec<&str> = names.iter().map(|n| n.as_str()).collect();
+            buf.push_str(&format!("(= ({}) ", names.join(" ")));
+            print_expression(ast, *value, buf);
+            buf.push(')');
+        }
+        Expression::Index {
+            token: _,
+            target,
+            index,
+        } => {
+            buf.push_str("(Index ");
+            print_expression(ast, *target, buf);
+            buf.push(' ');
+            print_expression(ast, *index, buf);
+            buf.push(')');
+        }
+        // a missing bound is printed as _
+        Expression::Slice {
+            token: _,
+            target,
+            start,
+            end,
+        } => {
+            buf.push_str("(Slice ");
+            print_expression(ast, *target, buf);
+            for bound in &[start, end] {
+                buf.push(' ');
+                match bound {
+                    Some(bound) => print_expression(ast, *bound, buf),
+                    None => buf.push('_'),
+                }
+            }
+            buf.push(')');
+        }
+        Expression::TypeTest {
+            token: _,
+            value,
+            type_name,
+        } => {
+            buf.push_str("(is ");
+            print_expression(ast, *value, buf);
+            buf.push_str(&format!(" {})", type_name));
+        }
+        Expression::Error { token: _, source } => buf.push_str(&format!("(Error {})", source)),
+    }
+}
+
+/// Prints the expression as sapo source, with parentheses only where the precedence
+/// of the operators requires them, e.g. ((1 * 2)) + (3 * 4) becomes 1 * 2 + 3 * 4.
+/// Unlike unparse the groupings of the tree are not kept, only its structure.
+pub fn to_source(ast: &Ast, id: ExprId) -> String {
+    source_operand(ast, id, 0)
+}
+
+/// Precedence of literals, variables and calls, which never need parentheses
+const ATOM_PRECEDENCE: u8 = 6;
+
+/// The expression as an operand that must bind at least as tight as min_precedence
+fn source_operand(ast: &Ast, id: ExprId, min_precedence: u8) -> String {
+    let (source, precedence) = source_with_precedence(ast, id);
+    if precedence < min_precedence {
+        format!("({})", source)
+    } else {
+        source
+    }
+}
+
+/// The source of the expression and how tightly it binds, assignments bind the loosest
+fn source_with_precedence(ast: &Ast, id: ExprId) -> (String, u8) {
+    match &ast[id] {
+        Expression::IntegerLiteral { token: _, value } => (value.to_string(), ATOM_PRECEDENCE),
+        Expression::BooleanLiteral { token: _, value } => (value.to_string(), ATOM_PRECEDENCE),
+        Expression::StringLiteral { token: _, value } => {
+            (format!("\"{}\"", value), ATOM_PRECEDENCE)
+        }
+        Expression::Grouping { token: _, expr } => source_with_precedence(ast, *expr),
+        Expression::Variable { token: _, name } => (name.to_string(), ATOM_PRECEDENCE),
+        Expression::Assignment {
+            token: _,
+            name,
+            value,
+        } => (format!("{} = {}", name, source_operand(ast, *value, 0)), 0),
+        Expression::Call {
+            token: _,
+            name,
+            arguments,
+        } => {
+            let arguments: Vec<String> = arguments
+                .iter()
+                .map(|&a| source_operand(ast, a, 0))
+                .collect();
+            (
+                format!("{}({})", name, arguments.join(", ")),
+                ATOM_PRECEDENCE,
+            )
+        }
+        Expression::UnaryExpression { token, right } => {
+            let right = source_operand(ast, *right, UNARY_PRECEDENCE);
+            (format!("{}{}", token.lexeme, right), UNARY_PRECEDENCE)
+        }
+        // binary operators are left associative, so a right operand of the same
+        // precedence needs parentheses, e.g. 1 - (2 - 3)
+        Expression::BinaryExpression { token, left, right } => {
+            // operators the parser doesn't know are put in parentheses
+            let precedence = precedence(&token.token_type).unwrap_or(0);
+            let left = source_operand(ast, *left, precedence);
+            let right = source_operand(ast, *right, precedence + 1);
+            (format!("{} {} {}", left, token.lexeme, right), precedence)
+        }
+        // always in parentheses, so that it can be an operand
+        Expression::Tuple { token: _, elements } => {
+            let elements: Vec<String> = elements
+                .iter()
+                .map(|&e| source_operand(ast, e, 0))
+                .collect();
+            let source = match elements.len() {
+                1 => format!("({},)", elements[0]),
+                _ => format!("({})", elements.join(", ")),
+            };
+            (source, ATOM_PRECEDENCE)
+        }
+        Expression::MultipleAssignment {
+            token: _,
+            names,
+            value,
+        } => {
+            let names: Vec<&str> = names.iter().map(|n| n.as_str()).collect();
+            let value = source_operand(ast, *value, 0);
+            (format!("{} = {}", names.join(", "), value), 0)
+        }
+        Expression::Index {
+            token: _,
+            target,
+            index,
+        } => {
+            let target = source_operand(ast, *target, ATOM_PRECEDENCE);
+            let index = source_operand(ast, *index, 0);
+            (format!("{}[{}]", target, index), ATOM_PRECEDENCE)
+        }
+        Expression::Slice {
+            token: _,
+            target,
+            start,
+            end,
+        } => {
+            let target = source_operand(ast, *target, ATOM_PRECEDENCE);
+            let bound = |bound: &Option<ExprId>| match bound {
+                Some(bound) => source_operand(ast, *bound, 0),
+                None => String::new(),
+            };
+            let source = format!("{}[{}:{}]", target, bound(start), bound(end));
+            (source, ATOM_PRECEDENCE)
+        }
+        Expression::TypeTest {
+            token: _,
+            value,
+            type_name,
+        } => {
+            let value = source_operand(ast, *value, TYPE_TEST_PRECEDENCE);
+            (format!("{} is {}", value, type_name), TYPE_TEST_PRECEDENCE)
+        }
+        Expression::Error { token: _, source } => (source.clone(), 0),
+    }
+}
+
+/// Prints the expression as a JSON object, every node has a "type" and the "line" of its token
+/// e.g. {"type":"Unary","operator":"-","right":{"type":"IntegerLiteral","value":1,"line":1},"line":1}
+pub fn print_ast_json(ast: &Ast, id: ExprId) -> String {
+    let (node_type, token, fields) = match &ast[id] {
+        Expression::IntegerLiteral { token, value } => {
+            ("IntegerLiteral", token, format!("\"value\":{}", value))
+        }
+        Expression::BooleanLiteral { token, value } => {
+            ("BooleanLiteral", token, format!("\"value\":{}", value))
+        }
+        Expression::StringLiteral { token, value } => (
+            "StringLiteral",
+            token,
+            format!("\"value\":{}", json::quote(value)),
+        ),
+        Expression::Grouping { token, expr } => (
+            "Grouping",
+            token,
+            format!("\"expression\":{}", print_ast_json(ast, *expr)),
+        ),
+        Expression::Variable { token, name } => (
+            "Variable",
+            token,
+            format!("\"name\":{}", json::quote(name.as_str())),
+        ),
+        Expression::Assignment { token, name, value } => (
+            "Assignment",
+            token,
+            format!(
+                "\"name\":{},\"value\":{}",
+                json::quote(name.as_str()),
+                print_ast_json(ast, *value)
+            ),
+        ),
+        Expression::Call {
+            token,
+            name,
+            arguments,
+        } => {
+            let arguments: Vec<String> =
+                arguments.iter().map(|&a| print_ast_json(ast, a)).collect();
+            (
+                "Call",
+                token,
+                format!(
+                    "\"name\":{},\"arguments\":[{}]",
+                    json::quote(name.as_str()),
+                    arguments.join(",")
+                ),
+            )
+        }
+        Expression::UnaryExpression { token, right } => (
+            "Unary",
+            token,
+            format!(
+                "\"operator\":{},\"right\":{}",
+                json::quote(&token.lexeme),
+                print_ast_json(ast, *right)
+            ),
+        ),
+        Expression::BinaryExpression { token, left, right } => (
+            "Binary",
+            token,
+            format!(
+                "\"operator\":{},\"left\":{},\"right\":{}",
+                json::quote(&token.lexeme),
+                print_ast_json(ast, *left),
+                print_ast_json(ast, *right)
+            ),
+        ),
+        Expression::Tuple { token, elements } => {
+            let elements: Vec<String> = elements.iter().map(|&e| print_ast_json(ast, e)).collect();
+            (
+                "Tuple",
+                token,
+                format!("\"elements\":[{}]", elements.join(",")),
+            )
+        }
+        Expression::MultipleAssignment {
+            token,
+            names,
+            value,
+        } => {
+            let names: Vec<String> = names.iter().map(|n| json::quote(n.as_str())).collect();
+            (
+                "MultipleAssignment",
+                token,
+                format!(
+                    "\"names\":[{}],\"value\":{}",
+                    names.join(","),
+                    print_ast_json(ast, *value)
+                ),
+            )
+        }
+        Expression::Index {
+            token,
+            target,
+            index,
+        } => (
+            "Index",
+            token,
+            format!(
+                "\"target\":{},\"index\":{}",
+                print_ast_json(ast, *target),
+                print_ast_json(ast, *index)
+            ),
+        ),
+        // missing bounds are null
+        Expression::Slice {
+            token,
+            target,
+            start,
+            end,
+        } => {
+            let bound = |bound: &Option<ExprId>| match bound {
+                Some(bound) => print_ast_json(ast, *bound),
+                None => String::from("null"),
+            };
+            (
+                "Slice",
+                token,
+                format!(
+                    "\"target\":{},\"start\":{},\"end\":{}",
+                    print_ast_json(ast, *target),
+                    bound(start),
+                    bound(end)
+                ),
+            )
+        }
+        Expression::TypeTest {
+            token,
+            value,
+            type_name,
+        } => (
+            "TypeTest",
+            token,
+            format!(
+                "\"value\":{},\"type_name\":{}",
+                print_ast_json(ast, *value),
+                json::quote(type_name.as_str())
+            ),
+        ),
+        Expression::Error { token, source } => (
+            "Error",
+            token,
+            format!("\"source\":{}", json::quote(source)),
+        ),
+    };
+    let span = ast.span(id);
+    format!(
+        "{{\"type\":\"{}\",{},\"line\":{},\"span\":{{\"start\":{},\"end\":{}}}}}",
+        node_type, fields, token.line, span.start, span.end
+    )
+}
+
+/// Prints the expression as an indented tree, one node per line
+pub fn print_ast_tree(ast: &Ast, id: ExprId) -> String {
+    let mut buf = String::new();
+    print_tree_node(ast, id, "", "", false, &mut buf);
+    buf
+}
+
+/// Prints the expression as an indented tree like print_ast_tree, with the line
+/// of the token of each node after it, e.g. "IntLit 1  [line 2]"
+pub fn print_ast_tree_with_lines(ast: &Ast, id: ExprId) -> String {
+    let mut buf = String::new();
+    print_tree_node(ast, id, "", "", true, &mut buf);
+    buf
+}
+
+/// The text a node is shown with in trees and graphs, and its children
+fn node_label(ast: &Ast, id: ExprId) -> (String, Vec<ExprId>) {
+    match &ast[id] {
+        Expression::IntegerLiteral { token: _, value } => (format!("IntLit {}", value), vec![]),
+        Expression::BooleanLiteral { token: _, value } => (format!("BoolLit {}", value), vec![]),
+        Expression::StringLiteral { token: _, value } => (format!("StrLit {}", value), vec![]),
+        Expression::Grouping { token: _, expr } => (String::from("Group"), vec![*expr]),
+        Expression::Variable { token: _, name } => (format!("Var {}", name), vec![]),
+        Expression::Assignment {
+            token: _,
+            name,
+            value,
+        } => (format!("= {}", name), vec![*value]),
+        Expression::Call {
+            token: _,
+            name,
+            arguments,
+        } => (format!("Call {}", name), arguments.clone()),
+        Expression::UnaryExpression { token, right } => (token.lexeme.to_string(), vec![*right]),
+        Expression::BinaryExpression { token, left, right } => {
+            (token.lexeme.to_string(), vec![*left, *right])
+        }
+        Expression::Tuple { token: _, elements } => (String::from("Tuple"), elements.clone()),
+        Expression::MultipleAssignment {
+            token: _,
+            names,
+            value,
+        } => {
+            let names: Vec<&str> = names.iter().map(|n| n.as_str()).collect();
+            (format!("= {}", names.join(", ")), vec![*value])
+        }
+        Expression::Index { .. } => (String::from("Index"), ast[id].children()),
+        Expression::Slice { .. } => (String::from("Slice"), ast[id].children()),
+        Expression::TypeTest {
+            token: _,
+            value,
+            type_name,
+        } => (format!("is {}", type_name), vec![*value]),
+        Expression::Error { token: _, source } => (format!("Error {}", source), vec![]),
+    }
+}
+
+/// prefix is written before the node, child_prefix before each of its children
+/// lines adds the line of the token of the node, unless it wasn't parsed from a source
+fn print_tree_node(
+    ast: &Ast,
+    id: ExprId,
+    prefix: &str,
+    child_prefix: &str,
+    lines: bool,
+    buf: &mut String,
+) {
+    let (label, children) = node_label(ast, id);
+    buf.push_str(prefix);
+    buf.push_str(&label);
+    let line = ast[id].token().line;
+    if lines && line > 0 {
+        buf.push_str(&format!("  [line {}]", line));
+    }
+    buf.push('\n');
+    for (i, child) in children.iter().enumerate() {
+        let (branch, indent) = if i + 1 == children.len() {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        let prefix = format!("{}{}", child_prefix, branch);
+        let child_prefix = format!("{}{}", child_prefix, indent);
+        print_tree_node(ast, *child, &prefix, &child_prefix, lines, buf);
+    }
+}
+
+/// Prints the expression as a Graphviz graph with an edge from each node to its children,
+/// render it with e.g. `dot -Tsvg`
+pub fn print_dot(ast: &Ast, id: ExprId) -> String {
+    let mut buf = String::from("digraph ast {\n");
+    print_graph_node(ast, id, Graph::Dot, &mut buf);
+    buf.push_str("}\n");
+    buf
+}
+
+/// Prints the expression as a Mermaid flowchart, which e.g. GitHub renders in markdown
+pub fn print_mermaid(ast: &Ast, id: ExprId) -> String {
+    let mut buf = String::from("graph TD\n");
+    print_graph_node(ast, id, Graph::Mermaid, &mut buf);
+    buf
+}
+
+#[derive(Clone, Copy)]
+enum Graph {
+    Dot,
+    Mermaid,
+}
+
+/// Writes the node, the edges to its children and then the children,
+/// nodes are named after their ExprId
+fn print_graph_node(ast: &Ast, id: ExprId, graph: Graph, buf: &mut String) {
+    let (label, children) = node_label(ast, id);
+    match graph {
+        Graph::Dot => {
+            let label = label
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            buf.push_str(&format!("    n{} [label=\"{}\"];\n", id.index(), label));
+        }
+        Graph::Mermaid => {
+            let label = label.replace('"', "#quot;").replace('\n', "<br>");
+            buf.push_str(&format!("    n{}[\"{}\"]\n", id.index(), label));
+        }
+    }
+    for child in &children {
+        match graph {
+            Graph::Dot => buf.push_str(&format!("    n{} -> n{};\n", id.index(), child.index())),
+            Graph::Mermaid => {
+                buf.push_str(&format!("    n{} --> n{}\n", id.index(), child.index()))
+            }
+        }
+    }
+    for child in children {
+        print_graph_node(ast, child, graph, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use crate::token::TokenType;
+
+    #[test]
+    fn print_json() {
+        let (ast, root) = parse("x = -f(1, \"a\")").unwrap();
+        assert_eq!(
+            print_ast_json(&ast, root),
+            concat!(
+                r#"{"type":"Assignment","name":"x","value":{"type":"Unary","operator":"-","right":"#,
+                r#"{"type":"Call","name":"f","arguments":["#,
+                r#"{"type":"IntegerLiteral","value":1,"line":1,"span":{"start":7,"end":8}},"#,
+                r#"{"type":"StringLiteral","value":"a","line":1,"span":{"start":10,"end":13}}],"#,
+                r#""line":1,"span":{"start":5,"end":14}},"#,
+                r#""line":1,"span":{"start":4,"end":14}},"#,
+                r#""line":1,"span":{"start":0,"end":14}}"#
+            )
+        );
+    }
+
+    #[test]
+    fn print_tree() {
+        let (ast, root) = parse("(1 + 2) * -3 == x").unwrap();
+        assert_eq!(
+            print_ast_tree(&ast, root),
+            concat!(
+                "==\n",
+                "├── *\n",
+                "│   ├── Group\n",
+                "│   │   └── +\n",
+                "│   │       ├── IntLit 1\n",
+                "│   │       └── IntLit 2\n",
+                "│   └── -\n",
+                "│       └── IntLit 3\n",
+                "└── Var x\n",
+            )
+        );
+    }
+
+    #[test]
+    fn print_graphs() {
+        let mut ast = Ast::new();
+        let text = ast.string("\"a\"");
+        let call = ast.call("f", vec![text]);
+        let negated = ast.unary(TokenType::Minus, call);
+        let root = ast.assignment("x", negated);
+        assert_eq!(
+            print_dot(&ast, root),
+            concat!(
+                "digraph ast {\n",
+                "    n3 [label=\"= x\"];\n",
+                "    n3 -> n2;\n",
+                "    n2 [label=\"-\"];\n",
+                "    n2 -> n1;\n",
+                "    n1 [label=\"Call f\"];\n",
+                "    n1 -> n0;\n",
+                "    n0 [label=\"StrLit \\\"a\\\"\"];\n",
+                "}\n"
+            )
+        );
+        assert_eq!(
+            print_mermaid(&ast, root),
+            concat!(
+                "graph TD\n",
+                "    n3[\"= x\"]\n",
+                "    n3 --> n2\n",
+                "    n2[\"-\"]\n",
+                "    n2 --> n1\n",
+                "    n1[\"Call f\"]\n",
+                "    n1 --> n0\n",
+                "    n0[\"StrLit #quot;a#quot;\"]\n",
+            )
+        );
+    }
+
+    #[test]
+    fn print_tree_with_lines() {
+        let program = crate::parse_program("x = 1;\nf(x,\n  2)").unwrap();
+        let trees: Vec<String> = program
+            .statements
+            .iter()
+            .map(|&statement| print_ast_tree_with_lines(&program.ast, statement))
+            .collect();
+        assert_eq!(
+            trees.concat(),
+            concat!(
+                "= x  [line 1]\n",
+                "└── IntLit 1  [line 1]\n",
+                "Call f  [line 2]\n",
+                "├── Var x  [line 2]\n",
+                "└── IntLit 2  [line 3]\n",
+            )
+        );
+        let mut ast = Ast::new();
+        let one = ast.integer(1);
+        assert_eq!(print_ast_tree_with_lines(&ast, one), "IntLit 1\n");
+    }
+
+    #[test]
+    fn print_source() {
+        let cases = [
+            ("((1 * 2)) + (3 * 4)", "1 * 2 + 3 * 4"),
+            ("(1 + 2) * (3 * 4)", "(1 + 2) * (3 * 4)"),
+            ("(1 - 2) - 3", "1 - 2 - 3"),
+            ("1 - (2 - 3)", "1 - (2 - 3)"),
+            ("1 + 2 * 3 == (4 > 5)", "1 + 2 * 3 == 4 > 5"),
+            ("(true == false) != (1 < 2)", "true == false != 1 < 2"),
+            ("(1 == 2) > 3", "(1 == 2) > 3"),
+            ("-(x) * -(1 + 2)", "-x * -(1 + 2)"),
+            ("(x = 1) + f((2), (y = 3))", "(x = 1) + f(2, y = 3)"),
+            ("x = (y = (\"sapo\"))", "x = y = \"sapo\""),
+        ];
+        for (input, expected) in &cases {
+            let (ast, root) = parse(input).unwrap();
+            let source = to_source(&ast, root);
+            assert_eq!(source, *expected);
+            // the source parses to the same tree, except for the groupings
+            let (reparsed, reparsed_root) = parse(&source).unwrap();
+            assert_eq!(to_source(&reparsed, reparsed_root), *expected);
         }
     }
 }
@@ -0,0 +1,261 @@
+// C interface for embedding sapo in programs written in other languages,
+// declared in include/sapo.h. Strings are UTF-8 and NUL terminated, the ones
+// returned by sapo must be released with sapo_string_free.
+
+use crate::evaluation::{EvalError, EvalOptions};
+use crate::interpreter::{Interpreter, InterpreterError};
+use crate::parsing::parse_program;
+use std::any::Any;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::time::Duration;
+
+pub const SAPO_OK: c_int = 0;
+pub const SAPO_PARSE_ERROR: c_int = 1;
+pub const SAPO_EVAL_ERROR: c_int = 2;
+/// The program called exit()
+pub const SAPO_EXIT: c_int = 3;
+/// A pointer was null or a string was not valid UTF-8
+pub const SAPO_INVALID_ARGUMENT: c_int = 4;
+/// sapo panicked, the interpreter should not be used anymore
+pub const SAPO_PANIC: c_int = 5;
+
+/// Opaque handle to an interpreter, its variables persist between calls to sapo_eval
+pub struct SapoInterpreter {
+    interpreter: Interpreter,
+}
+
+/// Creates an interpreter, release it with sapo_interpreter_free
+#[no_mangle]
+pub extern "C" fn sapo_interpreter_new() -> *mut SapoInterpreter {
+    Box::into_raw(Box::new(SapoInterpreter {
+        interpreter: Interpreter::new(),
+    }))
+}
+
+/// Creates an interpreter whose evaluations stop with an error when they evaluate more
+/// than max_steps expressions, use about more than max_memory bytes or take longer than
+/// timeout_ms milliseconds. A limit of 0 means no limit.
+#[no_mangle]
+pub extern "C" fn sapo_interpreter_new_with_limits(
+    max_steps: u64,
+    max_memory: usize,
+    timeout_ms: u64,
+) -> *mut SapoInterpreter {
+    let options = EvalOptions {
+        max_steps: Some(max_steps).filter(|&steps| steps > 0),
+        max_memory: Some(max_memory).filter(|&memory| memory > 0),
+        timeout: Some(timeout_ms)
+            .filter(|&timeout| timeout > 0)
+            .map(Duration::from_millis),
+        ..EvalOptions::default()
+    };
+    Box::into_raw(Box::new(SapoInterpreter {
+        interpreter: Interpreter::with_options(options),
+    }))
+}
+
+/// # Safety
+///
+/// interpreter must be null or a pointer returned by sapo_interpreter_new that was not freed
+#[no_mangle]
+pub unsafe extern "C" fn sapo_interpreter_free(interpreter: *mut SapoInterpreter) {
+    if !interpreter.is_null() {
+        drop(Box::from_raw(interpreter));
+    }
+}
+
+/// Evaluates a program. On success *result is set to the value of its last expression,
+/// or to an empty string if the program is empty. On errors it is set to the message
+/// of the error, and if the program called exit() to the status code it passed.
+///
+/// # Safety
+///
+/// interpreter must be a live pointer returned by sapo_interpreter_new, source a NUL
+/// terminated string and result a valid pointer to write the returned string to
+#[no_mangle]
+pub unsafe extern "C" fn sapo_eval(
+    interpreter: *mut SapoInterpreter,
+    source: *const c_char,
+    result: *mut *mut c_char,
+) -> c_int {
+    if interpreter.is_null() || result.is_null() {
+        return SAPO_INVALID_ARGUMENT;
+    }
+    let source = match to_str(source) {
+        Some(source) => source,
+        None => return SAPO_INVALID_ARGUMENT,
+    };
+    let interpreter = &mut (*interpreter).interpreter;
+    let evaluation = panic::catch_unwind(AssertUnwindSafe(|| interpreter.eval_str(source)));
+    let (code, text) = match evaluation {
+        Ok(Ok(Some(value))) => (SAPO_OK, value.to_string()),
+        Ok(Ok(None)) => (SAPO_OK, String::new()),
+        Ok(Err(InterpreterError::Eval(EvalError::Exit(_, status)))) => {
+            (SAPO_EXIT, status.to_string())
+        }
+        Ok(Err(error @ InterpreterError::Parse(_))) => (SAPO_PARSE_ERROR, error.to_string()),
+        Ok(Err(error)) => (SAPO_EVAL_ERROR, error.to_string()),
+        Err(payload) => (SAPO_PANIC, panic_message(payload)),
+    };
+    *result = to_c_string(text);
+    code
+}
+
+/// Parses a program without evaluating it. On success *result is set to its syntax
+/// tree as a JSON array with one element per expression, on errors to the message
+/// of the error.
+///
+/// # Safety
+///
+/// source must be a NUL terminated string and result a valid pointer to write
+/// the returned string to
+#[no_mangle]
+pub unsafe extern "C" fn sapo_parse(source: *const c_char, result: *mut *mut c_char) -> c_int {
+    if result.is_null() {
+        return SAPO_INVALID_ARGUMENT;
+    }
+    let source = match to_str(source) {
+        Some(source) => source,
+        None => return SAPO_INVALID_ARGUMENT,
+    };
+    let (code, text) = match panic::catch_unwind(|| parse_program(source)) {
+        Ok(Ok(program)) => (SAPO_OK, program.to_json()),
+        Ok(Err(error)) => (SAPO_PARSE_ERROR, error.to_string()),
+        Err(payload) => (SAPO_PANIC, panic_message(payload)),
+    };
+    *result = to_c_string(text);
+    code
+}
+
+/// # Safety
+///
+/// string must be null or a string returned by sapo that was not freed
+#[no_mangle]
+pub unsafe extern "C" fn sapo_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+unsafe fn to_str<'a>(string: *const c_char) -> Option<&'a str> {
+    if string.is_null() {
+        return None;
+    }
+    CStr::from_ptr(string).to_str().ok()
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    let message = match payload.downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => payload
+            .downcast_ref::<String>()
+            .cloned()
+            .unwrap_or_default(),
+    };
+    format!("sapo panicked: {}", message)
+}
+
+/// NUL characters can't be part of a C string, they are dropped
+fn to_c_string(text: String) -> *mut c_char {
+    match CString::new(text.replace('\0', "")) {
+        Ok(string) => string.into_raw(),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eval() {
+        unsafe {
+            let interpreter = sapo_interpreter_new();
+            assert_eq!(eval_str(interpreter, "x = 2"), (SAPO_OK, String::from("2")));
+            assert_eq!(
+                eval_str(interpreter, "\"a\" == x"),
+                (
+                    SAPO_EVAL_ERROR,
+                    String::from("Error at line 1: Invalid operands for '==' [E0004]")
+                )
+            );
+            assert_eq!(
+                eval_str(interpreter, "(x"),
+                (
+                    SAPO_PARSE_ERROR,
+                    String::from(
+                        "ParseError at end of file: Expected ')', but 'EOF' was found. [E0001]"
+                    )
+                )
+            );
+            assert_eq!(
+                eval_str(interpreter, "exit(x)"),
+                (SAPO_EXIT, String::from("2"))
+            );
+            assert_eq!(eval_str(interpreter, ""), (SAPO_OK, String::new()));
+            sapo_interpreter_free(interpreter);
+        }
+    }
+
+    #[test]
+    fn limits() {
+        unsafe {
+            let interpreter = sapo_interpreter_new_with_limits(0, 1000, 0);
+            assert_eq!(
+                eval_str(interpreter, "\"a\" * 2000"),
+                (
+                    SAPO_EVAL_ERROR,
+                    String::from("Error at line 1: Evaluation exceeded its memory limit [E0015]")
+                )
+            );
+            (*interpreter)
+                .interpreter
+                .register_fn("crash", 0, |_| -> Result<_, String> { panic!("crashed") });
+            assert_eq!(
+                eval_str(interpreter, "crash()"),
+                (SAPO_PANIC, String::from("sapo panicked: crashed"))
+            );
+            sapo_interpreter_free(interpreter);
+        }
+    }
+
+    #[test]
+    fn parse() {
+        let source = CString::new("1").unwrap();
+        let mut result = ptr::null_mut();
+        unsafe {
+            assert_eq!(sapo_parse(source.as_ptr(), &mut result), SAPO_OK);
+            assert_eq!(
+                CStr::from_ptr(result).to_str().unwrap(),
+                r#"[{"type":"IntegerLiteral","value":1,"line":1,"span":{"start":0,"end":1}}]"#
+            );
+            sapo_string_free(result);
+        }
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        let mut result = ptr::null_mut();
+        unsafe {
+            assert_eq!(sapo_parse(ptr::null(), &mut result), SAPO_INVALID_ARGUMENT);
+            let invalid = [0xffu8 as c_char, 0];
+            assert_eq!(
+                sapo_parse(invalid.as_ptr(), &mut result),
+                SAPO_INVALID_ARGUMENT
+            );
+            assert!(result.is_null());
+        }
+    }
+
+    unsafe fn eval_str(interpreter: *mut SapoInterpreter, source: &str) -> (c_int, String) {
+        let source = CString::new(source).unwrap();
+        let mut result = ptr::null_mut();
+        let code = sapo_eval(interpreter, source.as_ptr(), &mut result);
+        let text = CStr::from_ptr(result).to_str().unwrap().to_string();
+        sapo_string_free(result);
+        (code, text)
+    }
+}
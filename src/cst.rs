@@ -0,0 +1,109 @@
+use crate::parsing::Lexer;
+use crate::token::{Token, TokenType};
+use std::fmt;
+
+/// A token with the exact text it was read from and the whitespace before it
+#[derive(Debug, PartialEq, Clone)]
+pub struct SyntaxToken {
+    pub token: Token,
+    /// Whitespace, comments and the shebang line between the previous token and this one
+    pub leading: String,
+    /// The source of the token, unlike its lexeme it includes the quotes of strings
+    pub text: String,
+}
+
+/// Lossless concrete syntax of a program, the tokens of the source together with the text
+/// in between. Printing it gives back the source exactly, so tools can edit a program
+/// token by token, e.g. to rename a variable, without reformatting the rest of it.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct SyntaxTree {
+    tokens: Vec<SyntaxToken>,
+    /// Whitespace after the last token
+    trailing: String,
+}
+
+impl SyntaxTree {
+    pub fn new(source: &str) -> Self {
+        let mut tokens = Vec::new();
+        let mut end = 0;
+        for token in Lexer::new(source) {
+            let span = token.span;
+            tokens.push(SyntaxToken {
+                leading: source[end..span.start].to_string(),
+                text: source[span.start..span.end].to_string(),
+                token,
+            });
+            end = span.end;
+        }
+        SyntaxTree {
+            tokens,
+            trailing: source[end..].to_string(),
+        }
+    }
+
+    pub fn tokens(&self) -> &[SyntaxToken] {
+        &self.tokens
+    }
+
+    /// Renames every variable and function called from, returns the number of renamed tokens
+    pub fn rename(&mut self, from: &str, to: &str) -> usize {
+        let mut renamed = 0;
+        for syntax_token in &mut self.tokens {
+            let token = &mut syntax_token.token;
+            if token.token_type == TokenType::Identifier && &*token.lexeme == from {
+                token.lexeme = to.into();
+                syntax_token.text = to.to_string();
+                renamed += 1;
+            }
+        }
+        renamed
+    }
+}
+
+impl fmt::Display for SyntaxTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for token in &self.tokens {
+            write!(f, "{}{}", token.leading, token.text)?;
+        }
+        f.write_str(&self.trailing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reproduce_source() {
+        let sources = [
+            "",
+            "  \n",
+            "#!/usr/bin/env sapo\nx = 1;\n\n  print( \"a  b\" )\n",
+            "x=\"unterminated",
+            "ñandú # 1",
+            "// comment\nx = 1 // one\n//",
+        ];
+        for source in &sources {
+            assert_eq!(SyntaxTree::new(source).to_string(), *source);
+        }
+    }
+
+    #[test]
+    fn tokens_with_trivia() {
+        let tree = SyntaxTree::new("x =\n  \"a\" ");
+        let tokens: Vec<(&str, &str)> = tree
+            .tokens()
+            .iter()
+            .map(|t| (t.leading.as_str(), t.text.as_str()))
+            .collect();
+        assert_eq!(tokens, vec![("", "x"), (" ", "="), ("\n  ", "\"a\"")]);
+        assert_eq!(&*tree.tokens()[2].token.lexeme, "a");
+    }
+
+    #[test]
+    fn rename_keeps_formatting() {
+        let mut tree = SyntaxTree::new("x  = 1;\nprint(x +x) ; \"x\"");
+        assert_eq!(tree.rename("x", "total"), 3);
+        assert_eq!(tree.to_string(), "total  = 1;\nprint(total +total) ; \"x\"");
+    }
+}
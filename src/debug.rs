@@ -0,0 +1,128 @@
+use super::Capabilities;
+use sapo::{Debugger, Environment, EvalError, Object};
+use std::io;
+use std::io::{BufRead, Write};
+use std::process;
+
+const HELP: &str = "Commands:
+  break LINE   (b) Pause before evaluating the expression at LINE
+  delete LINE  (d) Remove the breakpoint at LINE
+  step         (s) Evaluate the next expression
+  continue     (c) Evaluate until the next breakpoint
+  env          (e) List the variables of the program
+  print EXPR   (p) Evaluate EXPR in the environment of the program
+  list         (l) Print the line of the next expression
+  quit         (q) Stop debugging";
+
+/// Debugs a program read from name, arguments are returned by args()
+pub fn run(name: &str, code: String, arguments: Vec<String>, capabilities: Capabilities) {
+    let program = match sapo::parse_program(&code) {
+        Ok(program) => program,
+        Err(error) => {
+            eprintln!("{}: {}", name, error);
+            process::exit(1);
+        }
+    };
+    let mut environment = Environment::new();
+    environment.set_arguments(arguments);
+    capabilities.enable(&mut environment);
+    let mut debugger = Debugger::new(program, environment);
+    println!("Debugging {}, type help for a list of commands", name);
+    print_current_line(&debugger, &code);
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("(debug) ");
+        let _ = io::stdout().flush();
+        let input = match lines.next() {
+            Some(Ok(input)) => input,
+            _ => break,
+        };
+        let input = input.trim();
+        let (command, argument) = match input.find(char::is_whitespace) {
+            Some(i) => (&input[..i], input[i..].trim()),
+            None => (input, ""),
+        };
+        match command {
+            "" => {}
+            "help" | "h" => println!("{}", HELP),
+            "break" | "b" => match argument.parse() {
+                Ok(line) => {
+                    debugger.add_breakpoint(line);
+                    println!("Breakpoints at lines {:?}", debugger.breakpoints());
+                }
+                Err(_) => println!("Expected a line number"),
+            },
+            "delete" | "d" => match argument.parse() {
+                Ok(line) if debugger.remove_breakpoint(line) => {
+                    println!("Breakpoints at lines {:?}", debugger.breakpoints())
+                }
+                Ok(line) => println!("There is no breakpoint at line {}", line),
+                Err(_) => println!("Expected a line number"),
+            },
+            "step" | "s" => match debugger.step() {
+                Some(result) => {
+                    print_result(name, result.map(Some));
+                    print_current_line(&debugger, &code);
+                }
+                None => println!("The program has finished"),
+            },
+            "continue" | "c" => {
+                if debugger.is_finished() {
+                    println!("The program has finished");
+                    continue;
+                }
+                print_result(name, debugger.resume());
+                print_current_line(&debugger, &code);
+            }
+            "env" | "e" => {
+                let environment = debugger.environment();
+                for name in environment.names() {
+                    if let Some(value) = environment.get(name) {
+                        println!("{}: {} = {}", name, value.type_name(), value);
+                    }
+                }
+            }
+            "print" | "p" => {
+                let result = sapo::parse(argument).map(|(ast, root)| {
+                    sapo::evaluate_with_environment(&ast, root, debugger.environment_mut())
+                });
+                match result {
+                    Ok(Ok(value)) => println!("{}", value),
+                    Ok(Err(error)) => println!("{}", error),
+                    Err(error) => println!("{}", error),
+                }
+            }
+            "list" | "l" => print_current_line(&debugger, &code),
+            "quit" | "q" => break,
+            _ => println!(
+                "Unknown command '{}', type help for a list of commands",
+                command
+            ),
+        }
+    }
+}
+
+/// Exits the process if the program called exit()
+fn print_result(name: &str, result: Result<Option<Object>, EvalError>) {
+    match result {
+        Ok(Some(value)) => println!("{}", value),
+        Ok(None) => {}
+        Err(EvalError::Exit(_, code)) => {
+            println!("The program exited with status {}", code);
+            process::exit(code);
+        }
+        Err(error) => println!("{}: {}", name, error),
+    }
+}
+
+fn print_current_line(debugger: &Debugger, code: &str) {
+    match debugger.current_line() {
+        Some(number) => {
+            let line = code.lines().nth((number as usize).saturating_sub(1));
+            println!("{:>4} | {}", number, line.unwrap_or(""));
+        }
+        None => println!("The program has finished"),
+    }
+}
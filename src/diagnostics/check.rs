@@ -0,0 +1,205 @@
+use super::{check_warnings, suggest, Diagnostic};
+use crate::ast::{Ast, ExprId, Expression};
+use crate::evaluation::{
+    builtin_arity, builtin_max_arity, is_variadic, EvalError, BUILTINS, FILE_FUNCTIONS,
+    PROCESS_FUNCTIONS,
+};
+use crate::parsing::{parse_program_with_recovery, Lexer, ParseOptions};
+use crate::token::TokenType;
+use std::collections::HashSet;
+
+/// Parses and resolves the program without evaluating it, statements with syntax
+/// errors are skipped. Returns the errors and warnings found, sorted by line
+pub fn check(input: &str) -> Vec<Diagnostic> {
+    let (program, errors) = parse_program_with_recovery(input, &ParseOptions::default());
+    let mut diagnostics: Vec<Diagnostic> = errors.iter().map(Diagnostic::from).collect();
+    let mut resolver = Resolver {
+        defined: HashSet::new(),
+        errors: Vec::new(),
+    };
+    for &statement in &program.statements {
+        resolver.resolve(&program.ast, statement);
+        diagnostics.extend(resolver.errors.drain(..).map(|e| Diagnostic::from(&e)));
+        let warnings = check_warnings(&program.ast, statement);
+        diagnostics.extend(warnings.iter().map(Diagnostic::from));
+    }
+    // errors at the end of the file go last
+    diagnostics.sort_by_key(|d| d.line.unwrap_or(i32::MAX));
+    diagnostics
+}
+
+/// Finds the errors that evaluating the program would report for variables
+/// and functions, visiting the expressions in evaluation order
+struct Resolver {
+    /// Variables assigned so far
+    defined: HashSet<String>,
+    errors: Vec<EvalError>,
+}
+
+impl Resolver {
+    fn resolve(&mut self, ast: &Ast, id: ExprId) {
+        match &ast[id] {
+            Expression::IntegerLiteral { .. }
+            | Expression::BooleanLiteral { .. }
+            | Expression::StringLiteral { .. } => {}
+            // the statement might assign any of its names, they aren't reported as undefined
+            Expression::Error { token: _, source } => {
+                let names = Lexer::new(source)
+                    .filter(|t| t.token_type == TokenType::Identifier)
+                    .map(|t| t.lexeme.to_string());
+                self.defined.extend(names);
+            }
+            Expression::Grouping { token: _, expr } => self.resolve(ast, *expr),
+            Expression::MultipleAssignment {
+                token: _,
+                names,
+                value,
+            } => {
+                self.resolve(ast, *value);
+                self.defined.extend(names.iter().map(|n| n.to_string()));
+            }
+            Expression::Index { .. }
+            | Expression::Slice { .. }
+            | Expression::Tuple { .. }
+            | Expression::TypeTest { .. } => {
+                for child in ast[id].children() {
+                    self.resolve(ast, child);
+                }
+            }
+            Expression::UnaryExpression { token: _, right } => self.resolve(ast, *right),
+            Expression::BinaryExpression {
+                token: _,
+                left,
+                right,
+            } => {
+                self.resolve(ast, *left);
+                self.resolve(ast, *right);
+            }
+            Expression::Variable { token, name } => {
+                if !self.defined.contains(name.as_str()) {
+                    let mut names: Vec<&str> = self.defined.iter().map(|n| n.as_str()).collect();
+                    names.sort_unstable();
+                    names.extend(&["true", "false"]);
+                    let suggestion = suggest(name.as_str(), &names).map(|s| s.to_string());
+                    self.errors
+                        .push(EvalError::UndefinedVariable(token.clone(), suggestion));
+                    // report each undefined variable only once
+                    self.defined.insert(name.to_string());
+                }
+            }
+            Expression::Assignment {
+                token: _,
+                name,
+                value,
+            } => {
+                self.resolve(ast, *value);
+                self.defined.insert(name.to_string());
+            }
+            Expression::Call {
+                token,
+                name,
+                arguments,
+            } => {
+                for argument in arguments {
+                    self.resolve(ast, *argument);
+                }
+                let given = arguments.len();
+                // the file and process functions might be enabled when the program runs
+                let capability_arity = || {
+                    FILE_FUNCTIONS
+                        .iter()
+                        .chain(&PROCESS_FUNCTIONS)
+                        .find(|(function, _)| *function == name.as_str())
+                        .map(|&(_, arity)| arity)
+                };
+                let max_arity = |expected| {
+                    if is_variadic(name.as_str()) {
+                        builtin_max_arity(name.as_str())
+                    } else {
+                        Some(expected)
+                    }
+                };
+                match builtin_arity(name.as_str()).or_else(capability_arity) {
+                    Some(expected)
+                        if given < expected
+                            || max_arity(expected).is_some_and(|max| given > max) =>
+                    {
+                        self.errors.push(EvalError::WrongNumberOfArguments(
+                            token.clone(),
+                            expected,
+                            given,
+                        ))
+                    }
+                    Some(_) => {}
+                    None => {
+                        let suggestion = suggest(name.as_str(), &BUILTINS).map(|s| s.to_string());
+                        self.errors
+                            .push(EvalError::UndefinedFunction(token.clone(), suggestion));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_valid_program() {
+        assert_eq!(check("x = 1;\ny = x + 1;\nexit(y)"), vec![]);
+    }
+
+    #[test]
+    fn check_parse_error() {
+        assert_eq!(
+            messages("x = (1 + 2;"),
+            vec!["E0001: Expected ')', but ';' was found."]
+        );
+    }
+
+    #[test]
+    fn check_after_parse_errors() {
+        assert_eq!(
+            messages("x = (1 + 2;\n!!x;\ny = 1 2;\nprint(y, z)"),
+            vec![
+                "E0001: Expected ')', but ';' was found.",
+                "W0001: Double '!' has no effect.",
+                "E0009: Expected ';', but '2' was found.",
+                "E0006: Undefined variable 'z'",
+                "E0011: 'print' expected 1 argument(s), but 2 were given",
+            ]
+        );
+    }
+
+    #[test]
+    fn check_resolves_names() {
+        assert_eq!(
+            messages("x = 1;\n!!y;\nexti(x, 2);\nargs(x);\nformat(\"{}{}\", x, x);\nformat();\nrange(1, 2, 3)"),
+            vec![
+                "E0006: Undefined variable 'y'",
+                "W0001: Double '!' has no effect.",
+                "E0010: Undefined function 'exti', did you mean 'exit'?",
+                "E0011: 'args' expected 0 argument(s), but 1 were given",
+                "E0011: 'format' expected at least 1 argument(s), but 0 were given",
+                "E0011: 'range' expected 1 to 2 argument(s), but 3 were given",
+            ]
+        );
+    }
+
+    #[test]
+    fn check_uses_assignment_order() {
+        assert_eq!(
+            messages("y = x; x = 1"),
+            vec!["E0006: Undefined variable 'x'"]
+        );
+    }
+
+    fn messages(input: &str) -> Vec<String> {
+        check(input)
+            .iter()
+            .map(|d| format!("{}: {}", d.code, d.message))
+            .collect()
+    }
+}
@@ -0,0 +1,173 @@
+use super::Warning;
+use crate::evaluation::EvalError;
+use crate::json;
+use crate::parsing::{LexError, ParseError};
+use crate::token::{Token, TokenType};
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// Common representation of errors and warnings, meant to be consumed by tools
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    /// None if the diagnostic refers to the end of the input
+    pub line: Option<i32>,
+    /// Column of the token in its line, counted in chars from 1,
+    /// None if the token wasn't read from a source
+    pub column: Option<usize>,
+}
+
+impl Diagnostic {
+    fn new(severity: Severity, code: &'static str, message: String, token: &Token) -> Self {
+        let line = match token.token_type {
+            TokenType::EOF => None,
+            _ => Some(token.line),
+        };
+        let column = Some(token.column).filter(|&c| c > 0 && line.is_some());
+        Diagnostic {
+            severity,
+            code,
+            message,
+            line,
+            column,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let span = match (self.line, self.column) {
+            (Some(line), Some(column)) => format!("{{\"line\":{},\"column\":{}}}", line, column),
+            (Some(line), None) => format!("{{\"line\":{}}}", line),
+            (None, _) => String::from("null"),
+        };
+        format!(
+            "{{\"severity\":{},\"code\":{},\"message\":{},\"span\":{}}}",
+            json::quote(&self.severity.to_string()),
+            json::quote(self.code),
+            json::quote(&self.message),
+            span
+        )
+    }
+}
+
+/// Same format as the errors and warnings the diagnostic was created from,
+/// e.g. "Warning at line 1: Double '!' has no effect. [W0001]"
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.severity {
+            Severity::Error => "Error",
+            Severity::Warning => "Warning",
+        };
+        match self.line {
+            Some(line) => write!(f, "{} at line {}: ", kind, line)?,
+            None => write!(f, "{} at end of file: ", kind)?,
+        }
+        write!(f, "{} [{}]", self.message, self.code)
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Self {
+        Diagnostic::new(
+            Severity::Error,
+            error.code(),
+            error.message(),
+            error.token(),
+        )
+    }
+}
+
+impl From<&LexError> for Diagnostic {
+    fn from(error: &LexError) -> Self {
+        Diagnostic::new(
+            Severity::Error,
+            error.code(),
+            error.message(),
+            error.token(),
+        )
+    }
+}
+
+impl From<&EvalError> for Diagnostic {
+    fn from(error: &EvalError) -> Self {
+        Diagnostic::new(
+            Severity::Error,
+            error.code(),
+            error.message(),
+            error.token(),
+        )
+    }
+}
+
+impl From<&Warning> for Diagnostic {
+    fn from(warning: &Warning) -> Self {
+        Diagnostic::new(
+            Severity::Warning,
+            warning.code(),
+            warning.message(),
+            warning.token(),
+        )
+    }
+}
+
+/// Serializes the diagnostics as a JSON array
+pub fn diagnostics_to_json(diagnostics: &[Diagnostic]) -> String {
+    let objects: Vec<String> = diagnostics.iter().map(|d| d.to_json()).collect();
+    format!("[{}]", objects.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{check_warnings, parse};
+
+    #[test]
+    fn parse_error_to_json() {
+        let error = parse("(1 + 2").unwrap_err();
+        assert_eq!(
+            Diagnostic::from(&error).to_json(),
+            r#"{"severity":"error","code":"E0001","message":"Expected ')', but 'EOF' was found.","span":null}"#
+        );
+    }
+
+    #[test]
+    fn display_diagnostic() {
+        let error = parse("(1 + 2").unwrap_err();
+        assert_eq!(
+            Diagnostic::from(&error).to_string(),
+            "Error at end of file: Expected ')', but 'EOF' was found. [E0001]"
+        );
+    }
+
+    #[test]
+    fn warnings_to_json() {
+        let (ast, root) = parse("!!true\n == \n!!true").unwrap();
+        let diagnostics: Vec<Diagnostic> = check_warnings(&ast, root)
+            .iter()
+            .map(Diagnostic::from)
+            .collect();
+        assert_eq!(
+            diagnostics_to_json(&diagnostics),
+            concat!(
+                r#"[{"severity":"warning","code":"W0002","message":"Both operands of '==' are identical, condition is always true.","span":{"line":2,"column":2}},"#,
+                r#"{"severity":"warning","code":"W0001","message":"Double '!' has no effect.","span":{"line":1,"column":1}},"#,
+                r#"{"severity":"warning","code":"W0001","message":"Double '!' has no effect.","span":{"line":3,"column":1}}]"#
+            )
+        );
+    }
+}
@@ -0,0 +1,595 @@
+/// Returns a detailed description of the error with the given code, e.g. "E0002"
+pub fn explain(code: &str) -> Option<&'static str> {
+    match code.to_uppercase().as_str() {
+        "E0001" => Some(E0001),
+        "E0002" => Some(E0002),
+        "E0003" => Some(E0003),
+        "E0004" => Some(E0004),
+        "E0005" => Some(E0005),
+        "E0006" => Some(E0006),
+        "E0007" => Some(E0007),
+        "E0008" => Some(E0008),
+        "E0009" => Some(E0009),
+        "E0010" => Some(E0010),
+        "E0011" => Some(E0011),
+        "E0012" => Some(E0012),
+        "E0013" => Some(E0013),
+        "E0014" => Some(E0014),
+        "E0015" => Some(E0015),
+        "E0016" => Some(E0016),
+        "E0017" => Some(E0017),
+        "E0018" => Some(E0018),
+        "E0019" => Some(E0019),
+        "E0020" => Some(E0020),
+        "E0021" => Some(E0021),
+        "E0022" => Some(E0022),
+        "E0023" => Some(E0023),
+        "E0024" => Some(E0024),
+        "E0025" => Some(E0025),
+        "E0026" => Some(E0026),
+        "E0027" => Some(E0027),
+        "E0028" => Some(E0028),
+        "E0029" => Some(E0029),
+        "E0030" => Some(E0030),
+        "E0031" => Some(E0031),
+        "E0032" => Some(E0032),
+        "E0033" => Some(E0033),
+        "E0034" => Some(E0034),
+        "E0035" => Some(E0035),
+        "E0036" => Some(E0036),
+        _ => None,
+    }
+}
+
+const E0001: &str = r#"E0001: Missing closing parenthesis.
+
+A grouping was opened with '(' but the matching ')' was never found.
+
+Erroneous example:
+
+    (8 + 7
+
+Every opening parenthesis must be closed:
+
+    (8 + 7)
+"#;
+
+const E0002: &str = r#"E0002: Expected expression.
+
+The parser expected an expression (a literal, a grouping or an operation)
+but found something else, like an operator, a ';' or the end of the input.
+
+Erroneous examples:
+
+    8 + ;
+    * 4
+
+Make sure every operator has all its operands:
+
+    8 + 1
+    3 * 4
+"#;
+
+const E0003: &str = r#"E0003: Invalid operand for unary operator.
+
+The operand of a unary operator has the wrong type. '!' can only be applied
+to booleans and '-' can only be applied to integers.
+
+Erroneous examples:
+
+    !0
+    -true
+
+Use an operand of the right type:
+
+    !false
+    -1
+"#;
+
+const E0004: &str = r#"E0004: Invalid operands for binary operator.
+
+The operands of a binary operator have types the operator doesn't support.
+Arithmetic ('+', '-', '*', '/') and comparison ('<', '<=', '>', '>=')
+operators only work on integers, except for '*' which also repeats a
+string an integer number of times. '==' and '!=' require both operands to
+be integers or both to be booleans. The right operand of 'in' must be an
+array, a tuple or a string, and if it's a string so must the left one.
+
+Erroneous examples:
+
+    1 + true
+    false > true
+    "sapo" == "sapo"
+    1 in "123"
+
+Use operands of the right type:
+
+    1 + 1
+    4 > 3
+    true == true
+"#;
+
+const E0005: &str = r#"E0005: Invalid operator.
+
+The evaluator found an operator it doesn't know how to apply. The parser
+never produces such expressions, so this error can only be triggered by
+building an abstract syntax tree by hand.
+"#;
+
+const E0006: &str = r#"E0006: Undefined variable.
+
+An expression reads a variable that has not been assigned yet. When the
+name is close to the name of an existing variable, a suggestion is printed.
+
+Erroneous example:
+
+    x + 1
+
+Assign the variable before using it:
+
+    x = 2
+    x + 1
+"#;
+
+const E0008: &str = r#"E0008: Invalid assignment target.
+
+Only variables can be assigned to, the left-hand side of '=' must be a
+single name or several names separated by ','.
+
+Erroneous examples:
+
+    x + 1 = 3
+    (x) = 3
+    x, y[0] = 1, 2
+
+Assign to variables instead:
+
+    x = 3 - 1
+    x, y = 1, 2
+"#;
+
+const E0007: &str = r#"E0007: Expected end of input.
+
+A sapo program consists of a single expression. The parser found a complete
+expression but there were more tokens after it.
+
+Erroneous examples:
+
+    1 2
+    (4 + 5) 6
+
+Combine the expressions with an operator:
+
+    1 + 2
+    (4 + 5) * 6
+"#;
+
+const E0009: &str = r#"E0009: Expected ';'.
+
+The expressions of a program must be separated by ';'.
+
+Erroneous example:
+
+    x = 2
+    x + 1
+
+Add a ';' after each expression:
+
+    x = 2;
+    x + 1;
+"#;
+
+const E0010: &str = r#"E0010: Undefined function.
+
+A function was called that does not exist. The available functions are
+args() and exit(code).
+
+Erroneous example:
+
+    arg()
+
+Check the spelling of the function:
+
+    args()
+"#;
+
+const E0011: &str = r#"E0011: Wrong number of arguments.
+
+A function was called with more or fewer arguments than it takes.
+
+Erroneous examples:
+
+    exit()
+    args(1)
+
+Pass as many arguments as the function expects:
+
+    exit(1)
+    args()
+"#;
+
+const E0012: &str = r#"E0012: Invalid argument.
+
+An argument passed to a function has the wrong type.
+
+Erroneous example:
+
+    exit("1")
+
+Pass an argument of the expected type:
+
+    exit(1)
+"#;
+
+const E0013: &str = r#"E0013: Program exited.
+
+The program called exit(code), evaluation was stopped. When running a
+program from the command line sapo terminates with the given status code
+instead of reporting this error.
+
+Example:
+
+    exit(2)
+"#;
+
+const E0014: &str = r#"E0014: Expression is nested too deeply.
+
+Expressions whose tree is deeper than a limit are rejected, evaluating
+them could run out of stack. Parentheses, unary operators, assignments,
+function arguments and binary operators all increase the depth, e.g.
+-(1 + 2) has a depth of 3.
+
+Erroneous example:
+
+    ((((((((((((((((( ... 1 ... )))))))))))))))))
+
+Split the expression using variables:
+
+    x = (((1)));
+    (((x)))
+"#;
+
+const E0015: &str = r#"E0015: Evaluation exceeded a resource limit.
+
+Programs can be evaluated with limits on the number of expressions
+evaluated, the memory taken by their values and the time they run, so
+that programs from untrusted sources are guaranteed to finish. The
+evaluation stopped because it reached one of them.
+
+Erroneous example, with a limit of 3 steps:
+
+    1 + 2 + 3
+
+Simplify the program or raise the limits of EvalOptions.
+"#;
+
+const E0016: &str = r#"E0016: Evaluation was cancelled.
+
+The evaluation was stopped before it finished, e.g. because Ctrl-C was
+pressed in the REPL while it was running. Programs embedding sapo can
+cancel evaluations with a CancellationToken.
+"#;
+
+const E0017: &str = r#"E0017: Function failed.
+
+A function provided by the application running the program reported an
+error, its description is part of the message. Check the documentation
+of the application for the arguments the function accepts.
+"#;
+
+const E0018: &str = r#"E0018: Integer literal is too large.
+
+Integers are 32 bit signed numbers, literals can't be larger than
+2147483647.
+
+Erroneous example:
+
+    x = 10000000000
+"#;
+
+const E0019: &str = r#"E0019: Assertion failed.
+
+A call to assert(condition, message) had a false condition, or the values
+passed to assert_eq(left, right) were different. `sapo test` reports the
+test containing the assertion as failed.
+
+Erroneous examples:
+
+    assert(1 > 2, "1 should be greater than 2")
+    assert_eq(2 + 2, 5)
+"#;
+
+const E0020: &str = r#"E0020: Unexpected character.
+
+The program contains a character that isn't part of any token of the
+language, outside of a string.
+
+Erroneous example:
+
+    x = 1 # one
+
+Remove the character, or put the text in a string:
+
+    x = 1
+"#;
+
+const E0021: &str = r#"E0021: Unterminated string.
+
+A string was opened with '"' but the closing '"' was never found, so the
+rest of the program was read as part of it.
+
+Erroneous example:
+
+    print("hello)
+
+Close every string:
+
+    print("hello")
+"#;
+
+const E0022: &str = r#"E0022: Malformed integer literal.
+
+Integer literals are decimal, or hexadecimal, octal and binary when they
+start with 0x, 0o and 0b. They need at least one digit valid for their
+radix, and can't end with the '_' used to separate digits.
+
+Erroneous examples:
+
+    x = 0x
+    y = 0b102
+    z = 1_000_
+
+Write the digits after the prefix and remove the trailing '_':
+
+    x = 0x0
+    y = 0b101
+    z = 1_000
+"#;
+
+const E0023: &str = r#"E0023: Statement with a syntax error.
+
+The evaluation reached a statement that couldn't be parsed. Tools like
+editors parse programs with syntax errors to work on the rest of them,
+such a program fails when it gets to the first statement with an error.
+
+Erroneous example:
+
+    x = 1;
+    y = (x + ;
+    print(x)
+
+Fix the syntax error reported for the statement:
+
+    y = (x + 1);
+"#;
+
+const E0024: &str = r#"E0024: String repeated a negative number of times.
+
+Multiplying a string by an integer repeats it that many times, which
+can't be done with a negative count.
+
+Erroneous example:
+
+    dashes = "-" * (0 - 3)
+
+Repeat the string zero or more times:
+
+    dashes = "-" * 3
+"#;
+
+const E0025: &str = r#"E0025: Index out of bounds.
+
+An index isn't in the array or string it was used on. Indices start at 0
+and negative ones count from the end, -1 being the last element. The
+bounds of a slice can also be the length, which is the end.
+
+Erroneous examples:
+
+    words = split("a b", " ");
+    words[2];
+    "sapo"[1:5];
+    pop(args())
+
+Use an index smaller than the length, and don't pop from empty arrays:
+
+    words[1];
+    "sapo"[1:4]
+"#;
+
+const E0026: &str = r#"E0026: Missing closing bracket.
+
+An indexing or a slice was opened with '[' but the matching ']' was
+never found.
+
+Erroneous example:
+
+    first = args()[0
+
+Close every '[':
+
+    first = args()[0]
+"#;
+
+const E0027: &str = r#"E0027: Wrong number of values to assign.
+
+A multiple assignment like `a, b = value` assigns the values of a tuple
+or an array to the names in order, so there must be as many values as
+names. Any other value counts as a single one.
+
+Erroneous examples:
+
+    a, b = (1, 2, 3);
+    a, b = 1
+
+Assign as many values as there are names:
+
+    a, b = (1, 2);
+    a, b = b, a
+"#;
+
+const E0028: &str = r#"E0028: Missing type name.
+
+The 'is' operator tests whether a value has a type, so it must be followed
+by the name of one.
+
+Erroneous examples:
+
+    x is 1
+    x is "Integer"
+
+Write the name of the type instead:
+
+    x is Integer
+"#;
+
+const E0029: &str = r#"E0029: Unknown type.
+
+The type name after 'is' isn't the name of a type. The types are Array,
+Boolean, Integer, String and Tuple, which is what type() returns.
+
+Erroneous examples:
+
+    x is integer
+    x is Number
+
+Use one of the types:
+
+    x is Integer
+    x is Boolean
+"#;
+
+const E0030: &str = r#"E0030: Conversion failed.
+
+int() converts strings of decimal digits, optionally with a sign, and
+bool() the strings "true" and "false". Whitespace around them is ignored,
+any other string can't be converted.
+
+Erroneous examples:
+
+    int("12a")
+    int("99999999999")
+    bool("yes")
+
+Convert strings that have the right format:
+
+    int(" -12 ")
+    bool("true")
+"#;
+
+const E0031: &str = r#"E0031: Invalid format string.
+
+format() replaces each '{}' of its first argument with the next of the
+other ones, so there must be as many placeholders as values. A placeholder
+can have a width and an alignment, like '{:5}', '{:<5}', '{:>5}' or '{:05}',
+and literal braces are written twice, '{{' and '}}'.
+
+Erroneous examples:
+
+    format("{} + {}", 1)
+    format("{x}", 1)
+    format("{", 1)
+
+Write a placeholder for each value:
+
+    format("{} + {} = {:03}", 1, 2, 3)
+    format("{{{}}}", 1)
+"#;
+
+const E0032: &str = r#"E0032: Invalid JSON.
+
+json_parse() reads a JSON document into sapo values: numbers become
+integers, objects become arrays of (key, value) tuples and the other
+values the ones of the same type. The document must be valid JSON, and
+sapo has no values for null and numbers that aren't 32 bit integers.
+
+Erroneous examples:
+
+    json_parse("{'a': 1}")
+    json_parse("[1.5]")
+    json_parse("null")
+
+Use documents with values sapo can represent:
+
+    json_parse("[1, true, [2, 3]]")
+    json_parse(read_file("config.json"))
+"#;
+
+const E0033: &str = r#"E0033: Invalid regular expression.
+
+The pattern of regex_match, regex_find_all or regex_replace must be a
+regular expression with the syntax of the Rust regex crate, see
+https://docs.rs/regex. Characters with a special meaning, like '(' or
+'.', match themselves when preceded by '\'.
+
+Erroneous examples:
+
+    regex_match("f(x)", "(x")
+    regex_find_all("a1b22", "[0-9")
+
+Close the groups and classes of the pattern:
+
+    regex_match("f(x)", "\(x\)")
+    regex_find_all("a1b22", "[0-9]+")
+"#;
+
+const E0034: &str = r#"E0034: Expected a function name.
+
+'|>' passes the value on its left as the first argument of the function
+called on its right, so it must be followed by the name of a function,
+optionally with the rest of the arguments in parentheses.
+
+Erroneous examples:
+
+    "a b" |> 1
+    x |> (f)
+
+Write the name of a function after '|>':
+
+    " a b " |> trim |> split(" ")
+"#;
+
+const E0035: &str = r#"E0035: Arithmetic error.
+
+Integers are 32 bit signed numbers, from -2147483648 to 2147483647. An
+addition, subtraction, multiplication or negation whose result is outside
+of that range overflows, and dividing by zero has no result. Both stop
+the evaluation instead of producing a wrong number.
+
+Erroneous examples:
+
+    1 / 0
+    2147483647 + 1
+    (0 - 2147483647 - 1) / -1
+
+Keep the operands and results within the range of Integer:
+
+    10 / 2
+    2147483646 + 1
+"#;
+
+const E0036: &str = r#"E0036: Invalid literal.
+
+A literal token passed to parse_tokens has text that isn't a literal of
+its type, like an IntegerLiteral token "1a" or a BooleanLiteral token
+"yes". The lexer never produces such tokens, tools synthesizing tokens
+must give IntegerLiteral tokens decimal, 0x, 0o or 0b digits, and
+BooleanLiteral tokens the text true or false.
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explain_known_code() {
+        assert!(explain("E0002")
+            .unwrap()
+            .starts_with("E0002: Expected expression."));
+        assert!(explain("e0004").unwrap().starts_with("E0004:"));
+    }
+
+    #[test]
+    fn explain_unknown_code() {
+        assert_eq!(explain("E9999"), None);
+    }
+}
@@ -0,0 +1,11 @@
+mod check;
+mod diagnostic;
+mod explain;
+mod suggestions;
+mod warnings;
+
+pub use check::*;
+pub use diagnostic::*;
+pub use explain::*;
+pub use suggestions::*;
+pub use warnings::*;
@@ -0,0 +1,60 @@
+/// Returns the candidate closest to name, if it's close enough to be a plausible typo
+pub fn suggest<'a>(name: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    let length = name.chars().count();
+    let max_distance = std::cmp::max(1, length / 3);
+    candidates
+        .iter()
+        .map(|&c| (edit_distance(name, c), c))
+        // replacing every character isn't a typo
+        .filter(|&(distance, _)| distance <= max_distance && distance < length)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, c)| c)
+}
+
+/// Edit distance between two strings counted in characters, where swapping two
+/// adjacent characters counts as a single edit (optimal string alignment distance)
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distance() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("sapo", ""), 4);
+        assert_eq!(edit_distance("true", "ture"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn suggest_closest_name() {
+        assert_eq!(suggest("flase", &["true", "false"]), Some("false"));
+        assert_eq!(suggest("tru", &["true", "false"]), Some("true"));
+        assert_eq!(suggest("x", &["true", "false"]), None);
+        assert_eq!(suggest("y", &["x", "xy"]), None);
+        assert_eq!(suggest("xx", &["x", "y"]), Some("x"));
+    }
+}
@@ -0,0 +1,210 @@
+use crate::ast::{Ast, ExprId, Expression};
+use crate::token::{Token, TokenType};
+use std::fmt;
+
+/// Non-fatal diagnostics, the program is still evaluated when warnings are emitted
+#[derive(Debug, PartialEq)]
+pub enum Warning {
+    DoubleNegation(Token),
+    ConstantCondition(Token, bool),
+}
+
+impl Warning {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Warning::DoubleNegation(_) => "W0001",
+            Warning::ConstantCondition(_, _) => "W0002",
+        }
+    }
+
+    /// The token of the operation the warning refers to
+    pub fn token(&self) -> &Token {
+        match self {
+            Warning::DoubleNegation(t) | Warning::ConstantCondition(t, _) => t,
+        }
+    }
+
+    /// Description of the warning without location or code
+    pub fn message(&self) -> String {
+        match self {
+            Warning::DoubleNegation(t) => format!("Double '{}' has no effect.", t.lexeme),
+            Warning::ConstantCondition(t, value) => format!(
+                "Both operands of '{}' are identical, condition is always {}.",
+                t.lexeme, value
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Warning at line {}: {} [{}]",
+            self.token().line,
+            self.message(),
+            self.code()
+        )
+    }
+}
+
+pub fn check_warnings(ast: &Ast, id: ExprId) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    check_expression(ast, id, &mut warnings);
+    warnings
+}
+
+fn check_expression(ast: &Ast, id: ExprId, warnings: &mut Vec<Warning>) {
+    match &ast[id] {
+        Expression::IntegerLiteral { .. }
+        | Expression::BooleanLiteral { .. }
+        | Expression::StringLiteral { .. }
+        | Expression::Variable { .. }
+        | Expression::Error { .. } => {}
+        Expression::Assignment {
+            token: _,
+            name: _,
+            value,
+        } => check_expression(ast, *value, warnings),
+        Expression::Call {
+            token: _,
+            name: _,
+            arguments,
+        } => {
+            for argument in arguments {
+                check_expression(ast, *argument, warnings);
+            }
+        }
+        Expression::Grouping { token: _, expr } => check_expression(ast, *expr, warnings),
+        Expression::Index { .. }
+        | Expression::Slice { .. }
+        | Expression::Tuple { .. }
+        | Expression::MultipleAssignment { .. }
+        | Expression::TypeTest { .. } => {
+            for child in ast[id].children() {
+                check_expression(ast, child, warnings);
+            }
+        }
+        Expression::UnaryExpression { token, right } => {
+            if let Expression::UnaryExpression { token: inner, .. } = &ast[*right] {
+                if inner.token_type == token.token_type {
+                    warnings.push(Warning::DoubleNegation(token.clone()));
+                }
+            }
+            check_expression(ast, *right, warnings);
+        }
+        Expression::BinaryExpression { token, left, right } => {
+            if is_same_expression(ast, *left, *right) {
+                match token.token_type {
+                    TokenType::Equals | TokenType::GreaterEquals | TokenType::SmallerEquals => {
+                        warnings.push(Warning::ConstantCondition(token.clone(), true))
+                    }
+                    TokenType::BangEquals | TokenType::Greater | TokenType::Smaller => {
+                        warnings.push(Warning::ConstantCondition(token.clone(), false))
+                    }
+                    _ => {}
+                }
+            }
+            check_expression(ast, *left, warnings);
+            check_expression(ast, *right, warnings);
+        }
+    }
+}
+
+/// Structural comparison that ignores the location of the tokens
+/// Calls are never considered the same, they might return different values
+fn is_same_expression(ast: &Ast, left: ExprId, right: ExprId) -> bool {
+    match (&ast[left], &ast[right]) {
+        (
+            Expression::IntegerLiteral { token: _, value: l },
+            Expression::IntegerLiteral { token: _, value: r },
+        ) => l == r,
+        (
+            Expression::BooleanLiteral { token: _, value: l },
+            Expression::BooleanLiteral { token: _, value: r },
+        ) => l == r,
+        (
+            Expression::StringLiteral { token: _, value: l },
+            Expression::StringLiteral { token: _, value: r },
+        ) => l == r,
+        (
+            Expression::Grouping { token: _, expr: l },
+            Expression::Grouping { token: _, expr: r },
+        ) => is_same_expression(ast, *l, *r),
+        (
+            Expression::UnaryExpression {
+                token: lt,
+                right: l,
+            },
+            Expression::UnaryExpression {
+                token: rt,
+                right: r,
+            },
+        ) => lt.token_type == rt.token_type && is_same_expression(ast, *l, *r),
+        (
+            Expression::BinaryExpression {
+                token: lt,
+                left: ll,
+                right: lr,
+            },
+            Expression::BinaryExpression {
+                token: rt,
+                left: rl,
+                right: rr,
+            },
+        ) => {
+            lt.token_type == rt.token_type
+                && is_same_expression(ast, *ll, *rl)
+                && is_same_expression(ast, *lr, *rr)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn no_warnings() {
+        assert_eq!(warnings("!true == (4 > 5)"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn double_negation() {
+        assert_eq!(
+            warnings("!!true"),
+            vec!["Warning at line 1: Double '!' has no effect. [W0001]"]
+        );
+        assert_eq!(
+            warnings("--4"),
+            vec!["Warning at line 1: Double '-' has no effect. [W0001]"]
+        );
+    }
+
+    #[test]
+    fn constant_condition() {
+        assert_eq!(
+            warnings("(1 + 2) == (1 + 2)"),
+            vec![
+                "Warning at line 1: Both operands of '==' are identical, condition is always true. [W0002]"
+            ]
+        );
+        assert_eq!(
+            warnings("\"sapo\" < \"sapo\""),
+            vec![
+                "Warning at line 1: Both operands of '<' are identical, condition is always false. [W0002]"
+            ]
+        );
+        assert_eq!(warnings("(1 + 2) == (2 + 1)"), Vec::<String>::new());
+    }
+
+    fn warnings(input: &str) -> Vec<String> {
+        let (ast, root) = parse(input).unwrap();
+        check_warnings(&ast, root)
+            .iter()
+            .map(|w| w.to_string())
+            .collect()
+    }
+}
@@ -0,0 +1,122 @@
+use super::evaluation::Evaluation;
+use super::{Environment, EvalError, Object};
+use crate::ast::Program;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Number of steps evaluated before the future returned by evaluate_async
+/// gives control back to the executor
+pub const ASYNC_YIELD_STEPS: usize = 1000;
+
+/// Like evaluate_program, but the evaluation happens while the returned future is polled
+/// and it yields to the executor every ASYNC_YIELD_STEPS steps, so that long programs
+/// don't block the thread running them. Dropping the future stops the evaluation.
+pub fn evaluate_async<'a>(program: &'a Program, env: &'a mut Environment) -> EvaluateAsync<'a> {
+    env.start();
+    EvaluateAsync {
+        program,
+        env,
+        next: 0,
+        evaluation: None,
+        result: None,
+    }
+}
+
+/// Future returned by evaluate_async
+pub struct EvaluateAsync<'a> {
+    program: &'a Program,
+    env: &'a mut Environment,
+    /// Index of the next expression of the program to evaluate
+    next: usize,
+    /// Evaluation of the current expression of the program, if it hasn't finished
+    evaluation: Option<Evaluation<'a>>,
+    /// Value of the last expression evaluated
+    result: Option<Object>,
+}
+
+impl<'a> Future for EvaluateAsync<'a> {
+    type Output = Result<Option<Object>, EvalError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        for _ in 0..ASYNC_YIELD_STEPS {
+            let evaluation = match &mut this.evaluation {
+                Some(evaluation) => evaluation,
+                None => {
+                    let statement = match this.program.statements.get(this.next) {
+                        Some(&statement) => statement,
+                        None => return Poll::Ready(Ok(this.result.take())),
+                    };
+                    this.next += 1;
+                    for hook in this.env.hooks_mut() {
+                        hook.on_statement(&this.program.ast[statement]);
+                    }
+                    match Evaluation::new(&this.program.ast, statement, this.env) {
+                        Ok(evaluation) => this.evaluation.insert(evaluation),
+                        Err(error) => return Poll::Ready(Err(error)),
+                    }
+                }
+            };
+            if let Some(result) = evaluation.step(this.env) {
+                this.evaluation = None;
+                match result {
+                    Ok(value) => this.result = Some(value),
+                    Err(error) => return Poll::Ready(Err(error)),
+                }
+            }
+        }
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+impl<'a> Drop for EvaluateAsync<'a> {
+    fn drop(&mut self) {
+        if let Some(evaluation) = &mut self.evaluation {
+            evaluation.abandon(self.env);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{evaluate_program, parse_program, EvalOptions};
+    use std::task::Waker;
+
+    #[test]
+    fn yields_while_evaluating() {
+        let source = vec!["x = x + 1"; 1000].join(";");
+        let program = parse_program(&format!("x = 0;{}", source)).unwrap();
+        let mut env = Environment::new();
+        let mut future = Box::pin(evaluate_async(&program, &mut env));
+        let mut context = Context::from_waker(Waker::noop());
+        let mut polls = 1;
+        let result = loop {
+            match future.as_mut().poll(&mut context) {
+                Poll::Ready(result) => break result,
+                Poll::Pending => polls += 1,
+            }
+        };
+        assert_eq!(result, Ok(Some(Object::Integer(1000))));
+        assert!(polls > 1);
+    }
+
+    #[test]
+    fn dropping_stops_the_evaluation() {
+        let program = parse_program(&vec!["-(-(1))"; 1000].join(";")).unwrap();
+        let mut env = Environment::with_options(EvalOptions {
+            max_depth: 4,
+            ..EvalOptions::default()
+        });
+        let mut future = Box::pin(evaluate_async(&program, &mut env));
+        let mut context = Context::from_waker(Waker::noop());
+        assert!(future.as_mut().poll(&mut context).is_pending());
+        drop(future);
+        assert_eq!(
+            evaluate_program(&program, &mut env),
+            Ok(Some(Object::Integer(1)))
+        );
+    }
+}
@@ -0,0 +1,1392 @@
+#[cfg(feature = "regex")]
+use super::patterns::call_regex;
+use super::{Environment, EvalError, Object, Resource};
+use crate::diagnostics::suggest;
+use crate::json;
+use crate::token::Token;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Widest placeholder of format(), whatever the memory limit is
+const MAX_FORMAT_WIDTH: usize = 1 << 20;
+
+/// Returns a description of the error if the call fails
+type NativeFn = dyn Fn(&[Object]) -> Result<Object, String> + Send + Sync;
+
+/// A function registered by the application embedding sapo, see Environment::register_fn
+pub(crate) struct NativeFunction {
+    pub(crate) arity: usize,
+    pub(crate) function: Box<NativeFn>,
+}
+
+/// Names of the functions that are always available
+pub const BUILTINS: [&str; 40] = [
+    "args",
+    "assert",
+    "assert_eq",
+    "bool",
+    "clock",
+    "contains",
+    "enumerate",
+    "exit",
+    "format",
+    "group_by",
+    "input",
+    "insert",
+    "int",
+    "join",
+    "json_parse",
+    "json_stringify",
+    "lower",
+    "now",
+    "pop",
+    "print",
+    "push",
+    "rand_int",
+    "random",
+    "range",
+    "regex_find_all",
+    "regex_match",
+    "regex_replace",
+    "remove",
+    "replace",
+    "reverse",
+    "shuffle",
+    "sleep",
+    "sort",
+    "sort_by",
+    "split",
+    "str",
+    "trim",
+    "type",
+    "upper",
+    "zip",
+];
+
+/// Number of arguments the built-in function takes, None if there is no such function.
+/// Variadic functions take at least this many.
+pub fn builtin_arity(name: &str) -> Option<usize> {
+    match name {
+        "args" => Some(0),
+        "assert" => Some(2),
+        "assert_eq" => Some(2),
+        "bool" => Some(1),
+        "clock" => Some(0),
+        "contains" => Some(2),
+        "enumerate" => Some(1),
+        "exit" => Some(1),
+        "format" => Some(1),
+        "input" => Some(0),
+        "group_by" => Some(2),
+        "insert" => Some(3),
+        "int" => Some(1),
+        "join" => Some(2),
+        "json_parse" => Some(1),
+        "json_stringify" => Some(1),
+        "lower" => Some(1),
+        "now" => Some(0),
+        "pop" => Some(1),
+        "print" => Some(1),
+        "push" => Some(2),
+        "rand_int" => Some(2),
+        "random" => Some(0),
+        "range" => Some(1),
+        "regex_find_all" => Some(2),
+        "regex_match" => Some(2),
+        "regex_replace" => Some(3),
+        "remove" => Some(2),
+        "replace" => Some(3),
+        "reverse" => Some(1),
+        "shuffle" => Some(1),
+        "sleep" => Some(1),
+        "sort" => Some(1),
+        "sort_by" => Some(2),
+        "split" => Some(2),
+        "str" => Some(1),
+        "trim" => Some(1),
+        "type" => Some(1),
+        "upper" => Some(1),
+        "zip" => Some(2),
+        _ => None,
+    }
+}
+
+/// Calls the built-in or registered function name with already evaluated arguments
+/// token is the name of the function at the call site, used for errors
+pub fn call_builtin(
+    token: &Token,
+    name: &str,
+    mut arguments: Vec<Object>,
+    env: &mut Environment,
+) -> Result<Object, EvalError> {
+    let expected = match builtin_arity(name) {
+        Some(expected) => expected,
+        None => return call_native(token, name, arguments, env),
+    };
+    let too_many = builtin_max_arity(name).is_some_and(|max| arguments.len() > max);
+    if arguments.len() < expected || too_many {
+        return Err(EvalError::WrongNumberOfArguments(
+            token.clone(),
+            expected,
+            arguments.len(),
+        ));
+    }
+    match name {
+        "args" => Ok(Object::Array(
+            env.arguments()
+                .iter()
+                .map(|a| Object::String(a.clone()))
+                .collect(),
+        )),
+        "assert" => match (arguments[0].condition(env), &arguments[1]) {
+            (Some(true), Object::String(_)) => Ok(Object::Boolean(true)),
+            (Some(false), Object::String(message)) => {
+                Err(EvalError::AssertionFailed(token.clone(), message.clone()))
+            }
+            (Some(_), _) => Err(EvalError::InvalidArgument(token.clone(), "String")),
+            (None, _) => Err(EvalError::InvalidArgument(token.clone(), "Boolean")),
+        },
+        "assert_eq" => {
+            if arguments[0] == arguments[1] {
+                return Ok(Object::Boolean(true));
+            }
+            let message = format!("{} != {}", arguments[0], arguments[1]);
+            Err(EvalError::AssertionFailed(token.clone(), message))
+        }
+        "contains" => match &arguments[0] {
+            Object::Array(values) => Ok(Object::Boolean(values.contains(&arguments[1]))),
+            Object::String(string) => Ok(Object::Boolean(
+                string.contains(string_argument(token, &arguments, 1)?),
+            )),
+            _ => Err(EvalError::InvalidArgument(token.clone(), "String or Array")),
+        },
+        "lower" => Ok(Object::String(
+            string_argument(token, &arguments, 0)?.to_lowercase(),
+        )),
+        "replace" => {
+            let from = string_argument(token, &arguments, 1)?;
+            let to = string_argument(token, &arguments, 2)?;
+            Ok(Object::String(
+                string_argument(token, &arguments, 0)?.replace(from, to),
+            ))
+        }
+        "split" => {
+            let string = string_argument(token, &arguments, 0)?;
+            let separator = string_argument(token, &arguments, 1)?;
+            // an empty separator splits the string into its chars
+            let parts: Vec<Object> = if separator.is_empty() {
+                string
+                    .chars()
+                    .map(|c| Object::String(c.to_string()))
+                    .collect()
+            } else {
+                string
+                    .split(separator)
+                    .map(|part| Object::String(part.to_string()))
+                    .collect()
+            };
+            Ok(Object::Array(parts))
+        }
+        // builds the string once, other values are converted like str() does
+        "join" => {
+            let values = array_argument(token, &mut arguments, 0)?;
+            let separator = string_argument(token, &arguments, 1)?;
+            let parts: Vec<String> = values
+                .into_iter()
+                .map(|value| match value {
+                    Object::String(string) => string,
+                    value => value.to_string(),
+                })
+                .collect();
+            let length = parts.iter().map(|part| part.len()).sum::<usize>()
+                + separator.len() * parts.len().saturating_sub(1);
+            if length > env.options().max_memory.unwrap_or(usize::MAX) {
+                return Err(EvalError::ResourceExhausted(
+                    token.clone(),
+                    Resource::Memory,
+                ));
+            }
+            Ok(Object::String(parts.join(separator)))
+        }
+        // arrays are values like the others, so these return a changed copy
+        "insert" => {
+            let mut values = array_argument(token, &mut arguments, 0)?;
+            // inserting at the length appends
+            let index = match integer_argument(token, &arguments, 1)? {
+                index if index == values.len() as i32 => values.len(),
+                index => array_index(token, index, values.len())?,
+            };
+            values.insert(index, arguments.pop().unwrap());
+            Ok(Object::Array(values))
+        }
+        "pop" => {
+            let mut values = array_argument(token, &mut arguments, 0)?;
+            let index = array_index(token, -1, values.len())?;
+            values.remove(index);
+            Ok(Object::Array(values))
+        }
+        "push" => {
+            let mut values = array_argument(token, &mut arguments, 0)?;
+            values.push(arguments.pop().unwrap());
+            Ok(Object::Array(values))
+        }
+        "remove" => {
+            let mut values = array_argument(token, &mut arguments, 0)?;
+            let index = integer_argument(token, &arguments, 1)?;
+            values.remove(array_index(token, index, values.len())?);
+            Ok(Object::Array(values))
+        }
+        "reverse" => {
+            let mut values = array_argument(token, &mut arguments, 0)?;
+            values.reverse();
+            Ok(Object::Array(values))
+        }
+        "sort" => {
+            let mut values = array_argument(token, &mut arguments, 0)?;
+            // only values of the same type can be compared
+            let same_type = values
+                .windows(2)
+                .all(|pair| compare(&pair[0], &pair[1]).is_some());
+            if !same_type {
+                return Err(EvalError::InvalidArgument(
+                    token.clone(),
+                    "Array of Integers, Booleans or Strings",
+                ));
+            }
+            values.sort_by(|a, b| compare(a, b).unwrap());
+            Ok(Object::Array(values))
+        }
+        // the key of each value is the result of the function named by the second argument
+        "sort_by" => {
+            let values = array_argument(token, &mut arguments, 0)?;
+            let mut keyed = keys(token, values, &arguments[1], env)?;
+            let same_type = keyed
+                .windows(2)
+                .all(|pair| compare(&pair[0].0, &pair[1].0).is_some());
+            if !same_type {
+                return Err(EvalError::InvalidArgument(
+                    token.clone(),
+                    "function returning Integers, Booleans or Strings",
+                ));
+            }
+            keyed.sort_by(|a, b| compare(&a.0, &b.0).unwrap());
+            Ok(Object::Array(keyed.into_iter().map(|(_, v)| v).collect()))
+        }
+        "group_by" => {
+            let values = array_argument(token, &mut arguments, 0)?;
+            // in the order of the first value of each group
+            let mut groups: Vec<(Object, Vec<Object>)> = Vec::new();
+            for (key, value) in keys(token, values, &arguments[1], env)? {
+                match groups.iter_mut().find(|(k, _)| *k == key) {
+                    Some((_, group)) => group.push(value),
+                    None => groups.push((key, vec![value])),
+                }
+            }
+            let groups = groups
+                .into_iter()
+                .map(|(key, group)| Object::Tuple(vec![key, Object::Array(group)]));
+            Ok(Object::Array(groups.collect()))
+        }
+        "zip" => {
+            let left = array_argument(token, &mut arguments, 0)?;
+            let right = array_argument(token, &mut arguments, 1)?;
+            // as long as the shorter one
+            let pairs = left
+                .into_iter()
+                .zip(right)
+                .map(|(l, r)| Object::Tuple(vec![l, r]));
+            Ok(Object::Array(pairs.collect()))
+        }
+        "enumerate" => {
+            let values = array_argument(token, &mut arguments, 0)?;
+            let pairs = (0..)
+                .zip(values)
+                .map(|(i, value)| Object::Tuple(vec![Object::Integer(i), value]));
+            Ok(Object::Array(pairs.collect()))
+        }
+        // range(end) or range(start, end), without end
+        "range" => {
+            let (start, end) = match arguments.len() {
+                1 => (0, integer_argument(token, &arguments, 0)?),
+                _ => (
+                    integer_argument(token, &arguments, 0)?,
+                    integer_argument(token, &arguments, 1)?,
+                ),
+            };
+            let count = (end as i64 - start as i64).max(0) as usize;
+            let max = env.options().max_memory.unwrap_or(usize::MAX);
+            if count.saturating_mul(std::mem::size_of::<Object>()) > max {
+                return Err(EvalError::ResourceExhausted(
+                    token.clone(),
+                    Resource::Memory,
+                ));
+            }
+            Ok(Object::Array((start..end).map(Object::Integer).collect()))
+        }
+        "trim" => Ok(Object::String(
+            string_argument(token, &arguments, 0)?.trim().to_string(),
+        )),
+        "type" => Ok(Object::String(arguments[0].type_name().to_string())),
+        "now" => {
+            require_clock(token)?;
+            let seconds = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs());
+            Ok(Object::Integer(seconds.min(i32::MAX as u64) as i32))
+        }
+        "clock" => {
+            require_clock(token)?;
+            // only the differences between the values are meaningful
+            static START: OnceLock<Instant> = OnceLock::new();
+            let milliseconds = START.get_or_init(Instant::now).elapsed().as_millis();
+            Ok(Object::Integer(milliseconds.min(i32::MAX as u128) as i32))
+        }
+        "sleep" => {
+            require_clock(token)?;
+            let milliseconds = integer_argument(token, &arguments, 0)?;
+            if milliseconds < 0 {
+                return Err(EvalError::InvalidArgument(
+                    token.clone(),
+                    "non-negative Integer",
+                ));
+            }
+            env.sleep(token, Duration::from_millis(milliseconds as u64))?;
+            Ok(Object::Integer(milliseconds))
+        }
+        "random" => {
+            let number = env.random().below(i32::MAX as u64 + 1);
+            Ok(Object::Integer(number as i32))
+        }
+        "rand_int" => {
+            let low = integer_argument(token, &arguments, 0)?;
+            let high = integer_argument(token, &arguments, 1)?;
+            if low > high {
+                return Err(EvalError::InvalidArgument(token.clone(), "lo <= hi"));
+            }
+            let count = (high as i64 - low as i64) as u64 + 1;
+            let number = low as i64 + env.random().below(count) as i64;
+            Ok(Object::Integer(number as i32))
+        }
+        "shuffle" => {
+            let mut values = array_argument(token, &mut arguments, 0)?;
+            // Fisher-Yates
+            for i in (1..values.len()).rev() {
+                let j = env.random().below(i as u64 + 1) as usize;
+                values.swap(i, j);
+            }
+            Ok(Object::Array(values))
+        }
+        "format" => {
+            let template = string_argument(token, &arguments, 0)?;
+            let max = env.options().max_memory.unwrap_or(usize::MAX);
+            Ok(Object::String(format(
+                token,
+                template,
+                &arguments[1..],
+                max,
+            )?))
+        }
+        "json_parse" => {
+            let text = string_argument(token, &arguments, 0)?;
+            let value = json::parse(text).map_err(|e| EvalError::InvalidJson(token.clone(), e))?;
+            from_json(token, value)
+        }
+        "json_stringify" => match arguments.get(1) {
+            None => Ok(Object::String(arguments[0].to_json())),
+            Some(Object::Boolean(pretty)) => Ok(Object::String(if *pretty {
+                arguments[0].to_json_pretty()
+            } else {
+                arguments[0].to_json()
+            })),
+            Some(_) => Err(EvalError::InvalidArgument(token.clone(), "Boolean")),
+        },
+        "regex_match" | "regex_find_all" | "regex_replace" => {
+            call_regex(token, name, &arguments, env)
+        }
+        "int" => match &arguments[0] {
+            Object::Integer(value) => Ok(Object::Integer(*value)),
+            Object::Boolean(value) => Ok(Object::Integer(*value as i32)),
+            Object::String(string) => match string.trim().parse() {
+                Ok(value) => Ok(Object::Integer(value)),
+                Err(_) => Err(conversion_failed(token, string, "Integer")),
+            },
+            _ => Err(EvalError::InvalidArgument(
+                token.clone(),
+                "Integer, Boolean or String",
+            )),
+        },
+        "str" => match arguments.into_iter().next().unwrap() {
+            Object::String(string) => Ok(Object::String(string)),
+            value => Ok(Object::String(value.to_string())),
+        },
+        "bool" => match &arguments[0] {
+            Object::Boolean(value) => Ok(Object::Boolean(*value)),
+            Object::Integer(value) => Ok(Object::Boolean(*value != 0)),
+            Object::String(string) => match string.trim() {
+                "true" => Ok(Object::Boolean(true)),
+                "false" => Ok(Object::Boolean(false)),
+                _ => Err(conversion_failed(token, string, "Boolean")),
+            },
+            _ => Err(EvalError::InvalidArgument(
+                token.clone(),
+                "Integer, Boolean or String",
+            )),
+        },
+        "upper" => Ok(Object::String(
+            string_argument(token, &arguments, 0)?.to_uppercase(),
+        )),
+        "exit" => match arguments[0] {
+            Object::Integer(code) => Err(EvalError::Exit(token.clone(), code)),
+            _ => Err(EvalError::InvalidArgument(token.clone(), "Integer")),
+        },
+        "print" => {
+            let value = arguments.into_iter().next().unwrap();
+            // strings are printed without quotes
+            let result = match &value {
+                Object::String(text) => writeln!(env.output(), "{}", text),
+                _ => writeln!(env.output(), "{}", value),
+            };
+            match result {
+                Ok(()) => Ok(value),
+                Err(error) => Err(EvalError::FunctionFailed(token.clone(), error.to_string())),
+            }
+        }
+        "input" => {
+            let mut line = String::new();
+            // at the end of the input the line is empty
+            if let Err(error) = env.input().read_line(&mut line) {
+                return Err(EvalError::FunctionFailed(token.clone(), error.to_string()));
+            }
+            let length = line.trim_end_matches(['\n', '\r']).len();
+            line.truncate(length);
+            Ok(Object::String(line))
+        }
+        _ => unreachable!("'{}' has an arity but no implementation", name),
+    }
+}
+
+/// The argument at index, which must be a String
+fn string_argument<'a>(
+    token: &Token,
+    arguments: &'a [Object],
+    index: usize,
+) -> Result<&'a str, EvalError> {
+    match &arguments[index] {
+        Object::String(value) => Ok(value),
+        _ => Err(EvalError::InvalidArgument(token.clone(), "String")),
+    }
+}
+
+/// The regex built-ins fail when sapo is built without the regex crate
+#[cfg(not(feature = "regex"))]
+fn call_regex(
+    token: &Token,
+    _name: &str,
+    _arguments: &[Object],
+    _env: &mut Environment,
+) -> Result<Object, EvalError> {
+    let message = String::from("sapo was built without the regex feature");
+    Err(EvalError::FunctionFailed(token.clone(), message))
+}
+
+/// Each value with its key, the result of calling the built-in or registered function
+/// named by function with it
+fn keys(
+    token: &Token,
+    values: Vec<Object>,
+    function: &Object,
+    env: &mut Environment,
+) -> Result<Vec<(Object, Object)>, EvalError> {
+    let name = match function {
+        Object::String(name) => name,
+        _ => {
+            return Err(EvalError::InvalidArgument(
+                token.clone(),
+                "String naming a function",
+            ))
+        }
+    };
+    // errors of the function point to the call that passed it
+    let mut function = token.clone();
+    function.lexeme = name.as_str().into();
+    values
+        .into_iter()
+        .map(|value| {
+            let key = call_builtin(&function, name, vec![value.clone()], env)?;
+            Ok((key, value))
+        })
+        .collect()
+}
+
+/// Fails on platforms without a clock, like WebAssembly in browsers
+fn require_clock(token: &Token) -> Result<(), EvalError> {
+    if cfg!(all(target_arch = "wasm32", not(target_os = "wasi"))) {
+        let message = String::from("there is no clock on this platform");
+        return Err(EvalError::FunctionFailed(token.clone(), message));
+    }
+    Ok(())
+}
+
+/// Most arguments the built-in function takes, None if there is no limit. Only
+/// variadic functions take more than their arity.
+pub fn builtin_max_arity(name: &str) -> Option<usize> {
+    match name {
+        "format" => None,
+        "json_stringify" | "range" => Some(2),
+        _ => builtin_arity(name),
+    }
+}
+
+/// Whether the built-in function takes more arguments than the ones of its arity
+pub fn is_variadic(name: &str) -> bool {
+    builtin_max_arity(name) != builtin_arity(name)
+}
+
+/// The template with each {} replaced by the next value, like str() converts it, and {{
+/// and }} replaced by braces. A placeholder can have a width, e.g. {:5}, and be aligned to
+/// the left or the right of it with {:<5} or {:>5}. Integers are aligned to the right by
+/// default and padded with zeros after their sign with {:05}, other values to the left.
+/// Placeholders wider than MAX_FORMAT_WIDTH or than max fail before allocating them.
+fn format(
+    token: &Token,
+    template: &str,
+    values: &[Object],
+    max: usize,
+) -> Result<String, EvalError> {
+    let invalid = |description: String| EvalError::InvalidFormat(token.clone(), description);
+    let mut result = String::new();
+    let mut values = values.iter();
+    let mut rest = template;
+    while let Some(position) = rest.find(['{', '}']) {
+        result.push_str(&rest[..position]);
+        let brace = &rest[position..position + 1];
+        rest = &rest[position + 1..];
+        if rest.starts_with(brace) {
+            result.push_str(brace);
+            rest = &rest[1..];
+            continue;
+        }
+        if brace == "}" {
+            return Err(invalid(String::from("'}' without an opening '{'")));
+        }
+        let end = rest
+            .find('}')
+            .ok_or_else(|| invalid(String::from("'{' without a closing '}'")))?;
+        let spec = &rest[..end];
+        rest = &rest[end + 1..];
+        let placeholder = || invalid(format!("invalid placeholder '{{{}}}'", spec));
+        let spec = match spec.strip_prefix(':') {
+            Some(spec) => spec,
+            None if spec.is_empty() => spec,
+            None => return Err(placeholder()),
+        };
+        let (align, spec) = match spec.chars().next() {
+            Some(align @ '<') | Some(align @ '>') => (Some(align), &spec[1..]),
+            _ => (None, spec),
+        };
+        let (zeros, spec) = match spec.strip_prefix('0') {
+            Some(spec) => (true, spec),
+            None => (false, spec),
+        };
+        let width = match spec {
+            "" => 0,
+            digits if digits.bytes().all(|b| b.is_ascii_digit()) => {
+                digits.parse().map_err(|_| placeholder())?
+            }
+            _ => return Err(placeholder()),
+        };
+        if width > MAX_FORMAT_WIDTH {
+            return Err(invalid(format!(
+                "width {} is larger than the maximum of {}",
+                width, MAX_FORMAT_WIDTH
+            )));
+        }
+        if width > max {
+            return Err(EvalError::ResourceExhausted(
+                token.clone(),
+                Resource::Memory,
+            ));
+        }
+        let value = values
+            .next()
+            .ok_or_else(|| invalid(String::from("more placeholders than values")))?;
+        // Padded by hand: std's width is limited to u16::MAX.
+        let text = match value {
+            Object::String(text) => text.clone(),
+            value => value.to_string(),
+        };
+        let padding = width.saturating_sub(text.chars().count());
+        let right = align.map_or(matches!(value, Object::Integer(_)), |align| align == '>');
+        match (value, align, zeros) {
+            (Object::Integer(_), None, true) => {
+                let digits = match text.strip_prefix('-') {
+                    Some(digits) => {
+                        result.push('-');
+                        digits
+                    }
+                    None => &text,
+                };
+                result.push_str(&"0".repeat(padding));
+                result.push_str(digits);
+            }
+            _ if right => {
+                result.push_str(&" ".repeat(padding));
+                result.push_str(&text);
+            }
+            _ => {
+                result.push_str(&text);
+                result.push_str(&" ".repeat(padding));
+            }
+        }
+    }
+    result.push_str(rest);
+    match values.next() {
+        Some(_) => Err(invalid(String::from("more values than placeholders"))),
+        None => Ok(result),
+    }
+}
+
+fn conversion_failed(token: &Token, string: &str, type_name: &'static str) -> EvalError {
+    EvalError::ConversionFailed(token.clone(), string.to_string(), type_name)
+}
+
+/// The sapo value of a JSON document, objects are arrays of (key, value) tuples
+fn from_json(token: &Token, value: json::Value) -> Result<Object, EvalError> {
+    let invalid =
+        |description: &str| EvalError::InvalidJson(token.clone(), description.to_string());
+    match value {
+        json::Value::Null => Err(invalid("null has no value in sapo")),
+        json::Value::Bool(value) => Ok(Object::Boolean(value)),
+        json::Value::Number(value) => match i32::try_from(value) {
+            Ok(value) => Ok(Object::Integer(value)),
+            Err(_) => Err(invalid(&format!(
+                "{} is out of the range of Integer",
+                value
+            ))),
+        },
+        json::Value::String(value) => Ok(Object::String(value)),
+        json::Value::Array(values) => values
+            .into_iter()
+            .map(|value| from_json(token, value))
+            .collect::<Result<_, _>>()
+            .map(Object::Array),
+        json::Value::Object(members) => members
+            .into_iter()
+            .map(|(key, value)| {
+                let value = from_json(token, value)?;
+                Ok(Object::Tuple(vec![Object::String(key), value]))
+            })
+            .collect::<Result<_, _>>()
+            .map(Object::Array),
+    }
+}
+
+/// The argument at index, which must be an Integer
+fn integer_argument(token: &Token, arguments: &[Object], index: usize) -> Result<i32, EvalError> {
+    match arguments[index] {
+        Object::Integer(value) => Ok(value),
+        _ => Err(EvalError::InvalidArgument(token.clone(), "Integer")),
+    }
+}
+
+/// Takes the argument at index, which must be an Array, leaving an empty one in its place
+fn array_argument(
+    token: &Token,
+    arguments: &mut [Object],
+    index: usize,
+) -> Result<Vec<Object>, EvalError> {
+    match &mut arguments[index] {
+        Object::Array(values) => Ok(std::mem::take(values)),
+        _ => Err(EvalError::InvalidArgument(token.clone(), "Array")),
+    }
+}
+
+/// The position in an array or string of length of index, negative indices count from the end
+pub(crate) fn array_index(token: &Token, index: i32, length: usize) -> Result<usize, EvalError> {
+    let position = if index < 0 {
+        length as i64 + index as i64
+    } else {
+        index as i64
+    };
+    if position < 0 || position >= length as i64 {
+        return Err(EvalError::IndexOutOfBounds(token.clone(), index, length));
+    }
+    Ok(position as usize)
+}
+
+/// Order of two values of the same type, None for values that can't be compared
+fn compare(a: &Object, b: &Object) -> Option<Ordering> {
+    match (a, b) {
+        (Object::Integer(a), Object::Integer(b)) => Some(a.cmp(b)),
+        (Object::Boolean(a), Object::Boolean(b)) => Some(a.cmp(b)),
+        (Object::String(a), Object::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+/// Calls a function registered with Environment::register_fn
+fn call_native(
+    token: &Token,
+    name: &str,
+    arguments: Vec<Object>,
+    env: &Environment,
+) -> Result<Object, EvalError> {
+    let native = match env.function(name) {
+        Some(native) => native,
+        None => {
+            let mut names = env.function_names();
+            names.extend(&BUILTINS);
+            let suggestion = suggest(name, &names).map(|s| s.to_string());
+            return Err(EvalError::UndefinedFunction(token.clone(), suggestion));
+        }
+    };
+    if arguments.len() != native.arity {
+        return Err(EvalError::WrongNumberOfArguments(
+            token.clone(),
+            native.arity,
+            arguments.len(),
+        ));
+    }
+    (native.function)(&arguments)
+        .map_err(|message| EvalError::FunctionFailed(token.clone(), message))
+}
+
+/// Calls an operator registered with Environment::register_operator, token is the operator
+pub(crate) fn call_operator(
+    token: &Token,
+    prefix: bool,
+    arguments: Vec<Object>,
+    env: &Environment,
+) -> Result<Object, EvalError> {
+    match env.operator(&token.lexeme, prefix) {
+        Some(native) => (native.function)(&arguments)
+            .map_err(|message| EvalError::FunctionFailed(token.clone(), message)),
+        None => Err(EvalError::InvalidOperator(token.clone())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::TokenType;
+    use crate::EvalOptions;
+    use std::io;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn args() {
+        let mut env = Environment::new();
+        env.set_arguments(vec![String::from("a"), String::from("b")]);
+        let result = call_builtin(&token("args"), "args", vec![], &mut env).unwrap();
+        assert_eq!(
+            result,
+            Object::Array(vec![
+                Object::String(String::from("a")),
+                Object::String(String::from("b"))
+            ])
+        );
+        assert_eq!(result.to_string(), "[\"a\", \"b\"]");
+    }
+
+    #[test]
+    fn exit() {
+        let error = call_builtin(
+            &token("exit"),
+            "exit",
+            vec![Object::Integer(3)],
+            &mut Environment::new(),
+        )
+        .unwrap_err();
+        assert_eq!(error, EvalError::Exit(token("exit"), 3));
+    }
+
+    #[test]
+    fn assertions() {
+        let mut env = Environment::new();
+        let message = Object::String(String::from("x is positive"));
+        let arguments = vec![Object::Boolean(true), message.clone()];
+        let result = call_builtin(&token("assert"), "assert", arguments, &mut env);
+        assert_eq!(result, Ok(Object::Boolean(true)));
+
+        let arguments = vec![Object::Boolean(false), message];
+        let error = call_builtin(&token("assert"), "assert", arguments, &mut env).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Error at line 1: Assertion failed: x is positive [E0019]"
+        );
+
+        let arguments = vec![Object::Integer(4), Object::String(String::from("4"))];
+        let error = call_builtin(&token("assert_eq"), "assert_eq", arguments, &mut env);
+        assert_eq!(
+            error,
+            Err(EvalError::AssertionFailed(
+                token("assert_eq"),
+                String::from("4 != \"4\"")
+            ))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Error at line 1: 'exit' expected 1 argument(s), but 0 were given")]
+    fn wrong_number_of_arguments() {
+        if let Err(error) = call_builtin(&token("exit"), "exit", vec![], &mut Environment::new()) {
+            panic!("{}", error);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Error at line 1: Invalid argument for 'exit', expected Integer")]
+    fn invalid_argument() {
+        let arguments = vec![Object::Boolean(true)];
+        if let Err(error) = call_builtin(&token("exit"), "exit", arguments, &mut Environment::new())
+        {
+            panic!("{}", error);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Error at line 1: Undefined function 'arg', did you mean 'args'?")]
+    fn undefined_function() {
+        if let Err(error) = call_builtin(&token("arg"), "arg", vec![], &mut Environment::new()) {
+            panic!("{}", error);
+        }
+    }
+
+    #[test]
+    fn call_registered_function() {
+        let mut env = Environment::new();
+        env.register_fn("double", 1, |arguments| match arguments[0] {
+            Object::Integer(value) => Ok(Object::Integer(value * 2)),
+            _ => Err("expected an Integer"),
+        });
+        let result = call_builtin(
+            &token("double"),
+            "double",
+            vec![Object::Integer(4)],
+            &mut env,
+        );
+        assert_eq!(result, Ok(Object::Integer(8)));
+
+        let error = call_builtin(&token("double"), "double", vec![], &mut env).unwrap_err();
+        assert_eq!(
+            error,
+            EvalError::WrongNumberOfArguments(token("double"), 1, 0)
+        );
+
+        let arguments = vec![Object::Boolean(true)];
+        let error = call_builtin(&token("double"), "double", arguments, &mut env).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Error at line 1: 'double' failed: expected an Integer [E0017]"
+        );
+
+        let error = call_builtin(&token("doubel"), "doubel", vec![], &mut env).unwrap_err();
+        assert_eq!(
+            error,
+            EvalError::UndefinedFunction(token("doubel"), Some(String::from("double")))
+        );
+    }
+
+    #[test]
+    fn strings() {
+        let call = |name: &str, arguments: &[&str]| {
+            let arguments = arguments
+                .iter()
+                .map(|a| Object::String(a.to_string()))
+                .collect();
+            call_builtin(&token(name), name, arguments, &mut Environment::new())
+                .map(|value| value.to_string())
+        };
+        assert_eq!(
+            call("split", &["a,b,", ","]),
+            Ok(r#"["a", "b", ""]"#.into())
+        );
+        assert_eq!(call("split", &["añb", ""]), Ok(r#"["a", "ñ", "b"]"#.into()));
+        assert_eq!(call("trim", &["\t sapo\n"]), Ok(r#""sapo""#.into()));
+        assert_eq!(call("upper", &["Straße"]), Ok(r#""STRASSE""#.into()));
+        assert_eq!(call("lower", &["SAPO"]), Ok(r#""sapo""#.into()));
+        assert_eq!(call("contains", &["sapo", "ap"]), Ok("true".into()));
+        assert_eq!(
+            call("replace", &["a-b-c", "-", "+"]),
+            Ok(r#""a+b+c""#.into())
+        );
+        let arguments = vec![Object::String("sapo".into()), Object::Integer(1)];
+        let error = call_builtin(
+            &token("contains"),
+            "contains",
+            arguments,
+            &mut Environment::new(),
+        );
+        assert_eq!(
+            error,
+            Err(EvalError::InvalidArgument(token("contains"), "String"))
+        );
+    }
+
+    #[test]
+    fn join() {
+        let call = |arguments: Vec<Object>, env: &mut Environment| {
+            call_builtin(&token("join"), "join", arguments, env)
+        };
+        let string = |s: &str| Object::String(s.to_string());
+        let values = Object::Array(vec![
+            string("a"),
+            Object::Integer(1),
+            Object::Tuple(vec![string("b")]),
+        ]);
+        let mut env = Environment::new();
+        assert_eq!(
+            call(vec![values.clone(), string(", ")], &mut env),
+            Ok(string(r#"a, 1, ("b",)"#))
+        );
+        assert_eq!(
+            call(vec![Object::Array(vec![]), string("-")], &mut env),
+            Ok(string(""))
+        );
+        let mut env = Environment::with_options(EvalOptions {
+            max_memory: Some(10),
+            ..EvalOptions::default()
+        });
+        assert_eq!(
+            call(vec![values, string(", ")], &mut env),
+            Err(EvalError::ResourceExhausted(
+                token("join"),
+                Resource::Memory
+            ))
+        );
+    }
+
+    #[test]
+    fn format_values() {
+        let call = |arguments: Vec<Object>| {
+            call_builtin(
+                &token("format"),
+                "format",
+                arguments,
+                &mut Environment::new(),
+            )
+            .map_err(|e| e.message())
+        };
+        let string = |s: &str| Object::String(s.to_string());
+        let arguments = vec![
+            string("x={} y={:04} {{{:>3}}} {:<4}|{:3}|"),
+            Object::Integer(1),
+            Object::Integer(-7),
+            string("ñ"),
+            Object::Boolean(true),
+            Object::Integer(42),
+        ];
+        assert_eq!(call(arguments), Ok(string("x=1 y=-007 {  ñ} true| 42|")));
+        assert_eq!(
+            call(vec![string("{}")]),
+            Err(String::from(
+                "Invalid format string: more placeholders than values"
+            ))
+        );
+        let error = call(vec![string("{:x}"), Object::Integer(1)]);
+        assert_eq!(
+            error,
+            Err(String::from(
+                "Invalid format string: invalid placeholder '{:x}'"
+            ))
+        );
+        let error = call(vec![string("}"), Object::Integer(1)]);
+        assert!(error.unwrap_err().contains("'}' without an opening '{'"));
+        let wide = call(vec![string("{:070000}"), Object::Integer(-1)]);
+        let wide = match wide {
+            Ok(Object::String(wide)) => wide,
+            other => panic!("format() didn't return a string: {:?}", other),
+        };
+        assert_eq!(wide.len(), 70000);
+        assert!(wide.starts_with("-00") && wide.ends_with("01"));
+        assert_eq!(
+            call(vec![string("{:99999999999}"), Object::Integer(1)]),
+            Err(String::from(
+                "Invalid format string: width 99999999999 is larger than the maximum of 1048576"
+            ))
+        );
+        assert_eq!(
+            call(vec![]),
+            Err(String::from(
+                "'format' expected at least 1 argument(s), but 0 were given"
+            ))
+        );
+    }
+
+    #[test]
+    fn random_numbers() {
+        let mut env = Environment::with_options(EvalOptions {
+            seed: Some(42),
+            ..EvalOptions::default()
+        });
+        let call = |name: &str, arguments: Vec<Object>, env: &mut Environment| {
+            call_builtin(&token(name), name, arguments, env)
+        };
+        let range = vec![Object::Integer(-2), Object::Integer(2)];
+        let numbers: Vec<Object> = (0..20)
+            .map(|_| call("rand_int", range.clone(), &mut env).unwrap())
+            .collect();
+        assert!(numbers.iter().all(|n| matches!(n, Object::Integer(-2..=2))));
+        let values: Vec<Object> = (0..10).map(Object::Integer).collect();
+        let shuffled = call("shuffle", vec![Object::Array(values.clone())], &mut env);
+        let mut sorted = match shuffled {
+            Ok(Object::Array(shuffled)) => shuffled,
+            other => panic!("shuffle() didn't return an array: {:?}", other),
+        };
+        assert_ne!(sorted, values);
+        sorted.sort_by(|a, b| compare(a, b).unwrap());
+        assert_eq!(sorted, values);
+        // the same seed gives the same numbers
+        env.set_options(EvalOptions::default());
+        env.set_options(EvalOptions {
+            seed: Some(42),
+            ..EvalOptions::default()
+        });
+        let again: Vec<Object> = (0..20)
+            .map(|_| call("rand_int", range.clone(), &mut env).unwrap())
+            .collect();
+        assert_eq!(again, numbers);
+        let full = vec![Object::Integer(i32::MIN), Object::Integer(i32::MAX)];
+        assert!(call("rand_int", full, &mut env).is_ok());
+        let empty = vec![Object::Integer(1), Object::Integer(0)];
+        assert_eq!(
+            call("rand_int", empty, &mut env),
+            Err(EvalError::InvalidArgument(token("rand_int"), "lo <= hi"))
+        );
+    }
+
+    #[test]
+    fn time() {
+        let mut env = Environment::with_options(EvalOptions {
+            timeout: Some(Duration::from_millis(20)),
+            ..EvalOptions::default()
+        });
+        let mut call = |name: &str, arguments: Vec<Object>| {
+            call_builtin(&token(name), name, arguments, &mut env)
+        };
+        let start = match call("clock", vec![]) {
+            Ok(Object::Integer(start)) => start,
+            other => panic!("clock() didn't return an integer: {:?}", other),
+        };
+        assert_eq!(
+            call("sleep", vec![Object::Integer(5)]),
+            Ok(Object::Integer(5))
+        );
+        assert!(matches!(call("clock", vec![]), Ok(Object::Integer(end)) if end >= start + 5));
+        assert!(matches!(call("now", vec![]), Ok(Object::Integer(now)) if now > 1_700_000_000));
+        assert_eq!(
+            call("sleep", vec![Object::Integer(-1)]),
+            Err(EvalError::InvalidArgument(
+                token("sleep"),
+                "non-negative Integer"
+            ))
+        );
+        // sleeping stops at the timeout of the evaluation and when it is cancelled
+        env.start();
+        let started = Instant::now();
+        let result = call_builtin(
+            &token("sleep"),
+            "sleep",
+            vec![Object::Integer(10_000)],
+            &mut env,
+        );
+        assert_eq!(
+            result,
+            Err(EvalError::ResourceExhausted(token("sleep"), Resource::Time))
+        );
+        env.set_options(EvalOptions::default());
+        env.cancellation_token().cancel();
+        let result = call_builtin(
+            &token("sleep"),
+            "sleep",
+            vec![Object::Integer(10_000)],
+            &mut env,
+        );
+        assert_eq!(result, Err(EvalError::Cancelled(token("sleep"))));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn conversions() {
+        let call = |name: &str, argument: Object| {
+            call_builtin(&token(name), name, vec![argument], &mut Environment::new())
+        };
+        let string = |s: &str| Object::String(s.to_string());
+        assert_eq!(call("int", string(" -12\n")), Ok(Object::Integer(-12)));
+        assert_eq!(call("int", Object::Boolean(true)), Ok(Object::Integer(1)));
+        assert_eq!(
+            call("int", string("99999999999")),
+            Err(EvalError::ConversionFailed(
+                token("int"),
+                "99999999999".into(),
+                "Integer"
+            ))
+        );
+        assert_eq!(call("bool", Object::Integer(-1)), Ok(Object::Boolean(true)));
+        assert_eq!(call("bool", string("false")), Ok(Object::Boolean(false)));
+        assert_eq!(
+            call("bool", string("no")).map_err(|e| e.code()),
+            Err("E0030")
+        );
+        assert_eq!(call("str", string("ñ")), Ok(string("ñ")));
+        let tuple = Object::Tuple(vec![Object::Integer(1), string("a")]);
+        assert_eq!(call("str", tuple.clone()), Ok(string(r#"(1, "a")"#)));
+        assert_eq!(
+            call("int", tuple),
+            Err(EvalError::InvalidArgument(
+                token("int"),
+                "Integer, Boolean or String"
+            ))
+        );
+    }
+
+    #[test]
+    fn json() {
+        let call = |name: &str, arguments: Vec<Object>| {
+            call_builtin(&token(name), name, arguments, &mut Environment::new())
+        };
+        let string = |s: &str| Object::String(s.to_string());
+        let document = string(r#" {"a": [1, true, "\u00f1"], "b": {}} "#);
+        let value = Object::Array(vec![
+            Object::Tuple(vec![
+                string("a"),
+                Object::Array(vec![Object::Integer(1), Object::Boolean(true), string("ñ")]),
+            ]),
+            Object::Tuple(vec![string("b"), Object::Array(vec![])]),
+        ]);
+        assert_eq!(call("json_parse", vec![document]), Ok(value.clone()));
+        assert_eq!(
+            call("json_stringify", vec![value.clone()]),
+            Ok(string(r#"[["a",[1,true,"ñ"]],["b",[]]]"#))
+        );
+        assert_eq!(
+            call(
+                "json_stringify",
+                vec![Object::Integer(1), Object::Boolean(true)]
+            ),
+            Ok(string("1"))
+        );
+        assert_eq!(
+            call("json_stringify", vec![value, Object::Integer(1)]),
+            Err(EvalError::InvalidArgument(
+                token("json_stringify"),
+                "Boolean"
+            ))
+        );
+        let arguments = vec![
+            Object::Integer(1),
+            Object::Boolean(true),
+            Object::Boolean(true),
+        ];
+        assert_eq!(
+            call("json_stringify", arguments),
+            Err(EvalError::WrongNumberOfArguments(
+                token("json_stringify"),
+                1,
+                3
+            ))
+        );
+        let invalid = |document: &str| match call("json_parse", vec![string(document)]) {
+            Err(EvalError::InvalidJson(_, description)) => description,
+            result => panic!("{:?}", result),
+        };
+        assert_eq!(invalid("[1,]"), "expected a value at byte 3");
+        assert_eq!(invalid("[null]"), "null has no value in sapo");
+        assert_eq!(
+            invalid("2147483648"),
+            "2147483648 is out of the range of Integer"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "regex")]
+    fn regular_expressions() {
+        let mut env = Environment::new();
+        let mut call = |name: &str, arguments: &[&str]| {
+            let arguments = arguments.iter().map(|a| Object::String(a.to_string()));
+            call_builtin(&token(name), name, arguments.collect(), &mut env)
+        };
+        let string = |s: &str| Object::String(s.to_string());
+        assert_eq!(
+            call("regex_match", &["frog42", r"^\w+\d$"]),
+            Ok(Object::Boolean(true))
+        );
+        assert_eq!(
+            call("regex_find_all", &["a1b22c", "[0-9]+"]),
+            Ok(Object::Array(vec![string("1"), string("22")]))
+        );
+        assert_eq!(
+            call("regex_replace", &["2024-06", r"(\d+)-(\d+)", "$2/$1"]),
+            Ok(string("06/2024"))
+        );
+        assert_eq!(
+            call("regex_match", &["f(x)", "(x"]),
+            Err(EvalError::InvalidPattern(
+                token("regex_match"),
+                "unclosed group".to_string()
+            ))
+        );
+        assert_eq!(env.patterns().compiled.len(), 3);
+    }
+
+    #[test]
+    fn combinators() {
+        let mut env = Environment::new();
+        env.register_fn("negate", 1, |arguments| match arguments[0] {
+            Object::Integer(value) => Ok(Object::Integer(-value)),
+            _ => Err("expected an Integer"),
+        });
+        let mut call = |name: &str, arguments: Vec<Object>| {
+            call_builtin(&token(name), name, arguments, &mut env)
+        };
+        let array =
+            |values: &[i32]| Object::Array(values.iter().map(|&v| Object::Integer(v)).collect());
+        let name = |s: &str| Object::String(s.to_string());
+        assert_eq!(
+            call("range", vec![Object::Integer(4)]),
+            Ok(array(&[0, 1, 2, 3]))
+        );
+        let values = array(&[2, -3, 1]);
+        assert_eq!(
+            call("sort_by", vec![values.clone(), name("negate")]),
+            Ok(array(&[2, 1, -3]))
+        );
+        assert_eq!(
+            call("group_by", vec![values.clone(), name("type")]),
+            Ok(Object::Array(vec![Object::Tuple(vec![
+                name("Integer"),
+                values.clone()
+            ])]))
+        );
+        assert_eq!(
+            call("sort_by", vec![values.clone(), name("str")]),
+            Ok(array(&[-3, 1, 2]))
+        );
+        assert_eq!(
+            call("sort_by", vec![values.clone(), Object::Integer(1)]),
+            Err(EvalError::InvalidArgument(
+                token("sort_by"),
+                "String naming a function"
+            ))
+        );
+        assert_eq!(
+            call("group_by", vec![values.clone(), name("pop")]),
+            Err(EvalError::InvalidArgument(token("pop"), "Array"))
+        );
+        let arguments = vec![Object::Integer(0), Object::Integer(1), Object::Integer(2)];
+        assert_eq!(
+            call("range", arguments),
+            Err(EvalError::WrongNumberOfArguments(token("range"), 1, 3))
+        );
+        assert_eq!(
+            call("range", vec![]).map_err(|e| e.message()),
+            Err(String::from(
+                "'range' expected 1 to 2 argument(s), but 0 were given"
+            ))
+        );
+        let mut env = Environment::with_options(EvalOptions {
+            max_memory: Some(1000),
+            ..EvalOptions::default()
+        });
+        assert_eq!(
+            call_builtin(
+                &token("range"),
+                "range",
+                vec![Object::Integer(1000)],
+                &mut env
+            ),
+            Err(EvalError::ResourceExhausted(
+                token("range"),
+                Resource::Memory
+            ))
+        );
+    }
+
+    #[test]
+    fn arrays() {
+        let array =
+            |values: &[i32]| Object::Array(values.iter().map(|&v| Object::Integer(v)).collect());
+        let call = |name: &str, arguments: Vec<Object>| {
+            call_builtin(&token(name), name, arguments, &mut Environment::new())
+        };
+        let values = array(&[3, 1, 2]);
+        let two = Object::Integer(2);
+        assert_eq!(
+            call("push", vec![values.clone(), Object::Integer(4)]),
+            Ok(array(&[3, 1, 2, 4]))
+        );
+        assert_eq!(call("pop", vec![values.clone()]), Ok(array(&[3, 1])));
+        assert_eq!(
+            call(
+                "insert",
+                vec![values.clone(), Object::Integer(3), Object::Integer(0)]
+            ),
+            Ok(array(&[3, 1, 2, 0]))
+        );
+        assert_eq!(
+            call("remove", vec![values.clone(), Object::Integer(-3)]),
+            Ok(array(&[1, 2]))
+        );
+        assert_eq!(call("sort", vec![values.clone()]), Ok(array(&[1, 2, 3])));
+        assert_eq!(call("reverse", vec![values.clone()]), Ok(array(&[2, 1, 3])));
+        assert_eq!(
+            call("contains", vec![values.clone(), two]),
+            Ok(Object::Boolean(true))
+        );
+
+        assert_eq!(
+            call("remove", vec![values.clone(), Object::Integer(3)]),
+            Err(EvalError::IndexOutOfBounds(token("remove"), 3, 3))
+        );
+        assert_eq!(
+            call("pop", vec![array(&[])]).unwrap_err().to_string(),
+            "Error at line 1: Index -1 is out of bounds for length 0 [E0025]"
+        );
+        let mixed = Object::Array(vec![Object::Integer(1), Object::Boolean(true)]);
+        assert!(call("sort", vec![mixed]).is_err());
+    }
+
+    #[test]
+    fn print() {
+        let output = SharedBuffer::default();
+        let mut env = Environment::new();
+        env.set_output(Box::new(output.clone()));
+        for value in [Object::String(String::from("sapo")), Object::Integer(1)] {
+            let result = call_builtin(&token("print"), "print", vec![value.clone()], &mut env);
+            assert_eq!(result, Ok(value));
+        }
+        assert_eq!(output.0.lock().unwrap().as_slice(), b"sapo\n1\n");
+    }
+
+    #[test]
+    fn input() {
+        let mut env = Environment::new();
+        env.set_input(Box::new(io::Cursor::new("frog\r\n\ntoad")));
+        let lines: Vec<_> = (0..4)
+            .map(|_| call_builtin(&token("input"), "input", vec![], &mut env))
+            .collect();
+        let expected = ["frog", "", "toad", ""].map(|s| Ok(Object::String(s.to_string())));
+        assert_eq!(lines, expected);
+    }
+
+    /// Output that can still be read after being moved into the environment
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn token(name: &str) -> Token {
+        Token::new(TokenType::Identifier, name.to_string(), 1)
+    }
+}
@@ -0,0 +1,55 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Lets another thread stop an evaluation in progress, e.g. when the user presses Ctrl-C.
+/// Clones share the same flag: keep a clone, pass the original to
+/// Environment::set_cancellation_token and call cancel() on the clone.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    /// Evaluations using the token fail with EvalError::Cancelled until it is reset
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Allows evaluating again with the token after it was cancelled
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{evaluate_program, parse_program, Environment, EvalError};
+    use std::thread;
+
+    #[test]
+    fn cancel_from_another_thread() {
+        let token = CancellationToken::new();
+        let mut env = Environment::new();
+        env.set_cancellation_token(token.clone());
+        let program = parse_program("1 + 2").unwrap();
+        assert!(evaluate_program(&program, &mut env).is_ok());
+
+        let handle = token.clone();
+        thread::spawn(move || handle.cancel()).join().unwrap();
+        let error = evaluate_program(&program, &mut env).unwrap_err();
+        assert!(matches!(error, EvalError::Cancelled(_)));
+        assert_eq!(error.code(), "E0016");
+
+        token.reset();
+        assert!(evaluate_program(&program, &mut env).is_ok());
+    }
+}
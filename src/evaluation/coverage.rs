@@ -0,0 +1,139 @@
+use super::EvalHook;
+use crate::ast::{Ast, ExprId, Expression, Program};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::sync::{Arc, Mutex};
+
+/// Counts how many times each line of a program was executed.
+/// Clones share the counts, so a clone can be added as a hook to the
+/// environment and the original used to build the report afterwards.
+#[derive(Clone, Debug, Default)]
+pub struct Coverage {
+    state: Arc<Mutex<CoverageState>>,
+}
+
+#[derive(Debug, Default)]
+struct CoverageState {
+    hits: BTreeMap<i32, usize>,
+    /// Lines already counted for the statement being executed
+    seen: HashSet<i32>,
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Coverage::default()
+    }
+
+    /// Number of times the given line was executed
+    pub fn hits(&self, line: i32) -> usize {
+        self.state
+            .lock()
+            .unwrap()
+            .hits
+            .get(&line)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Hit count of every line with code, including the ones that were never executed
+    pub fn line_hits(&self, program: &Program) -> Vec<(i32, usize)> {
+        let mut lines = BTreeSet::new();
+        for &statement in &program.statements {
+            collect_lines(&program.ast, statement, &mut lines);
+        }
+        lines.iter().map(|&line| (line, self.hits(line))).collect()
+    }
+
+    /// LCOV tracefile of the program, source_file is the path of the program
+    pub fn to_lcov(&self, source_file: &str, program: &Program) -> String {
+        let lines = self.line_hits(program);
+        let mut buf = format!("TN:\nSF:{}\n", source_file);
+        for (line, hits) in &lines {
+            buf.push_str(&format!("DA:{},{}\n", line, hits));
+        }
+        let executed = lines.iter().filter(|(_, hits)| *hits > 0).count();
+        buf.push_str(&format!(
+            "LH:{}\nLF:{}\nend_of_record\n",
+            executed,
+            lines.len()
+        ));
+        buf
+    }
+
+    /// The source with the hit count of each line in front of it,
+    /// lines that were never executed are marked with #####
+    pub fn annotate(&self, source: &str, program: &Program) -> String {
+        let lines: BTreeMap<i32, usize> = self.line_hits(program).into_iter().collect();
+        let mut buf = String::new();
+        for (i, text) in source.lines().enumerate() {
+            let number = i as i32 + 1;
+            let count = match lines.get(&number) {
+                Some(0) => String::from("#####"),
+                Some(hits) => hits.to_string(),
+                None => String::from("-"),
+            };
+            buf.push_str(&format!("{:>6} | {}\n", count, text));
+        }
+        buf
+    }
+}
+
+impl EvalHook for Coverage {
+    fn on_statement(&mut self, _statement: &Expression) {
+        self.state.lock().unwrap().seen.clear();
+    }
+
+    fn on_enter_expression(&mut self, expression: &Expression) {
+        let line = expression.token().line;
+        let mut state = self.state.lock().unwrap();
+        // a line with several expressions is executed once per statement
+        if state.seen.insert(line) {
+            *state.hits.entry(line).or_insert(0) += 1;
+        }
+    }
+}
+
+fn collect_lines(ast: &Ast, id: ExprId, lines: &mut BTreeSet<i32>) {
+    lines.insert(ast[id].token().line);
+    for child in ast[id].children() {
+        collect_lines(ast, child, lines);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{evaluate_program, parse_program, Environment};
+
+    #[test]
+    fn count_lines() {
+        let source = "x = 1;\nx = x +\n  y;\nexit(\n  x)";
+        let (coverage, program) = run(source);
+        assert_eq!(
+            coverage.line_hits(&program),
+            vec![(1, 1), (2, 1), (3, 1), (4, 0), (5, 0)]
+        );
+        assert_eq!(
+            coverage.to_lcov("test.sapo", &program),
+            "TN:\nSF:test.sapo\nDA:1,1\nDA:2,1\nDA:3,1\nDA:4,0\nDA:5,0\nLH:3\nLF:5\nend_of_record\n"
+        );
+    }
+
+    #[test]
+    fn annotate_source() {
+        let source = "x = 1;\n\ny = x;\nz";
+        let (coverage, program) = run(source);
+        assert_eq!(
+            coverage.annotate(source, &program),
+            "     1 | x = 1;\n     - | \n     1 | y = x;\n     1 | z\n"
+        );
+    }
+
+    fn run(source: &str) -> (Coverage, Program) {
+        let program = parse_program(source).unwrap();
+        let coverage = Coverage::new();
+        let mut env = Environment::new();
+        env.add_hook(Box::new(coverage.clone()));
+        let _ = evaluate_program(&program, &mut env);
+        (coverage, program)
+    }
+}
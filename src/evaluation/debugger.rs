@@ -0,0 +1,137 @@
+use super::{evaluate_statement, Environment, EvalError, Object};
+use crate::ast::{Ast, ExprId, Expression, Program};
+use std::collections::BTreeSet;
+
+/// Evaluates a program one expression at a time, pausing at breakpoints
+pub struct Debugger {
+    program: Program,
+    environment: Environment,
+    /// Index of the next expression to evaluate
+    next: usize,
+    breakpoints: BTreeSet<i32>,
+}
+
+impl Debugger {
+    pub fn new(program: Program, environment: Environment) -> Self {
+        Debugger {
+            program,
+            environment,
+            next: 0,
+            breakpoints: BTreeSet::new(),
+        }
+    }
+
+    /// Pauses before evaluating any expression that starts at line
+    pub fn add_breakpoint(&mut self, line: i32) {
+        self.breakpoints.insert(line);
+    }
+
+    /// Returns false if there was no breakpoint at line
+    pub fn remove_breakpoint(&mut self, line: i32) -> bool {
+        self.breakpoints.remove(&line)
+    }
+
+    /// Lines with breakpoints, in ascending order
+    pub fn breakpoints(&self) -> Vec<i32> {
+        self.breakpoints.iter().copied().collect()
+    }
+
+    /// Line of the next expression to evaluate, None if the program has finished
+    pub fn current_line(&self) -> Option<i32> {
+        let statement = *self.program.statements.get(self.next)?;
+        Some(start_line(&self.program.ast, statement))
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.program.len()
+    }
+
+    pub fn environment(&self) -> &Environment {
+        &self.environment
+    }
+
+    /// Useful to evaluate expressions while paused
+    pub fn environment_mut(&mut self) -> &mut Environment {
+        &mut self.environment
+    }
+
+    /// Evaluates the next expression of the program, None if it has finished
+    pub fn step(&mut self) -> Option<Result<Object, EvalError>> {
+        let statement = *self.program.statements.get(self.next)?;
+        self.next += 1;
+        self.environment.start();
+        Some(evaluate_statement(
+            &self.program.ast,
+            statement,
+            &mut self.environment,
+        ))
+    }
+
+    /// Evaluates expressions until the next one is at a breakpoint or the program finishes
+    /// Returns the value of the last expression evaluated
+    pub fn resume(&mut self) -> Result<Option<Object>, EvalError> {
+        let mut result = None;
+        while let Some(value) = self.step() {
+            result = Some(value?);
+            match self.current_line() {
+                Some(line) if self.breakpoints.contains(&line) => break,
+                _ => {}
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// Line of the leftmost token of the expression
+fn start_line(ast: &Ast, id: ExprId) -> i32 {
+    match &ast[id] {
+        Expression::BinaryExpression { left, .. } => start_line(ast, *left),
+        Expression::TypeTest { value, .. } => start_line(ast, *value),
+        expression => expression.token().line,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_program;
+
+    #[test]
+    fn step() {
+        let mut debugger = debugger("x = 1;\nx = x + 1");
+        assert_eq!(debugger.current_line(), Some(1));
+        assert_eq!(debugger.step(), Some(Ok(Object::Integer(1))));
+        assert_eq!(debugger.current_line(), Some(2));
+        assert_eq!(debugger.step(), Some(Ok(Object::Integer(2))));
+        assert_eq!(debugger.current_line(), None);
+        assert_eq!(debugger.step(), None);
+        assert!(debugger.is_finished());
+    }
+
+    #[test]
+    fn resume_until_breakpoint() {
+        let mut debugger = debugger("x = 1;\nx = 2;\nx = 3;\nx = 4");
+        debugger.add_breakpoint(3);
+        assert_eq!(debugger.resume(), Ok(Some(Object::Integer(2))));
+        assert_eq!(debugger.current_line(), Some(3));
+        assert_eq!(debugger.environment().get("x"), Some(&Object::Integer(2)));
+        assert_eq!(debugger.resume(), Ok(Some(Object::Integer(4))));
+        assert!(debugger.is_finished());
+    }
+
+    #[test]
+    fn remove_breakpoint() {
+        let mut debugger = debugger("1;\n2;\n3");
+        debugger.add_breakpoint(2);
+        debugger.add_breakpoint(3);
+        assert!(debugger.remove_breakpoint(2));
+        assert!(!debugger.remove_breakpoint(2));
+        assert_eq!(debugger.breakpoints(), vec![3]);
+        assert_eq!(debugger.resume(), Ok(Some(Object::Integer(2))));
+    }
+
+    fn debugger(input: &str) -> Debugger {
+        let program = parse_program(input).unwrap();
+        Debugger::new(program, Environment::new())
+    }
+}
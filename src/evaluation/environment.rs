@@ -0,0 +1,451 @@
+use super::builtins::NativeFunction;
+use super::files::register_file_functions;
+#[cfg(feature = "regex")]
+use super::patterns::Patterns;
+use super::process::register_process_functions;
+use super::random::Random;
+use super::{CancellationToken, EvalError, EvalHook, Object, Resource};
+use crate::ast::Expression;
+use crate::interner::Symbol;
+use crate::parsing::{is_operator_symbol, CustomOperator, Fixity, DEFAULT_MAX_DEPTH};
+use crate::token::Token;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::io::{BufRead, Write};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How deeply expressions can be nested during evaluation by default,
+/// the same as in the parser so that every program that parses can be evaluated
+pub const DEFAULT_MAX_EVAL_DEPTH: usize = DEFAULT_MAX_DEPTH;
+
+/// How many values of expressions an environment remembers, see EvalOptions::memoize
+const MAX_MEMOIZED: usize = 4096;
+
+/// How long sleep() waits at most before checking again whether the evaluation was
+/// cancelled
+const SLEEP_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Limits of an evaluation, hosts running untrusted programs can use them to make sure
+/// that they finish. Exceeding one of them stops the evaluation with an error.
+/// The steps, memory and time are counted from the start of each call to evaluate.
+#[derive(Debug, Clone)]
+pub struct EvalOptions {
+    /// How deeply expressions can be nested
+    pub max_depth: usize,
+    /// How many expressions can be evaluated, None for no limit
+    pub max_steps: Option<u64>,
+    /// How many bytes the variables and the values being computed can take, approximately
+    pub max_memory: Option<usize>,
+    pub timeout: Option<Duration>,
+    /// Whether the values of pure expressions, like arithmetic on variables, are remembered
+    /// and reused when an expression with the same variable values is evaluated again,
+    /// even in another program. Hooks aren't called for the subexpressions of reused ones.
+    pub memoize: bool,
+    /// Whether conditions, like the operand of '!', can be any value, see Object::is_truthy.
+    /// By default they must be Booleans.
+    pub truthiness: bool,
+    /// Seed of the numbers of random(), rand_int() and shuffle(), so that evaluations can be
+    /// repeated, e.g. in tests. None for different numbers each time.
+    pub seed: Option<u64>,
+}
+
+impl Default for EvalOptions {
+    fn default() -> Self {
+        EvalOptions {
+            max_depth: DEFAULT_MAX_EVAL_DEPTH,
+            max_steps: None,
+            max_memory: None,
+            timeout: None,
+            memoize: false,
+            truthiness: false,
+            seed: None,
+        }
+    }
+}
+
+/// Holds the values of the variables of a program
+pub struct Environment {
+    values: HashMap<Symbol, Object>,
+    /// Command line arguments of the script, returned by args()
+    arguments: Vec<String>,
+    hooks: Vec<Box<dyn EvalHook>>,
+    /// Where print() writes to
+    output: Box<dyn Write + Send>,
+    /// Where input() reads lines from
+    input: Box<dyn BufRead + Send>,
+    /// Functions registered by the application embedding sapo
+    functions: HashMap<String, NativeFunction>,
+    /// Operators registered by the application embedding sapo, with their implementation
+    operators: Vec<(CustomOperator, NativeFunction)>,
+    options: EvalOptions,
+    cancellation: CancellationToken,
+    /// Nesting of the expression being evaluated
+    depth: usize,
+    /// Expressions evaluated since the evaluation started
+    steps: u64,
+    /// Only measured with a timeout, there is no clock on some platforms like WebAssembly
+    started: Option<Instant>,
+    /// Approximate number of bytes taken by the variables
+    memory: usize,
+    /// Values of pure expressions by their memo key, see EvalOptions::memoize
+    memoized: HashMap<String, Object>,
+    random: Random,
+    #[cfg(feature = "regex")]
+    patterns: Patterns,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Environment::with_options(EvalOptions::default())
+    }
+
+    pub fn with_options(options: EvalOptions) -> Self {
+        let random = Random::new(options.seed);
+        Environment {
+            values: HashMap::new(),
+            arguments: Vec::new(),
+            hooks: Vec::new(),
+            output: Box::new(io::stdout()),
+            input: Box::new(io::BufReader::new(io::stdin())),
+            functions: HashMap::new(),
+            operators: Vec::new(),
+            options,
+            cancellation: CancellationToken::new(),
+            depth: 0,
+            steps: 0,
+            started: None,
+            memory: 0,
+            memoized: HashMap::new(),
+            random,
+            #[cfg(feature = "regex")]
+            patterns: Patterns::default(),
+        }
+    }
+
+    /// An environment for running programs with nobody at the terminal: print() writes to
+    /// the returned buffer and input() returns ""
+    pub fn unattended(options: EvalOptions) -> (Self, OutputBuffer) {
+        let output = OutputBuffer::default();
+        let mut environment = Environment::with_options(options);
+        environment.set_output(Box::new(output.clone()));
+        environment.set_input(Box::new(io::empty()));
+        (environment, output)
+    }
+
+    /// Binds name to value, replacing the previous value if there was one
+    pub fn set(&mut self, name: &str, value: Object) {
+        self.set_symbol(Symbol::intern(name), value)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Object> {
+        Symbol::lookup(name).and_then(|name| self.get_symbol(name))
+    }
+
+    pub(crate) fn set_symbol(&mut self, name: Symbol, value: Object) {
+        let length = name.as_str().len();
+        self.memory += length + value.memory_size();
+        if let Some(old) = self.values.insert(name, value) {
+            self.memory -= length + old.memory_size();
+        }
+    }
+
+    pub(crate) fn get_symbol(&self, name: Symbol) -> Option<&Object> {
+        self.values.get(&name)
+    }
+
+    pub fn set_arguments(&mut self, arguments: Vec<String>) {
+        self.arguments = arguments;
+    }
+
+    pub fn arguments(&self) -> &[String] {
+        &self.arguments
+    }
+
+    /// The hook is called while evaluating with this environment, after the ones added before it
+    pub fn add_hook(&mut self, hook: Box<dyn EvalHook>) {
+        self.hooks.push(hook);
+    }
+
+    pub(crate) fn hooks_mut(&mut self) -> &mut [Box<dyn EvalHook>] {
+        &mut self.hooks
+    }
+
+    /// Redirects the output of print(), which is written to stdout by default
+    pub fn set_output(&mut self, output: Box<dyn Write + Send>) {
+        self.output = output;
+    }
+
+    pub fn output(&mut self) -> &mut dyn Write {
+        &mut *self.output
+    }
+
+    /// Replaces where input() reads lines from, which is stdin by default
+    pub fn set_input(&mut self, input: Box<dyn BufRead + Send>) {
+        self.input = input;
+    }
+
+    pub(crate) fn input(&mut self) -> &mut dyn BufRead {
+        &mut *self.input
+    }
+
+    /// Makes function callable from programs as name, calls with a number of arguments
+    /// other than arity fail. Errors returned by function are reported with their description.
+    /// Built-in functions can't be replaced, registering one of their names has no effect.
+    pub fn register_fn<F, E>(&mut self, name: &str, arity: usize, function: F)
+    where
+        F: Fn(&[Object]) -> Result<Object, E> + Send + Sync + 'static,
+        E: fmt::Display,
+    {
+        let function = Box::new(move |arguments: &[Object]| {
+            function(arguments).map_err(|error| error.to_string())
+        });
+        self.functions
+            .insert(name.to_string(), NativeFunction { arity, function });
+    }
+
+    /// Registers read_file(path), read_lines(path) and write_file(path, text). They aren't
+    /// available by default, so that programs can only access files if the application
+    /// allows it.
+    pub fn enable_io(&mut self) {
+        register_file_functions(self);
+    }
+
+    /// Registers env(name), set_env(name, value), cwd() and run(command), which runs
+    /// command with the shell and returns its output. Like enable_io, they have to be
+    /// allowed by the application.
+    pub fn enable_process(&mut self) {
+        register_process_functions(self);
+    }
+
+    pub(crate) fn function(&self, name: &str) -> Option<&NativeFunction> {
+        self.functions.get(name)
+    }
+
+    /// Names of the registered functions, sorted alphabetically
+    pub fn function_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.functions.keys().map(|n| n.as_str()).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Makes symbol an operator that calls function with its operand, or its left and right
+    /// operands if it is an infix one. Programs using it have to be parsed with the operators
+    /// returned by operators() in their ParseOptions, which Interpreter does. Registering an
+    /// operator with the same symbol and fixity as another one replaces it.
+    ///
+    /// # Panics
+    ///
+    /// If symbol is a built-in operator or isn't made of punctuation, see CustomOperator
+    pub fn register_operator<F, E>(&mut self, symbol: &str, fixity: Fixity, function: F)
+    where
+        F: Fn(&[Object]) -> Result<Object, E> + Send + Sync + 'static,
+        E: fmt::Display,
+    {
+        assert!(
+            is_operator_symbol(symbol),
+            "'{}' can't be the symbol of an operator",
+            symbol
+        );
+        let arity = match fixity {
+            Fixity::Prefix => 1,
+            Fixity::Infix(_) => 2,
+        };
+        let function = Box::new(move |arguments: &[Object]| {
+            function(arguments).map_err(|error| error.to_string())
+        });
+        let operator = CustomOperator {
+            symbol: symbol.to_string(),
+            fixity,
+        };
+        let prefix = fixity == Fixity::Prefix;
+        self.operators
+            .retain(|(o, _)| o.symbol != symbol || (o.fixity == Fixity::Prefix) != prefix);
+        self.operators
+            .push((operator, NativeFunction { arity, function }));
+    }
+
+    /// The registered operators, in the order they were registered
+    pub fn operators(&self) -> Vec<CustomOperator> {
+        self.operators.iter().map(|(o, _)| o.clone()).collect()
+    }
+
+    /// Implementation of the registered prefix or infix operator with the given symbol
+    pub(crate) fn operator(&self, symbol: &str, prefix: bool) -> Option<&NativeFunction> {
+        self.operators
+            .iter()
+            .find(|(o, _)| o.symbol == symbol && (o.fixity == Fixity::Prefix) == prefix)
+            .map(|(_, function)| function)
+    }
+
+    pub fn options(&self) -> &EvalOptions {
+        &self.options
+    }
+
+    pub fn set_options(&mut self, options: EvalOptions) {
+        // the memoized values of conditions depend on the truthiness
+        if options.truthiness != self.options.truthiness {
+            self.memoized.clear();
+        }
+        if options.seed != self.options.seed {
+            self.random = Random::new(options.seed);
+        }
+        self.options = options;
+    }
+
+    /// Evaluations with this environment fail with EvalError::Cancelled once token is cancelled
+    pub fn set_cancellation_token(&mut self, token: CancellationToken) {
+        self.cancellation = token;
+    }
+
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    /// Starts counting the steps and time of an evaluation from zero
+    pub(crate) fn start(&mut self) {
+        self.steps = 0;
+        self.started = self.options.timeout.map(|_| Instant::now());
+    }
+
+    /// Enters a nested expression, fails if that exceeds one of the limits.
+    /// The depth is counted like in the parser, the outermost expression has a depth of 0.
+    pub(crate) fn enter(&mut self, expression: &Expression) -> Result<(), EvalError> {
+        let token = expression.token();
+        if self.depth > self.options.max_depth {
+            return Err(EvalError::NestingTooDeep(token.clone()));
+        }
+        if self.cancellation.is_cancelled() {
+            return Err(EvalError::Cancelled(token.clone()));
+        }
+        self.steps += 1;
+        if matches!(self.options.max_steps, Some(max) if self.steps > max) {
+            return Err(EvalError::ResourceExhausted(token.clone(), Resource::Steps));
+        }
+        let elapsed = self.started.map(|started| started.elapsed());
+        if matches!((self.options.timeout, elapsed), (Some(timeout), Some(elapsed)) if elapsed >= timeout)
+        {
+            return Err(EvalError::ResourceExhausted(token.clone(), Resource::Time));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Blocks the thread for duration, failing as soon as the evaluation is cancelled or
+    /// exceeds its timeout. token is the call to sleep().
+    pub(crate) fn sleep(&self, token: &Token, duration: Duration) -> Result<(), EvalError> {
+        let end = Instant::now() + duration;
+        let deadline = match (self.options.timeout, self.started) {
+            (Some(timeout), Some(started)) => Some(started + timeout),
+            _ => None,
+        };
+        loop {
+            if self.cancellation.is_cancelled() {
+                return Err(EvalError::Cancelled(token.clone()));
+            }
+            let now = Instant::now();
+            if matches!(deadline, Some(deadline) if now >= deadline) {
+                return Err(EvalError::ResourceExhausted(token.clone(), Resource::Time));
+            }
+            if now >= end {
+                return Ok(());
+            }
+            let wake = deadline.map_or(end, |deadline| deadline.min(end));
+            thread::sleep((wake - now).min(SLEEP_INTERVAL));
+        }
+    }
+
+    /// Fails if storing value on top of the variables exceeds the memory limit
+    pub(crate) fn check_memory(
+        &self,
+        expression: &Expression,
+        value: &Object,
+    ) -> Result<(), EvalError> {
+        match self.options.max_memory {
+            Some(max) if self.memory + value.memory_size() > max => Err(
+                EvalError::ResourceExhausted(expression.token().clone(), Resource::Memory),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn memoized(&self, key: &str) -> Option<&Object> {
+        self.memoized.get(key)
+    }
+
+    /// Remembers the value of a pure expression, forgetting all the others once there are
+    /// MAX_MEMOIZED of them
+    pub(crate) fn memoize(&mut self, key: String, value: Object) {
+        if self.memoized.len() >= MAX_MEMOIZED {
+            self.memoized.clear();
+        }
+        self.memoized.insert(key, value);
+    }
+
+    pub(crate) fn random(&mut self) -> &mut Random {
+        &mut self.random
+    }
+
+    #[cfg(feature = "regex")]
+    pub(crate) fn patterns(&mut self) -> &mut Patterns {
+        &mut self.patterns
+    }
+
+    pub(crate) fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    /// Names of all the variables, sorted alphabetically
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.values.keys().map(Symbol::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Environment::new()
+    }
+}
+
+impl fmt::Debug for Environment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Environment")
+            .field("values", &self.values)
+            .field("arguments", &self.arguments)
+            .field("hooks", &self.hooks.len())
+            .field("functions", &self.function_names())
+            .field("operators", &self.operators())
+            .field("options", &self.options)
+            .finish()
+    }
+}
+
+/// Collects what is written to it, e.g. the output of print() when passed to
+/// Environment::set_output. Clones share the same contents.
+#[derive(Clone, Default)]
+pub struct OutputBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl OutputBuffer {
+    /// What was written so far, invalid UTF-8 is replaced
+    pub fn contents(&self) -> String {
+        let buffer = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        String::from_utf8_lossy(&buffer).into_owned()
+    }
+}
+
+impl Write for OutputBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
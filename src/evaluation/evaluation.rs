@@ -1,15 +1,343 @@
-use crate::ast::Expression;
+use super::builtins::{array_index, builtin_max_arity, call_builtin, call_operator, is_variadic};
+use super::Environment;
+use crate::ast::{Ast, ExprId, Expression, Program};
+use crate::diagnostics::suggest;
+use crate::json;
 use crate::token::{Token, TokenType};
+use std::collections::HashMap;
 use std::fmt;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Object {
     Integer(i32),
     Boolean(bool),
     String(String),
+    Array(Vec<Object>),
+    /// A fixed group of values, e.g. (1, "a")
+    Tuple(Vec<Object>),
+}
+
+/// A limit of EvalOptions
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Resource {
+    Steps,
+    Memory,
+    Time,
+}
+
+/// Why integer arithmetic has no result
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Arithmetic {
+    DivisionByZero,
+    /// The result doesn't fit in an Integer
+    Overflow,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum EvalError {
+    InvalidOperand(Token),
+    InvalidOperands(Token),
+    InvalidOperator(Token),
+    UndefinedVariable(Token, Option<String>),
+    UndefinedFunction(Token, Option<String>),
+    /// Expected and given number of arguments
+    WrongNumberOfArguments(Token, usize, usize),
+    /// Name of the expected type
+    InvalidArgument(Token, &'static str),
+    NestingTooDeep(Token),
+    /// The evaluation exceeded one of the limits of its EvalOptions
+    ResourceExhausted(Token, Resource),
+    /// An arithmetic operator had no Integer result
+    ArithmeticError(Token, Arithmetic),
+    /// A function registered by the embedding application failed with the given description
+    FunctionFailed(Token, String),
+    /// The CancellationToken of the environment was cancelled
+    Cancelled(Token),
+    /// assert() or assert_eq() failed, with the description of the failure
+    AssertionFailed(Token, String),
+    /// The program has a statement that couldn't be parsed, see Expression::Error
+    SyntaxError(Token),
+    /// A string was multiplied by a negative number
+    NegativeRepetition(Token),
+    /// The index and the length of the array or string it was used on
+    IndexOutOfBounds(Token, i32, usize),
+    /// Number of names of a multiple assignment and of values assigned to them
+    WrongNumberOfValues(Token, usize, usize),
+    /// The type name of a type test isn't one of TYPE_NAMES, with the closest one
+    UnknownType(Token, String, Option<String>),
+    /// A string that int() or bool() couldn't convert and the name of the type
+    ConversionFailed(Token, String, &'static str),
+    /// The template of format() doesn't match its values, with a description of why
+    InvalidFormat(Token, String),
+    /// json_parse() got a document it can't read or convert, with a description of why
+    InvalidJson(Token, String),
+    /// A regex built-in got a pattern that isn't a valid regular expression
+    InvalidPattern(Token, String),
+    /// exit() was called with the given status code, not an actual error
+    /// but it stops the evaluation like one
+    Exit(Token, i32),
+}
+
+impl EvalError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            EvalError::InvalidOperand(_) => "E0003",
+            EvalError::InvalidOperands(_) => "E0004",
+            EvalError::InvalidOperator(_) => "E0005",
+            EvalError::UndefinedVariable(_, _) => "E0006",
+            EvalError::UndefinedFunction(_, _) => "E0010",
+            EvalError::WrongNumberOfArguments(_, _, _) => "E0011",
+            EvalError::InvalidArgument(_, _) => "E0012",
+            EvalError::Exit(_, _) => "E0013",
+            EvalError::NestingTooDeep(_) => "E0014",
+            EvalError::ResourceExhausted(_, _) => "E0015",
+            EvalError::ArithmeticError(_, _) => "E0035",
+            EvalError::Cancelled(_) => "E0016",
+            EvalError::FunctionFailed(_, _) => "E0017",
+            EvalError::AssertionFailed(_, _) => "E0019",
+            EvalError::SyntaxError(_) => "E0023",
+            EvalError::NegativeRepetition(_) => "E0024",
+            EvalError::IndexOutOfBounds(_, _, _) => "E0025",
+            EvalError::WrongNumberOfValues(_, _, _) => "E0027",
+            EvalError::UnknownType(_, _, _) => "E0029",
+            EvalError::ConversionFailed(_, _, _) => "E0030",
+            EvalError::InvalidFormat(_, _) => "E0031",
+            EvalError::InvalidJson(_, _) => "E0032",
+            EvalError::InvalidPattern(_, _) => "E0033",
+        }
+    }
+
+    /// The token of the operation that failed
+    pub fn token(&self) -> &Token {
+        match self {
+            EvalError::InvalidOperand(t)
+            | EvalError::InvalidOperands(t)
+            | EvalError::InvalidOperator(t)
+            | EvalError::UndefinedVariable(t, _)
+            | EvalError::UndefinedFunction(t, _)
+            | EvalError::WrongNumberOfArguments(t, _, _)
+            | EvalError::InvalidArgument(t, _)
+            | EvalError::Exit(t, _)
+            | EvalError::SyntaxError(t)
+            | EvalError::NegativeRepetition(t)
+            | EvalError::IndexOutOfBounds(t, _, _)
+            | EvalError::WrongNumberOfValues(t, _, _)
+            | EvalError::UnknownType(t, _, _)
+            | EvalError::ConversionFailed(t, _, _)
+            | EvalError::InvalidFormat(t, _)
+            | EvalError::InvalidJson(t, _)
+            | EvalError::InvalidPattern(t, _)
+            | EvalError::ResourceExhausted(t, _)
+            | EvalError::ArithmeticError(t, _)
+            | EvalError::Cancelled(t)
+            | EvalError::FunctionFailed(t, _)
+            | EvalError::AssertionFailed(t, _)
+            | EvalError::NestingTooDeep(t) => t,
+        }
+    }
+
+    /// Description of the error without location or code
+    pub fn message(&self) -> String {
+        match self {
+            EvalError::InvalidOperand(t) => format!(
+                "Invalid operand for '{}', expected {} expression",
+                operator_symbol(&t.token_type),
+                match t.token_type {
+                    TokenType::Bang => "boolean",
+                    _ => "integer",
+                }
+            ),
+            EvalError::InvalidOperands(t) => {
+                format!("Invalid operands for '{}'", operator_symbol(&t.token_type))
+            }
+            EvalError::InvalidOperator(t) => format!("Invalid operator '{}'", t.lexeme),
+            EvalError::UndefinedVariable(t, suggestion) => format!(
+                "Undefined variable '{}'{}",
+                t.lexeme,
+                match suggestion {
+                    Some(name) => format!(", did you mean '{}'?", name),
+                    None => String::new(),
+                }
+            ),
+            EvalError::UndefinedFunction(t, suggestion) => format!(
+                "Undefined function '{}'{}",
+                t.lexeme,
+                match suggestion {
+                    Some(name) => format!(", did you mean '{}'?", name),
+                    None => String::new(),
+                }
+            ),
+            EvalError::WrongNumberOfArguments(t, expected, given) => {
+                // t is the name of the called function
+                let expected = match builtin_max_arity(&t.lexeme) {
+                    _ if !is_variadic(&t.lexeme) => expected.to_string(),
+                    Some(max) => format!("{} to {}", expected, max),
+                    None => format!("at least {}", expected),
+                };
+                format!(
+                    "'{}' expected {} argument(s), but {} were given",
+                    t.lexeme, expected, given
+                )
+            }
+            EvalError::InvalidArgument(t, expected) => {
+                format!("Invalid argument for '{}', expected {}", t.lexeme, expected)
+            }
+            EvalError::Exit(_, code) => format!("Exited with status {}", code),
+            EvalError::SyntaxError(_) => {
+                String::from("Statement with a syntax error can't be evaluated")
+            }
+            EvalError::NegativeRepetition(_) => {
+                String::from("A string can't be repeated a negative number of times")
+            }
+            EvalError::IndexOutOfBounds(_, index, length) => {
+                format!("Index {} is out of bounds for length {}", index, length)
+            }
+            EvalError::WrongNumberOfValues(_, expected, given) => format!(
+                "Expected {} values to assign, but {} {} given",
+                expected,
+                given,
+                if *given == 1 { "was" } else { "were" }
+            ),
+            EvalError::UnknownType(_, name, suggestion) => format!(
+                "Unknown type '{}'{}",
+                name,
+                match suggestion {
+                    Some(name) => format!(", did you mean '{}'?", name),
+                    None => String::new(),
+                }
+            ),
+            EvalError::ConversionFailed(_, string, type_name) => {
+                format!("Can't convert {:?} to {}", string, type_name)
+            }
+            EvalError::InvalidFormat(_, description) => {
+                format!("Invalid format string: {}", description)
+            }
+            EvalError::InvalidJson(_, description) => format!("Invalid JSON: {}", description),
+            EvalError::InvalidPattern(_, description) => {
+                format!("Invalid regular expression: {}", description)
+            }
+            EvalError::NestingTooDeep(_) => String::from("Expression is nested too deeply"),
+            EvalError::ArithmeticError(_, Arithmetic::DivisionByZero) => {
+                String::from("Division by zero")
+            }
+            EvalError::ArithmeticError(t, Arithmetic::Overflow) => {
+                format!("Integer overflow in '{}'", t.lexeme)
+            }
+            EvalError::Cancelled(_) => String::from("Evaluation was cancelled"),
+            EvalError::FunctionFailed(t, message) => format!("'{}' failed: {}", t.lexeme, message),
+            EvalError::AssertionFailed(_, message) => format!("Assertion failed: {}", message),
+            EvalError::ResourceExhausted(_, resource) => format!(
+                "Evaluation exceeded its {} limit",
+                match resource {
+                    Resource::Steps => "step",
+                    Resource::Memory => "memory",
+                    Resource::Time => "time",
+                }
+            ),
+        }
+    }
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} [{}]",
+            error(&self.message(), self.token()),
+            self.code()
+        )
+    }
 }
 
-type EvalResult = Result<Object, String>;
+type EvalResult = Result<Object, EvalError>;
+
+/// The names Object::type_name returns, which 'is' tests for
+pub const TYPE_NAMES: [&str; 5] = ["Array", "Boolean", "Integer", "String", "Tuple"];
+
+impl Object {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::Integer(_) => "Integer",
+            Object::Boolean(_) => "Boolean",
+            Object::String(_) => "String",
+            Object::Array(_) => "Array",
+            Object::Tuple(_) => "Tuple",
+        }
+    }
+
+    /// The closest JSON value: a number, boolean, string or array, also for tuples
+    pub fn to_json(&self) -> String {
+        let mut buf = String::new();
+        self.write_json(&mut buf, None);
+        buf
+    }
+
+    /// Like to_json, with each element of an array on its own line indented by two spaces
+    pub fn to_json_pretty(&self) -> String {
+        let mut buf = String::new();
+        self.write_json(&mut buf, Some(0));
+        buf
+    }
+
+    /// indent is the depth of the value when pretty printing
+    fn write_json(&self, buf: &mut String, indent: Option<usize>) {
+        match self {
+            Object::Integer(value) => buf.push_str(&value.to_string()),
+            Object::Boolean(value) => buf.push_str(&value.to_string()),
+            Object::String(value) => buf.push_str(&json::quote(value)),
+            Object::Array(values) | Object::Tuple(values) => {
+                buf.push('[');
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        buf.push(',');
+                    }
+                    if let Some(depth) = indent {
+                        buf.push('\n');
+                        buf.push_str(&"  ".repeat(depth + 1));
+                    }
+                    value.write_json(buf, indent.map(|depth| depth + 1));
+                }
+                if let (Some(depth), false) = (indent, values.is_empty()) {
+                    buf.push('\n');
+                    buf.push_str(&"  ".repeat(depth));
+                }
+                buf.push(']');
+            }
+        }
+    }
+
+    /// Whether the object counts as true when used as a condition with
+    /// EvalOptions::truthiness: false, 0, "" and [] are false and everything else is true
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Object::Integer(value) => *value != 0,
+            Object::Boolean(value) => *value,
+            Object::String(value) => !value.is_empty(),
+            Object::Array(values) | Object::Tuple(values) => !values.is_empty(),
+        }
+    }
+
+    /// The value of a condition, which must be a Boolean unless truthiness is enabled
+    pub(crate) fn condition(&self, env: &Environment) -> Option<bool> {
+        match self {
+            Object::Boolean(value) => Some(*value),
+            _ if env.options().truthiness => Some(self.is_truthy()),
+            _ => None,
+        }
+    }
+
+    /// Approximate number of bytes taken by the object
+    pub(crate) fn memory_size(&self) -> usize {
+        let heap = match self {
+            Object::Integer(_) | Object::Boolean(_) => 0,
+            Object::String(value) => value.len(),
+            Object::Array(values) | Object::Tuple(values) => {
+                values.iter().map(|v| v.memory_size()).sum()
+            }
+        };
+        std::mem::size_of::<Object>() + heap
+    }
+}
 
 impl fmt::Display for Object {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -17,101 +345,547 @@ impl fmt::Display for Object {
             Object::Integer(value) => write!(f, "{}", value),
             Object::String(value) => write!(f, "\"{}\"", value),
             Object::Boolean(value) => write!(f, "{}", value),
+            Object::Array(values) => {
+                let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                write!(f, "[{}]", values.join(", "))
+            }
+            // (1,) like the source of a tuple with one value
+            Object::Tuple(values) if values.len() == 1 => write!(f, "({},)", values[0]),
+            Object::Tuple(values) => {
+                let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                write!(f, "({})", values.join(", "))
+            }
+        }
+    }
+}
+
+/// Evaluates the expression id of ast in an empty environment
+pub fn evaluate(ast: &Ast, id: ExprId) -> EvalResult {
+    evaluate_with_environment(ast, id, &mut Environment::new())
+}
+
+/// Evaluates the expressions of the program in order, returns the value of the last one
+/// or None if the program is empty
+pub fn evaluate_program(
+    program: &Program,
+    env: &mut Environment,
+) -> Result<Option<Object>, EvalError> {
+    env.start();
+    let mut result = None;
+    for &statement in &program.statements {
+        result = Some(evaluate_statement(&program.ast, statement, env)?);
+    }
+    Ok(result)
+}
+
+/// Evaluates a top-level expression of a program
+pub(crate) fn evaluate_statement(
+    ast: &Ast,
+    statement: ExprId,
+    env: &mut Environment,
+) -> EvalResult {
+    for hook in env.hooks_mut() {
+        hook.on_statement(&ast[statement]);
+    }
+    evaluate_node(ast, statement, env)
+}
+
+/// Evaluates the expression id of ast, reading and assigning variables in env
+pub fn evaluate_with_environment(ast: &Ast, id: ExprId, env: &mut Environment) -> EvalResult {
+    env.start();
+    evaluate_node(ast, id, env)
+}
+
+/// Evaluates an expression within the limits of the evaluation in progress
+fn evaluate_node(ast: &Ast, id: ExprId, env: &mut Environment) -> EvalResult {
+    let mut evaluation = Evaluation::new(ast, id, env)?;
+    loop {
+        if let Some(result) = evaluation.step(env) {
+            return result;
         }
     }
 }
 
-pub fn evaluate(ast: &Expression) -> EvalResult {
+/// An expression being evaluated
+struct Frame<'a> {
+    expression: &'a Expression,
+    children: Vec<ExprId>,
+    /// Number of children entered so far
+    entered: usize,
+    /// Whether the expression is pure, see memo_key
+    pure: bool,
+    /// Key of the value of the expression if it is memoized, see EvalOptions::memoize
+    memo_key: Option<String>,
+    /// Memoized value of the expression, its children aren't entered then
+    memoized: Option<Object>,
+}
+
+/// Evaluation of an expression that can be paused between any two steps.
+/// Subexpressions are evaluated using an explicit stack instead of recursion,
+/// their values wait in another stack until the expression containing them uses them.
+pub(crate) struct Evaluation<'a> {
+    ast: &'a Ast,
+    frames: Vec<Frame<'a>>,
+    values: Vec<Object>,
+    /// Whether each expression of the tree is pure, computed once if memoization is enabled
+    pure: HashMap<ExprId, bool>,
+}
+
+impl<'a> Evaluation<'a> {
+    /// Enters the expression id of ast, fails if that exceeds one of the limits of env
+    pub(crate) fn new(ast: &'a Ast, id: ExprId, env: &mut Environment) -> Result<Self, EvalError> {
+        let mut evaluation = Evaluation {
+            ast,
+            frames: Vec::new(),
+            values: Vec::new(),
+            pure: HashMap::new(),
+        };
+        if env.options().memoize {
+            evaluation.pure = purity(ast, id);
+        }
+        evaluation.enter(id, env)?;
+        Ok(evaluation)
+    }
+
+    fn enter(&mut self, id: ExprId, env: &mut Environment) -> Result<(), EvalError> {
+        let expression = &self.ast[id];
+        env.enter(expression)?;
+        for hook in env.hooks_mut() {
+            hook.on_enter_expression(expression);
+        }
+        // only the outermost pure expressions are memoized
+        let pure = self.pure.get(&id).copied().unwrap_or(false);
+        let in_pure = self.frames.last().is_some_and(|f| f.pure);
+        let memo_key = if env.options().memoize && pure && !in_pure {
+            memo_key(self.ast, id, env)
+        } else {
+            None
+        };
+        let memoized = memo_key.as_ref().and_then(|key| env.memoized(key).cloned());
+        let children = match memoized {
+            Some(_) => Vec::new(),
+            None => expression.children(),
+        };
+        self.frames.push(Frame {
+            expression,
+            children,
+            entered: 0,
+            pure,
+            memo_key,
+            memoized,
+        });
+        Ok(())
+    }
+
+    /// Enters the next child of the innermost expression, or computes its value once all
+    /// its children have been evaluated. Returns the result when the evaluation finishes.
+    pub(crate) fn step(&mut self, env: &mut Environment) -> Option<EvalResult> {
+        let frame = self.frames.last_mut()?;
+        if frame.entered < frame.children.len() {
+            let child = frame.children[frame.entered];
+            frame.entered += 1;
+            return match self.enter(child, env) {
+                Ok(()) => None,
+                Err(error) => Some(self.unwind(error, env)),
+            };
+        }
+        let frame = self.frames.pop()?;
+        let values = self
+            .values
+            .split_off(self.values.len() - frame.children.len());
+        let ast = frame.expression;
+        let result = match frame.memoized {
+            Some(value) => Ok(value),
+            None => {
+                let result = evaluate_expression(ast, values, env);
+                if let (Some(key), Ok(value)) = (frame.memo_key, &result) {
+                    env.memoize(key, value.clone());
+                }
+                result
+            }
+        };
+        let result = result.and_then(|value| env.check_memory(ast, &value).map(|_| value));
+        for hook in env.hooks_mut() {
+            hook.on_exit_expression(ast, &result);
+        }
+        env.leave();
+        match result {
+            Ok(value) if self.frames.is_empty() => Some(Ok(value)),
+            Ok(value) => {
+                self.values.push(value);
+                None
+            }
+            Err(error) => Some(self.unwind(error, env)),
+        }
+    }
+
+    /// Leaves the expressions being evaluated after one of them failed
+    fn unwind(&mut self, error: EvalError, env: &mut Environment) -> EvalResult {
+        let result = Err(error);
+        while let Some(frame) = self.frames.pop() {
+            for hook in env.hooks_mut() {
+                hook.on_exit_expression(frame.expression, &result);
+            }
+            env.leave();
+        }
+        result
+    }
+
+    /// Stops the evaluation before it finishes, so that env can be used for other evaluations
+    pub(crate) fn abandon(&mut self, env: &mut Environment) {
+        for _ in self.frames.drain(..) {
+            env.leave();
+        }
+    }
+}
+
+/// Whether each expression of the tree id is pure, i.e. doesn't call functions or assign
+/// variables. Subexpressions come before the expressions containing them, so visiting the
+/// tree by increasing ExprId decides each expression after its children.
+fn purity(ast: &Ast, id: ExprId) -> HashMap<ExprId, bool> {
+    let mut ids = vec![id];
+    let mut stack = vec![id];
+    while let Some(id) = stack.pop() {
+        let children = ast[id].children();
+        ids.extend(&children);
+        stack.extend(children);
+    }
+    ids.sort_unstable();
+    ids.dedup();
+    let mut pure = HashMap::with_capacity(ids.len());
+    for id in ids {
+        let expression = &ast[id];
+        let is_pure = is_pure_node(expression)
+            && expression
+                .children()
+                .iter()
+                .all(|child| pure.get(child) == Some(&true));
+        pure.insert(id, is_pure);
+    }
+    pure
+}
+
+/// Whether the expression itself, without its children, is pure
+fn is_pure_node(expression: &Expression) -> bool {
+    match expression {
+        Expression::BinaryExpression { token, .. } | Expression::UnaryExpression { token, .. } => {
+            token.token_type != TokenType::Operator
+        }
+        Expression::IntegerLiteral { .. }
+        | Expression::BooleanLiteral { .. }
+        | Expression::StringLiteral { .. }
+        | Expression::Grouping { .. }
+        | Expression::Index { .. }
+        | Expression::Tuple { .. }
+        | Expression::TypeTest { .. }
+        | Expression::Slice { .. }
+        | Expression::Variable { .. } => true,
+        _ => false,
+    }
+}
+
+/// Describes the pure expression id and the values of its variables, so that expressions
+/// with the same key have the same value. None if the expression isn't pure or uses
+/// undefined variables, or if it is a literal or a variable
+fn memo_key(ast: &Ast, id: ExprId, env: &Environment) -> Option<String> {
+    use std::fmt::Write;
+
+    if ast[id].children().is_empty() {
+        return None;
+    }
+    let mut key = String::new();
+    let mut stack = vec![id];
+    while let Some(id) = stack.pop() {
+        let expression = &ast[id];
+        match expression {
+            Expression::BinaryExpression { token, .. }
+            | Expression::UnaryExpression { token, .. }
+                if token.token_type != TokenType::Operator =>
+            {
+                write!(key, "{}{} ", token.lexeme, expression.children().len()).unwrap()
+            }
+            Expression::IntegerLiteral { value, .. } => write!(key, "{} ", value).unwrap(),
+            Expression::BooleanLiteral { value, .. } => write!(key, "{} ", value).unwrap(),
+            Expression::StringLiteral { value, .. } => write!(key, "{:?} ", value).unwrap(),
+            Expression::Grouping { .. } => key.push_str("() "),
+            Expression::Index { .. } => key.push_str("[] "),
+            Expression::Tuple { elements, .. } => write!(key, "(,){} ", elements.len()).unwrap(),
+            Expression::TypeTest { type_name, .. } => write!(key, "is {} ", type_name).unwrap(),
+            // which bounds the slice has, a[1:] and a[:1] have the same children
+            Expression::Slice { start, end, .. } => {
+                write!(key, "[{}:{}] ", start.is_some(), end.is_some()).unwrap()
+            }
+            Expression::Variable { name, .. } => {
+                let value = env.get_symbol(*name)?;
+                write!(key, "{}={:?} ", name, value).unwrap()
+            }
+            _ => return None,
+        }
+        stack.extend(expression.children().into_iter().rev());
+    }
+    Some(key)
+}
+
+/// Computes the value of the expression from the values of its children
+fn evaluate_expression(
+    ast: &Expression,
+    mut values: Vec<Object>,
+    env: &mut Environment,
+) -> EvalResult {
     match ast {
         Expression::IntegerLiteral { token: _, value } => Ok(Object::Integer(*value)),
         Expression::BooleanLiteral { token: _, value } => Ok(Object::Boolean(*value)),
-        Expression::StringLiteral { token: _, value } => Ok(Object::String(value.clone())),
-        Expression::Grouping { token: _, expr } => evaluate(&*expr),
-        Expression::UnaryExpression { token, right } => evaluate_unary_expression(&token, right),
-        Expression::BinaryExpression { token, left, right } => {
-            evaluate_binary_expression(&token, left, right)
+        Expression::StringLiteral { token: _, value } => Ok(Object::String(value.to_string())),
+        Expression::Grouping { .. } => Ok(values.remove(0)),
+        Expression::Error { token, .. } => Err(EvalError::SyntaxError(token.clone())),
+        Expression::Index { token, .. } => {
+            let index = values.pop().unwrap();
+            index_value(token, values.pop().unwrap(), index)
+        }
+        Expression::Slice {
+            token, start, end, ..
+        } => {
+            let mut values = values.into_iter();
+            let target = values.next().unwrap();
+            let start = start.map(|_| values.next().unwrap());
+            let end = end.map(|_| values.next().unwrap());
+            slice_value(token, target, start, end)
+        }
+        Expression::Variable { token, name } => match env.get_symbol(*name) {
+            Some(value) => Ok(value.clone()),
+            None => {
+                let mut names = env.names();
+                names.extend(&["true", "false"]);
+                let suggestion = suggest(name.as_str(), &names).map(|s| s.to_string());
+                Err(EvalError::UndefinedVariable(token.clone(), suggestion))
+            }
+        },
+        Expression::Assignment { name, .. } => {
+            let value = values.remove(0);
+            env.set_symbol(*name, value.clone());
+            Ok(value)
+        }
+        Expression::Call { token, name, .. } => call_builtin(token, name.as_str(), values, env),
+        Expression::Tuple { .. } => Ok(Object::Tuple(values)),
+        Expression::TypeTest {
+            token, type_name, ..
+        } => {
+            let type_name = type_name.as_str();
+            if !TYPE_NAMES.contains(&type_name) {
+                let suggestion = suggest(type_name, &TYPE_NAMES).map(|s| s.to_string());
+                let name = type_name.to_string();
+                return Err(EvalError::UnknownType(token.clone(), name, suggestion));
+            }
+            Ok(Object::Boolean(values[0].type_name() == type_name))
+        }
+        Expression::MultipleAssignment { token, names, .. } => {
+            let value = values.remove(0);
+            // any other value is a single one
+            let elements = match &value {
+                Object::Tuple(elements) | Object::Array(elements) => elements.as_slice(),
+                value => std::slice::from_ref(value),
+            };
+            if elements.len() != names.len() {
+                let (expected, given) = (names.len(), elements.len());
+                return Err(EvalError::WrongNumberOfValues(
+                    token.clone(),
+                    expected,
+                    given,
+                ));
+            }
+            for (name, element) in names.iter().zip(elements) {
+                env.set_symbol(*name, element.clone());
+            }
+            Ok(value)
+        }
+        Expression::UnaryExpression { token, .. } if token.token_type == TokenType::Operator => {
+            call_operator(token, true, values, env)
+        }
+        Expression::BinaryExpression { token, .. } if token.token_type == TokenType::Operator => {
+            call_operator(token, false, values, env)
+        }
+        Expression::UnaryExpression { token, .. } => {
+            evaluate_unary_expression(token, values.remove(0), env)
+        }
+        Expression::BinaryExpression { token, .. } => {
+            let right = values.remove(1);
+            evaluate_binary_expression(token, values.remove(0), right, env)
         }
     }
 }
 
-fn evaluate_unary_expression(token: &Token, right: &Expression) -> EvalResult {
-    let right = evaluate(right)?;
+fn evaluate_unary_expression(token: &Token, right: Object, env: &Environment) -> EvalResult {
     match token.token_type {
-        TokenType::Bang => {
-            if let Object::Boolean(value) = right {
-                Ok(Object::Boolean(!value))
-            } else {
-                Err(error(
-                    "Invalid operand for '!', expected boolean expression",
-                    token,
-                ))
-            }
-        }
+        TokenType::Bang => match right.condition(env) {
+            Some(value) => Ok(Object::Boolean(!value)),
+            None => Err(EvalError::InvalidOperand(token.clone())),
+        },
         TokenType::Minus => {
             if let Object::Integer(value) = right {
-                Ok(Object::Integer(-value))
+                checked(token, value.checked_neg())
             } else {
-                Err(error(
-                    "Invalid operand for '-', expected integer expression",
-                    token,
-                ))
+                Err(EvalError::InvalidOperand(token.clone()))
             }
         }
-        _ => Err("Unreachable".to_string()),
+        _ => Err(EvalError::InvalidOperator(token.clone())),
     }
 }
 
-fn evaluate_binary_expression(token: &Token, left: &Expression, right: &Expression) -> EvalResult {
-    let left = evaluate(left)?;
-    let right = evaluate(right)?;
-
+fn evaluate_binary_expression(
+    token: &Token,
+    left: Object,
+    right: Object,
+    env: &Environment,
+) -> EvalResult {
     match token.token_type {
         TokenType::Minus => match (left, right) {
-            (Object::Integer(l), Object::Integer(r)) => Ok(Object::Integer(l - r)),
-            _ => Err(error("Invalid operands for '-'", token)),
+            (Object::Integer(l), Object::Integer(r)) => checked(token, l.checked_sub(r)),
+            _ => Err(EvalError::InvalidOperands(token.clone())),
         },
         TokenType::Plus => match (left, right) {
-            (Object::Integer(l), Object::Integer(r)) => Ok(Object::Integer(l + r)),
-            _ => Err(error("Invalid operands for '+'", token)),
+            (Object::Integer(l), Object::Integer(r)) => checked(token, l.checked_add(r)),
+            _ => Err(EvalError::InvalidOperands(token.clone())),
         },
         TokenType::Star => match (left, right) {
-            (Object::Integer(l), Object::Integer(r)) => Ok(Object::Integer(l * r)),
-            _ => Err(error("Invalid operands for '*'", token)),
+            (Object::Integer(l), Object::Integer(r)) => checked(token, l.checked_mul(r)),
+            (Object::String(s), Object::Integer(count))
+            | (Object::Integer(count), Object::String(s)) => repeat(token, &s, count, env),
+            _ => Err(EvalError::InvalidOperands(token.clone())),
         },
         TokenType::Slash => match (left, right) {
-            (Object::Integer(l), Object::Integer(r)) => Ok(Object::Integer(l / r)),
-            _ => Err(error("Invalid operands for '/'", token)),
+            (Object::Integer(_), Object::Integer(0)) => Err(EvalError::ArithmeticError(
+                token.clone(),
+                Arithmetic::DivisionByZero,
+            )),
+            // the only other failure is i32::MIN / -1
+            (Object::Integer(l), Object::Integer(r)) => checked(token, l.checked_div(r)),
+            _ => Err(EvalError::InvalidOperands(token.clone())),
         },
         TokenType::Greater => match (left, right) {
             (Object::Integer(l), Object::Integer(r)) => Ok(Object::Boolean(l > r)),
-            _ => Err(error("Invalid operands for '>'", token)),
+            _ => Err(EvalError::InvalidOperands(token.clone())),
         },
         TokenType::GreaterEquals => match (left, right) {
             (Object::Integer(l), Object::Integer(r)) => Ok(Object::Boolean(l >= r)),
-            _ => Err(error("Invalid operands for '>='", token)),
+            _ => Err(EvalError::InvalidOperands(token.clone())),
         },
         TokenType::SmallerEquals => match (left, right) {
             (Object::Integer(l), Object::Integer(r)) => Ok(Object::Boolean(l <= r)),
-            _ => Err(error("Invalid operands for '<='", token)),
+            _ => Err(EvalError::InvalidOperands(token.clone())),
         },
         TokenType::Smaller => match (left, right) {
             (Object::Integer(l), Object::Integer(r)) => Ok(Object::Boolean(l < r)),
-            _ => Err(error("Invalid operands for '<'", token)),
+            _ => Err(EvalError::InvalidOperands(token.clone())),
         },
         TokenType::Equals => match (left, right) {
             (Object::Integer(l), Object::Integer(r)) => Ok(Object::Boolean(l == r)),
             (Object::Boolean(l), Object::Boolean(r)) => Ok(Object::Boolean(l == r)),
-            _ => Err(error("Invalid operands for '=='", token)),
+            _ => Err(EvalError::InvalidOperands(token.clone())),
         },
         TokenType::BangEquals => match (left, right) {
             (Object::Integer(l), Object::Integer(r)) => Ok(Object::Boolean(l != r)),
             (Object::Boolean(l), Object::Boolean(r)) => Ok(Object::Boolean(l != r)),
-            _ => Err(error("Invalid operands for '!='", token)),
+            _ => Err(EvalError::InvalidOperands(token.clone())),
+        },
+        TokenType::In => match (left, right) {
+            (element, Object::Array(values)) | (element, Object::Tuple(values)) => {
+                Ok(Object::Boolean(values.contains(&element)))
+            }
+            (Object::String(s), Object::String(string)) => {
+                Ok(Object::Boolean(string.contains(s.as_str())))
+            }
+            _ => Err(EvalError::InvalidOperands(token.clone())),
         },
-        _ => Err("Unreachable".to_string()),
+        _ => Err(EvalError::InvalidOperator(token.clone())),
     }
 }
 
+/// The result of checked integer arithmetic, None if it overflowed
+fn checked(token: &Token, result: Option<i32>) -> EvalResult {
+    result
+        .map(Object::Integer)
+        .ok_or_else(|| EvalError::ArithmeticError(token.clone(), Arithmetic::Overflow))
+}
+
+/// The string repeated count times, failing before allocating it if it would exceed
+/// the memory limit
+fn repeat(token: &Token, string: &str, count: i32, env: &Environment) -> EvalResult {
+    if count < 0 {
+        return Err(EvalError::NegativeRepetition(token.clone()));
+    }
+    let count = count as usize;
+    let max = env.options().max_memory.unwrap_or(usize::MAX);
+    match string.len().checked_mul(count) {
+        Some(length) if length <= max => Ok(Object::String(string.repeat(count))),
+        _ => Err(EvalError::ResourceExhausted(
+            token.clone(),
+            Resource::Memory,
+        )),
+    }
+}
+
+/// The element of an array or tuple or the char of a string at index, counted from the
+/// end if it is negative
+fn index_value(token: &Token, target: Object, index: Object) -> EvalResult {
+    match (target, index) {
+        (Object::Array(mut values), Object::Integer(index))
+        | (Object::Tuple(mut values), Object::Integer(index)) => {
+            let position = array_index(token, index, values.len())?;
+            Ok(values.swap_remove(position))
+        }
+        (Object::String(string), Object::Integer(index)) => {
+            let position = array_index(token, index, string.chars().count())?;
+            let c = string.chars().nth(position).unwrap();
+            Ok(Object::String(c.to_string()))
+        }
+        _ => Err(EvalError::InvalidOperands(token.clone())),
+    }
+}
+
+/// The elements of an array or tuple or the chars of a string from start (inclusive) to
+/// end (exclusive), which default to the start and the end of the target
+fn slice_value(
+    token: &Token,
+    target: Object,
+    start: Option<Object>,
+    end: Option<Object>,
+) -> EvalResult {
+    let length = match &target {
+        Object::Array(values) | Object::Tuple(values) => values.len(),
+        Object::String(string) => string.chars().count(),
+        _ => return Err(EvalError::InvalidOperands(token.clone())),
+    };
+    let start = slice_bound(token, start, 0, length)?;
+    let end = slice_bound(token, end, length, length)?.max(start);
+    match target {
+        Object::Array(mut values) => Ok(Object::Array(values.drain(start..end).collect())),
+        Object::Tuple(mut values) => Ok(Object::Tuple(values.drain(start..end).collect())),
+        Object::String(string) => Ok(Object::String(
+            string.chars().skip(start).take(end - start).collect(),
+        )),
+        _ => unreachable!("the length of the target was computed"),
+    }
+}
+
+/// The position of a bound of a slice, which can be the length of the target
+fn slice_bound(
+    token: &Token,
+    bound: Option<Object>,
+    default: usize,
+    length: usize,
+) -> Result<usize, EvalError> {
+    match bound {
+        None => Ok(default),
+        Some(Object::Integer(bound)) if bound == length as i32 => Ok(length),
+        Some(Object::Integer(bound)) => array_index(token, bound, length),
+        Some(_) => Err(EvalError::InvalidOperands(token.clone())),
+    }
+}
+
+fn operator_symbol(token_type: &TokenType) -> &'static str {
+    token_type.symbol().unwrap_or("?")
+}
+
 fn error(msg: &str, token: &Token) -> String {
     if let TokenType::EOF = token.token_type {
         return format!("Error at end of file: {}", msg);
@@ -122,189 +896,539 @@ fn error(msg: &str, token: &Token) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::interner::Symbol;
+    use crate::EvalOptions;
+    use std::time::Duration;
 
     #[test]
     fn evaluate_boolean_literal() {
-        let result = evaluate(&Expression::BooleanLiteral {
-            token: token(TokenType::BooleanLiteral),
-            value: false,
-        })
-        .unwrap();
+        let mut ast = Ast::new();
+        let literal = ast.boolean(false);
+        let result = evaluate(&ast, literal).unwrap();
         assert_eq!(result, Object::Boolean(false));
     }
 
     #[test]
     fn evaluate_arithmetic_expression() {
         // 3 - 30 / 6
-        let ast = Expression::BinaryExpression {
-            token: token(TokenType::Minus),
-            left: Box::new(Expression::IntegerLiteral {
-                token: token(TokenType::IntegerLiteral),
-                value: 3,
-            }),
-            right: Box::new(integer_binary_expr(TokenType::Slash, 30, 6)),
-        };
-        let result = evaluate(&ast).unwrap();
+        let mut ast = Ast::new();
+        let left = ast.integer(3);
+        let right = integer_binary_expr(&mut ast, TokenType::Slash, 30, 6);
+        let expression = ast.binary(TokenType::Minus, left, right);
+        let result = evaluate(&ast, expression).unwrap();
         assert_eq!(result, Object::Integer(-2));
     }
 
+    #[test]
+    fn arithmetic_errors() {
+        let error = |result: EvalResult| match result {
+            Err(EvalError::ArithmeticError(_, arithmetic)) => arithmetic,
+            result => panic!("{:?}", result),
+        };
+        let (max, min) = (i32::MAX, i32::MIN);
+        let division_by_zero = evaluate_integers(TokenType::Slash, 1, 0);
+        assert_eq!(error(division_by_zero), Arithmetic::DivisionByZero);
+        for (operator, left, right) in [
+            (TokenType::Plus, max, 1),
+            (TokenType::Minus, min, 1),
+            (TokenType::Star, max, 2),
+            (TokenType::Slash, min, -1),
+        ] {
+            let result = evaluate_integers(operator, left, right);
+            assert_eq!(error(result), Arithmetic::Overflow);
+        }
+        let mut ast = Ast::new();
+        let right = ast.integer(min);
+        let negation = ast.unary(TokenType::Minus, right);
+        let result = evaluate(&ast, negation);
+        assert_eq!(result.unwrap_err().message(), "Integer overflow in '-'");
+        assert_eq!(
+            evaluate_integers(TokenType::Plus, max - 1, 1),
+            Ok(Object::Integer(max))
+        );
+    }
+
     #[test]
     fn evaluate_integer_equality() {
-        let result = evaluate(&integer_binary_expr(TokenType::Equals, 30, 30)).unwrap();
+        let result = evaluate_integers(TokenType::Equals, 30, 30).unwrap();
         assert_eq!(result, Object::Boolean(true));
 
-        let result = evaluate(&integer_binary_expr(TokenType::Equals, 34, 30)).unwrap();
+        let result = evaluate_integers(TokenType::Equals, 34, 30).unwrap();
         assert_eq!(result, Object::Boolean(false));
 
-        let result = evaluate(&integer_binary_expr(TokenType::BangEquals, 30, 30)).unwrap();
+        let result = evaluate_integers(TokenType::BangEquals, 30, 30).unwrap();
         assert_eq!(result, Object::Boolean(false));
 
-        let result = evaluate(&integer_binary_expr(TokenType::BangEquals, 34, 30)).unwrap();
+        let result = evaluate_integers(TokenType::BangEquals, 34, 30).unwrap();
         assert_eq!(result, Object::Boolean(true));
     }
 
     #[test]
     fn evaluate_bool_equality() {
-        let result = evaluate(&bool_binary_expr(TokenType::Equals, true, true)).unwrap();
+        let result = evaluate_bools(TokenType::Equals, true, true).unwrap();
         assert_eq!(result, Object::Boolean(true));
 
-        let result = evaluate(&bool_binary_expr(TokenType::Equals, false, false)).unwrap();
+        let result = evaluate_bools(TokenType::Equals, false, false).unwrap();
         assert_eq!(result, Object::Boolean(true));
 
-        let result = evaluate(&bool_binary_expr(TokenType::Equals, true, false)).unwrap();
+        let result = evaluate_bools(TokenType::Equals, true, false).unwrap();
         assert_eq!(result, Object::Boolean(false));
 
-        let result = evaluate(&bool_binary_expr(TokenType::BangEquals, true, false)).unwrap();
+        let result = evaluate_bools(TokenType::BangEquals, true, false).unwrap();
         assert_eq!(result, Object::Boolean(true));
 
-        let result = evaluate(&bool_binary_expr(TokenType::BangEquals, false, false)).unwrap();
+        let result = evaluate_bools(TokenType::BangEquals, false, false).unwrap();
         assert_eq!(result, Object::Boolean(false));
     }
 
     #[test]
     fn evaluate_integer_comparison() {
-        let result = evaluate(&integer_binary_expr(TokenType::Smaller, 30, 30)).unwrap();
+        let result = evaluate_integers(TokenType::Smaller, 30, 30).unwrap();
         assert_eq!(result, Object::Boolean(false));
 
-        let result = evaluate(&integer_binary_expr(TokenType::SmallerEquals, 30, 30)).unwrap();
+        let result = evaluate_integers(TokenType::SmallerEquals, 30, 30).unwrap();
         assert_eq!(result, Object::Boolean(true));
 
-        let result = evaluate(&integer_binary_expr(TokenType::Greater, 30, 30)).unwrap();
+        let result = evaluate_integers(TokenType::Greater, 30, 30).unwrap();
         assert_eq!(result, Object::Boolean(false));
 
-        let result = evaluate(&integer_binary_expr(TokenType::GreaterEquals, 30, 30)).unwrap();
+        let result = evaluate_integers(TokenType::GreaterEquals, 30, 30).unwrap();
         assert_eq!(result, Object::Boolean(true));
     }
 
     #[test]
     fn evaluate_grouping() {
         // (12 - 10) * 8
-        let ast = Expression::BinaryExpression {
-            token: token(TokenType::Star),
-            left: Box::new(Expression::Grouping {
-                token: token(TokenType::LeftBrace),
-                expr: Box::new(integer_binary_expr(TokenType::Minus, 12, 10)),
-            }),
-            right: Box::new(Expression::IntegerLiteral {
-                token: token(TokenType::IntegerLiteral),
-                value: 8,
-            }),
-        };
-        let result = evaluate(&ast).unwrap();
+        let mut ast = Ast::new();
+        let expr = integer_binary_expr(&mut ast, TokenType::Minus, 12, 10);
+        let left = ast.grouping(expr);
+        let right = ast.integer(8);
+        let expression = ast.binary(TokenType::Star, left, right);
+        let result = evaluate(&ast, expression).unwrap();
         assert_eq!(result, Object::Integer(16));
     }
 
     #[test]
     #[should_panic(expected = "Invalid operand for '!'")]
     fn wrong_bang_operand() {
-        let ast = Expression::UnaryExpression {
-            token: token(TokenType::Bang),
-            right: Box::new(Expression::IntegerLiteral {
-                token: token(TokenType::IntegerLiteral),
-                value: 0,
-            }),
-        };
-        let result = evaluate(&ast);
+        let mut ast = Ast::new();
+        let right = ast.integer(0);
+        let expression = ast.unary(TokenType::Bang, right);
+        let result = evaluate(&ast, expression);
         panic_on_error(result);
     }
 
+    #[test]
+    fn membership() {
+        let mut ast = Ast::new();
+        let (one, two, a) = (ast.integer(1), ast.integer(2), ast.string("a"));
+        let tuple = ast.tuple(vec![one, a]);
+        let contains = |element: ExprId, container: ExprId, ast: &mut Ast| {
+            let expression = ast.binary(TokenType::In, element, container);
+            evaluate(ast, expression).map_err(|e| e.code())
+        };
+        assert_eq!(contains(a, tuple, &mut ast), Ok(Object::Boolean(true)));
+        assert_eq!(contains(two, tuple, &mut ast), Ok(Object::Boolean(false)));
+        let (string, empty) = (ast.string("bab"), ast.string(""));
+        assert_eq!(contains(a, string, &mut ast), Ok(Object::Boolean(true)));
+        assert_eq!(contains(empty, string, &mut ast), Ok(Object::Boolean(true)));
+        assert_eq!(contains(one, string, &mut ast), Err("E0004"));
+        assert_eq!(contains(one, two, &mut ast), Err("E0004"));
+    }
+
+    #[test]
+    fn type_tests() {
+        let mut ast = Ast::new();
+        let one = ast.integer(1);
+        let tuple = ast.tuple(vec![one]);
+        let type_of = ast.call("type", vec![tuple]);
+        assert_eq!(evaluate(&ast, type_of), Ok(Object::String("Tuple".into())));
+        for &type_name in &TYPE_NAMES {
+            let test = ast.type_test(tuple, type_name);
+            let expected = Object::Boolean(type_name == "Tuple");
+            assert_eq!(evaluate(&ast, test), Ok(expected));
+        }
+        let test = ast.type_test(one, "Integr");
+        assert_eq!(
+            evaluate(&ast, test),
+            Err(EvalError::UnknownType(
+                ast[test].token().clone(),
+                "Integr".into(),
+                Some("Integer".into())
+            ))
+        );
+    }
+
+    #[test]
+    fn repeat_strings() {
+        let mut ast = Ast::new();
+        let mut repeat = |string: &str, count: i32| {
+            let string = ast.string(string);
+            let count = ast.integer(count);
+            let expression = ast.binary(TokenType::Star, count, string);
+            evaluate(&ast, expression).map_err(|e| e.code())
+        };
+        assert_eq!(repeat("ab", 3), Ok(Object::String("ababab".to_string())));
+        assert_eq!(repeat("ab", 0), Ok(Object::String(String::new())));
+        assert_eq!(repeat("ab", -1), Err("E0024"));
+        let mut env = Environment::with_options(EvalOptions {
+            max_memory: Some(1000),
+            ..EvalOptions::default()
+        });
+        let string = ast.string("ab");
+        let count = ast.integer(i32::MAX);
+        let expression = ast.binary(TokenType::Star, string, count);
+        assert_eq!(
+            evaluate_with_environment(&ast, expression, &mut env),
+            Err(EvalError::ResourceExhausted(
+                ast[expression].token().clone(),
+                Resource::Memory
+            ))
+        );
+    }
+
+    #[test]
+    fn index_and_slice() {
+        let mut ast = Ast::new();
+        let target = ast.string("añb");
+        let mut bound = |value: Option<i32>| value.map(|value| ast.integer(value));
+        let (last, start, end) = (bound(Some(-1)), bound(Some(1)), bound(Some(3)));
+        let (too_large, empty) = (bound(Some(4)), bound(None));
+        let index = ast.index(target, last.unwrap());
+        assert_eq!(evaluate(&ast, index), Ok(Object::String("b".to_string())));
+        let slice = ast.slice(target, start, end);
+        assert_eq!(evaluate(&ast, slice), Ok(Object::String("ñb".to_string())));
+        let slice = ast.slice(target, end, empty);
+        assert_eq!(evaluate(&ast, slice), Ok(Object::String(String::new())));
+        let slice = ast.slice(target, empty, too_large);
+        assert_eq!(
+            evaluate(&ast, slice),
+            Err(EvalError::IndexOutOfBounds(
+                ast[slice].token().clone(),
+                4,
+                3
+            ))
+        );
+        let index = ast.index(start.unwrap(), start.unwrap());
+        assert_eq!(
+            evaluate(&ast, index),
+            Err(EvalError::InvalidOperands(ast[index].token().clone()))
+        );
+    }
+
+    #[test]
+    fn multiple_assignment() {
+        let mut env = Environment::new();
+        let mut ast = Ast::new();
+        let (one, two) = (ast.integer(1), ast.integer(2));
+        let values = ast.tuple(vec![one, two]);
+        let assignment = ast.multiple_assignment(&["a", "b"], values);
+        evaluate_with_environment(&ast, assignment, &mut env).unwrap();
+        let (a, b) = (ast.variable("a"), ast.variable("b"));
+        let swapped = ast.tuple(vec![b, a]);
+        let swap = ast.multiple_assignment(&["a", "b"], swapped);
+        assert_eq!(
+            evaluate_with_environment(&ast, swap, &mut env),
+            Ok(Object::Tuple(vec![Object::Integer(2), Object::Integer(1)]))
+        );
+        assert_eq!(
+            env.get_symbol(Symbol::intern("a")),
+            Some(&Object::Integer(2))
+        );
+        let assignment = ast.multiple_assignment(&["a", "b", "c"], values);
+        assert_eq!(
+            evaluate_with_environment(&ast, assignment, &mut env),
+            Err(EvalError::WrongNumberOfValues(
+                ast[assignment].token().clone(),
+                3,
+                2
+            ))
+        );
+    }
+
+    #[test]
+    fn truthiness() {
+        let mut env = Environment::with_options(EvalOptions {
+            truthiness: true,
+            ..EvalOptions::default()
+        });
+        let mut ast = Ast::new();
+        let mut negate = |value: ExprId, ast: &mut Ast| {
+            let expression = ast.unary(TokenType::Bang, value);
+            evaluate_with_environment(ast, expression, &mut env)
+        };
+        let falsy = [ast.integer(0), ast.string(""), ast.boolean(false)];
+        for value in falsy {
+            assert_eq!(negate(value, &mut ast), Ok(Object::Boolean(true)));
+        }
+        let truthy = [ast.integer(-1), ast.string("0"), ast.boolean(true)];
+        for value in truthy {
+            assert_eq!(negate(value, &mut ast), Ok(Object::Boolean(false)));
+        }
+        assert!(Object::Array(vec![Object::Boolean(false)]).is_truthy());
+        assert!(!Object::Array(Vec::new()).is_truthy());
+    }
+
     #[test]
     #[should_panic(expected = "Invalid operand for '-'")]
     fn wrong_minus_operand() {
-        let ast = Expression::UnaryExpression {
-            token: token(TokenType::Minus),
-            right: Box::new(Expression::BooleanLiteral {
-                token: token(TokenType::BooleanLiteral),
-                value: false,
-            }),
-        };
-        let result = evaluate(&ast);
+        let mut ast = Ast::new();
+        let right = ast.boolean(false);
+        let expression = ast.unary(TokenType::Minus, right);
+        let result = evaluate(&ast, expression);
         panic_on_error(result);
     }
 
     #[test]
     #[should_panic(expected = "Invalid operands for '>='")]
     fn invalid_operands_greater_equals() {
-        let result = evaluate(&bool_binary_expr(TokenType::GreaterEquals, false, false));
+        let result = evaluate_bools(TokenType::GreaterEquals, false, false);
         panic_on_error(result);
     }
 
     #[test]
     #[should_panic(expected = "Invalid operands for '>'")]
     fn invalid_operands_greater() {
-        let result = evaluate(&bool_binary_expr(TokenType::Greater, false, false));
+        let result = evaluate_bools(TokenType::Greater, false, false);
         panic_on_error(result);
     }
 
     #[test]
     #[should_panic(expected = "Invalid operands for '<'")]
     fn invalid_operands_smaller() {
-        let result = evaluate(&bool_binary_expr(TokenType::Smaller, false, false));
+        let result = evaluate_bools(TokenType::Smaller, false, false);
         panic_on_error(result);
     }
 
     #[test]
     #[should_panic(expected = "Invalid operands for '<='")]
     fn invalid_operands_smaller_equals() {
-        let result = evaluate(&bool_binary_expr(TokenType::SmallerEquals, false, false));
+        let result = evaluate_bools(TokenType::SmallerEquals, false, false);
         panic_on_error(result);
     }
 
-    fn token(token_type: TokenType) -> Token {
-        Token::new(token_type, String::new(), -1)
+    #[test]
+    fn error_codes() {
+        let error = evaluate_bools(TokenType::Plus, true, false).unwrap_err();
+        assert_eq!(error.code(), "E0004");
+        assert!(error.to_string().ends_with("[E0004]"));
     }
 
-    fn panic_on_error(result: EvalResult) {
-        if let Err(msg) = result {
-            panic!(msg);
+    #[test]
+    fn evaluate_assignment() {
+        let mut env = Environment::new();
+        let mut ast = Ast::new();
+        let value = integer_binary_expr(&mut ast, TokenType::Plus, 1, 2);
+        let assignment = ast.assignment("x", value);
+        let result = evaluate_with_environment(&ast, assignment, &mut env).unwrap();
+        assert_eq!(result, Object::Integer(3));
+
+        let variable = ast.variable("x");
+        let result = evaluate_with_environment(&ast, variable, &mut env).unwrap();
+        assert_eq!(result, Object::Integer(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "Undefined variable 'flase', did you mean 'false'?")]
+    fn undefined_variable() {
+        let mut ast = Ast::new();
+        let variable = ast.variable("flase");
+        panic_on_error(evaluate(&ast, variable));
+    }
+
+    #[test]
+    fn evaluate_call() {
+        let mut env = Environment::new();
+        env.set_arguments(vec![String::from("sapo")]);
+        let mut ast = Ast::new();
+        let call = ast.call("args", vec![]);
+        let result = evaluate_with_environment(&ast, call, &mut env).unwrap();
+        assert_eq!(
+            result,
+            Object::Array(vec![Object::String(String::from("sapo"))])
+        );
+    }
+
+    #[test]
+    fn nesting_limit() {
+        // -(-(-1))
+        let mut ast = Ast::new();
+        let literal = ast.integer(1);
+        let mut expression = literal;
+        for _ in 0..3 {
+            expression = ast.unary(TokenType::Minus, expression);
         }
+        let mut env = Environment::with_options(max_depth(3));
+        assert_eq!(
+            evaluate_with_environment(&ast, expression, &mut env),
+            Ok(Object::Integer(-1))
+        );
+        env.set_options(max_depth(2));
+        let error = evaluate_with_environment(&ast, expression, &mut env).unwrap_err();
+        assert_eq!(
+            error,
+            EvalError::NestingTooDeep(ast[literal].token().clone())
+        );
+        // the depth is restored after an error
+        env.set_options(max_depth(3));
+        assert!(evaluate_with_environment(&ast, expression, &mut env).is_ok());
     }
 
-    fn integer_binary_expr(token_type: TokenType, left: i32, right: i32) -> Expression {
-        Expression::BinaryExpression {
-            token: token(token_type),
-            left: Box::new(Expression::IntegerLiteral {
-                token: token(TokenType::IntegerLiteral),
-                value: left,
-            }),
-            right: Box::new(Expression::IntegerLiteral {
-                token: token(TokenType::IntegerLiteral),
-                value: right,
-            }),
+    #[test]
+    fn step_limit() {
+        // 1 + 2 evaluates three expressions
+        let mut ast = Ast::new();
+        let expression = integer_binary_expr(&mut ast, TokenType::Plus, 1, 2);
+        let mut env = Environment::with_options(EvalOptions {
+            max_steps: Some(3),
+            ..EvalOptions::default()
+        });
+        assert_eq!(
+            evaluate_with_environment(&ast, expression, &mut env),
+            Ok(Object::Integer(3))
+        );
+        // the steps are counted again for every evaluation
+        assert!(evaluate_with_environment(&ast, expression, &mut env).is_ok());
+        env.set_options(EvalOptions {
+            max_steps: Some(2),
+            ..EvalOptions::default()
+        });
+        assert_eq!(
+            evaluate_with_environment(&ast, expression, &mut env),
+            Err(EvalError::ResourceExhausted(
+                ast[ast[expression].children()[1]].token().clone(),
+                Resource::Steps
+            ))
+        );
+    }
+
+    #[test]
+    fn memory_limit() {
+        let mut env = Environment::with_options(EvalOptions {
+            max_memory: Some(100),
+            ..EvalOptions::default()
+        });
+        let mut ast = Ast::new();
+        let mut assignment = |value: &str| {
+            let value = ast.string(value);
+            let assignment = ast.assignment("x", value);
+            evaluate_with_environment(&ast, assignment, &mut env)
+        };
+        assert!(assignment("short").is_ok());
+        // replacing a variable frees its old value
+        assert!(assignment("short").is_ok());
+        let error = assignment(&"long".repeat(25));
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "Error at line -1: Evaluation exceeded its memory limit [E0015]"
+        );
+    }
+
+    #[test]
+    fn memoize() {
+        let mut env = Environment::with_options(EvalOptions {
+            memoize: true,
+            ..EvalOptions::default()
+        });
+        let mut ast = Ast::new();
+        let x = ast.variable("x");
+        let two = ast.integer(2);
+        let product = ast.binary(TokenType::Star, x, two);
+        let sum = integer_binary_expr(&mut ast, TokenType::Plus, 1, 2);
+        let call = ast.call("assert_eq", vec![sum, sum]);
+        let statements = [ast.assignment("x", sum), product, call];
+        let pure = purity(&ast, call);
+        assert_eq!((pure[&call], pure[&sum], pure.len()), (false, true, 4));
+        for &statement in &statements {
+            assert!(evaluate_with_environment(&ast, statement, &mut env).is_ok());
         }
+        // a memoized expression takes a single step, but not the calls containing one
+        env.set_options(EvalOptions {
+            memoize: true,
+            max_steps: Some(1),
+            ..EvalOptions::default()
+        });
+        assert_eq!(
+            evaluate_with_environment(&ast, product, &mut env),
+            Ok(Object::Integer(6))
+        );
+        assert!(evaluate_with_environment(&ast, call, &mut env).is_err());
+        // the value depends on the ones of the variables
+        let four = ast.integer(4);
+        let assignment = ast.assignment("x", four);
+        env.set_options(EvalOptions {
+            memoize: true,
+            ..EvalOptions::default()
+        });
+        assert!(evaluate_with_environment(&ast, assignment, &mut env).is_ok());
+        assert_eq!(
+            evaluate_with_environment(&ast, product, &mut env),
+            Ok(Object::Integer(8))
+        );
+    }
+
+    #[test]
+    fn timeout() {
+        let mut env = Environment::with_options(EvalOptions {
+            timeout: Some(Duration::from_secs(0)),
+            ..EvalOptions::default()
+        });
+        let mut ast = Ast::new();
+        let expression = integer_binary_expr(&mut ast, TokenType::Plus, 1, 2);
+        assert_eq!(
+            evaluate_with_environment(&ast, expression, &mut env),
+            Err(EvalError::ResourceExhausted(
+                ast[expression].token().clone(),
+                Resource::Time
+            ))
+        );
     }
 
-    fn bool_binary_expr(token_type: TokenType, left: bool, right: bool) -> Expression {
-        Expression::BinaryExpression {
-            token: token(token_type),
-            left: Box::new(Expression::BooleanLiteral {
-                token: token(TokenType::BooleanLiteral),
-                value: left,
-            }),
-            right: Box::new(Expression::BooleanLiteral {
-                token: token(TokenType::BooleanLiteral),
-                value: right,
-            }),
+    fn max_depth(max_depth: usize) -> EvalOptions {
+        EvalOptions {
+            max_depth,
+            ..EvalOptions::default()
         }
     }
+
+    #[test]
+    fn object_to_json() {
+        let object = Object::Array(vec![
+            Object::Integer(-1),
+            Object::Boolean(false),
+            Object::String(String::from("\"sapo\"")),
+        ]);
+        assert_eq!(object.to_json(), r#"[-1,false,"\"sapo\""]"#);
+        let object = Object::Tuple(vec![Object::Array(vec![]), object]);
+        assert_eq!(
+            object.to_json_pretty(),
+            "[\n  [],\n  [\n    -1,\n    false,\n    \"\\\"sapo\\\"\"\n  ]\n]"
+        );
+    }
+
+    fn panic_on_error(result: EvalResult) {
+        if let Err(msg) = result {
+            panic!("{}", msg);
+        }
+    }
+
+    fn integer_binary_expr(ast: &mut Ast, token_type: TokenType, left: i32, right: i32) -> ExprId {
+        let left = ast.integer(left);
+        let right = ast.integer(right);
+        ast.binary(token_type, left, right)
+    }
+
+    fn evaluate_integers(token_type: TokenType, left: i32, right: i32) -> EvalResult {
+        let mut ast = Ast::new();
+        let expression = integer_binary_expr(&mut ast, token_type, left, right);
+        evaluate(&ast, expression)
+    }
+
+    fn evaluate_bools(token_type: TokenType, left: bool, right: bool) -> EvalResult {
+        let mut ast = Ast::new();
+        let left = ast.boolean(left);
+        let right = ast.boolean(right);
+        let expression = ast.binary(token_type, left, right);
+        evaluate(&ast, expression)
+    }
 }
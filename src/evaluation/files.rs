@@ -0,0 +1,35 @@
+use super::{Environment, Object};
+use std::fs;
+
+/// Names and arities of the functions Environment::enable_io registers
+pub(crate) const FILE_FUNCTIONS: [(&str, usize); 3] =
+    [("read_file", 1), ("read_lines", 1), ("write_file", 2)];
+
+pub(crate) fn register_file_functions(env: &mut Environment) {
+    env.register_fn("read_file", 1, |arguments| {
+        let path = string(arguments, 0)?;
+        fs::read_to_string(path)
+            .map(Object::String)
+            .map_err(|error| format!("{}: {}", path, error))
+    });
+    env.register_fn("read_lines", 1, |arguments| {
+        let path = string(arguments, 0)?;
+        let text = fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+        let lines = text.lines().map(|line| Object::String(line.to_string()));
+        Ok::<_, String>(Object::Array(lines.collect()))
+    });
+    env.register_fn("write_file", 2, |arguments| {
+        let path = string(arguments, 0)?;
+        fs::write(path, string(arguments, 1)?)
+            .map(|()| Object::Boolean(true))
+            .map_err(|error| format!("{}: {}", path, error))
+    });
+}
+
+/// The argument at index, which must be a String
+pub(super) fn string(arguments: &[Object], index: usize) -> Result<&str, String> {
+    match &arguments[index] {
+        Object::String(value) => Ok(value),
+        value => Err(format!("expected a String, but got {}", value.type_name())),
+    }
+}
@@ -0,0 +1,91 @@
+use super::{EvalError, Object};
+use crate::ast::Expression;
+
+/// Callbacks the evaluator invokes while running a program, register them with
+/// Environment::add_hook to implement tracing, logging or coverage.
+/// All of them do nothing by default. Hooks must be Send so that environments
+/// can be moved to other threads.
+pub trait EvalHook: Send {
+    /// Called before evaluating each of the top-level expressions of a program
+    fn on_statement(&mut self, _statement: &Expression) {}
+
+    /// Called before evaluating any expression, including nested ones
+    fn on_enter_expression(&mut self, _expression: &Expression) {}
+
+    /// Called after evaluating an expression with its result
+    fn on_exit_expression(
+        &mut self,
+        _expression: &Expression,
+        _result: &Result<Object, EvalError>,
+    ) {
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{evaluate_program, parse_program, Environment};
+    use std::sync::{Arc, Mutex};
+
+    /// Records the lexeme of the token of every expression
+    struct Tracer {
+        events: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl EvalHook for Tracer {
+        fn on_statement(&mut self, statement: &Expression) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("statement {}", lexeme(statement)));
+        }
+
+        fn on_enter_expression(&mut self, expression: &Expression) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("enter {}", lexeme(expression)));
+        }
+
+        fn on_exit_expression(&mut self, _: &Expression, result: &Result<Object, EvalError>) {
+            let result = match result {
+                Ok(value) => value.to_string(),
+                Err(error) => error.code().to_string(),
+            };
+            self.events.lock().unwrap().push(format!("exit {}", result));
+        }
+    }
+
+    #[test]
+    fn hooks_are_called_in_evaluation_order() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut env = Environment::new();
+        env.add_hook(Box::new(Tracer {
+            events: Arc::clone(&events),
+        }));
+        let program = parse_program("-1; x").unwrap();
+        assert!(evaluate_program(&program, &mut env).is_err());
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                "statement -",
+                "enter -",
+                "enter 1",
+                "exit 1",
+                "exit -1",
+                "statement x",
+                "enter x",
+                "exit E0006",
+            ]
+        );
+    }
+
+    fn lexeme(expression: &Expression) -> &str {
+        match expression {
+            Expression::UnaryExpression { token, .. }
+            | Expression::IntegerLiteral { token, .. }
+            | Expression::Variable { token, .. } => &token.lexeme,
+            _ => "?",
+        }
+    }
+}
@@ -1,3 +1,27 @@
+mod asynchronous;
+mod builtins;
+mod cancellation;
+mod coverage;
+mod debugger;
+mod environment;
+#[allow(clippy::module_inception)]
 mod evaluation;
+mod files;
+mod hooks;
+#[cfg(feature = "regex")]
+mod patterns;
+mod process;
+mod random;
+#[cfg(feature = "serde")]
+mod serialization;
 
+pub use asynchronous::*;
+pub use builtins::{builtin_arity, builtin_max_arity, is_variadic, BUILTINS};
+pub use cancellation::*;
+pub use coverage::*;
+pub use debugger::*;
+pub use environment::*;
 pub use evaluation::*;
+pub(crate) use files::FILE_FUNCTIONS;
+pub use hooks::*;
+pub(crate) use process::PROCESS_FUNCTIONS;
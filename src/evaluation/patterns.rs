@@ -0,0 +1,68 @@
+use super::{Environment, EvalError, Object};
+use crate::token::Token;
+use regex::Regex;
+use std::collections::HashMap;
+
+/// How many compiled patterns an environment keeps
+const MAX_PATTERNS: usize = 64;
+
+/// The regular expressions of the regex built-ins by their pattern, so that calling them
+/// in a loop compiles each pattern once
+#[derive(Debug, Default)]
+pub(crate) struct Patterns {
+    pub(super) compiled: HashMap<String, Regex>,
+}
+
+impl Patterns {
+    /// The compiled pattern, or a description of why it is invalid
+    fn get(&mut self, pattern: &str) -> Result<&Regex, String> {
+        if !self.compiled.contains_key(pattern) {
+            // the description is the last line of the error, the others point at the pattern
+            let regex = Regex::new(pattern).map_err(|error| {
+                let message = error.to_string();
+                let last = message.lines().last().unwrap_or_default();
+                last.trim_start_matches("error: ").to_string()
+            })?;
+            if self.compiled.len() == MAX_PATTERNS {
+                self.compiled.clear();
+            }
+            self.compiled.insert(pattern.to_string(), regex);
+        }
+        Ok(&self.compiled[pattern])
+    }
+}
+
+/// Calls regex_match, regex_find_all or regex_replace, whose arguments are
+/// a string, a pattern and for regex_replace the replacement
+pub(crate) fn call_regex(
+    token: &Token,
+    name: &str,
+    arguments: &[Object],
+    env: &mut Environment,
+) -> Result<Object, EvalError> {
+    let strings: Option<Vec<&str>> = arguments
+        .iter()
+        .map(|argument| match argument {
+            Object::String(string) => Some(string.as_str()),
+            _ => None,
+        })
+        .collect();
+    let strings = strings.ok_or_else(|| EvalError::InvalidArgument(token.clone(), "String"))?;
+    let regex = env
+        .patterns()
+        .get(strings[1])
+        .map_err(|description| EvalError::InvalidPattern(token.clone(), description))?;
+    match name {
+        "regex_match" => Ok(Object::Boolean(regex.is_match(strings[0]))),
+        "regex_find_all" => Ok(Object::Array(
+            regex
+                .find_iter(strings[0])
+                .map(|found| Object::String(found.as_str().to_string()))
+                .collect(),
+        )),
+        "regex_replace" => Ok(Object::String(
+            regex.replace_all(strings[0], strings[2]).into_owned(),
+        )),
+        _ => unreachable!("'{}' isn't a regex built-in", name),
+    }
+}
@@ -0,0 +1,58 @@
+use super::files::string;
+use super::{Environment, Object};
+use std::env;
+use std::process::Command;
+
+/// Names and arities of the functions Environment::enable_process registers
+pub(crate) const PROCESS_FUNCTIONS: [(&str, usize); 4] =
+    [("cwd", 0), ("env", 1), ("run", 1), ("set_env", 2)];
+
+pub(crate) fn register_process_functions(env: &mut Environment) {
+    env.register_fn("env", 1, |arguments| {
+        let name = string(arguments, 0)?;
+        // unset variables are empty, like in shells
+        Ok::<_, String>(Object::String(env::var(name).unwrap_or_default()))
+    });
+    env.register_fn("set_env", 2, |arguments| {
+        let name = string(arguments, 0)?;
+        let value = string(arguments, 1)?;
+        if name.is_empty() || name.contains(['=', '\0']) || value.contains('\0') {
+            return Err(format!("invalid variable {:?}", name));
+        }
+        env::set_var(name, value);
+        Ok(Object::Boolean(true))
+    });
+    env.register_fn("cwd", 0, |_| {
+        let path = env::current_dir().map_err(|error| error.to_string())?;
+        Ok::<_, String>(Object::String(path.to_string_lossy().into_owned()))
+    });
+    env.register_fn("run", 1, |arguments| {
+        let command = string(arguments, 0)?;
+        let output = shell(command)
+            .output()
+            .map_err(|error| format!("{}: {}", command, error))?;
+        if !output.status.success() {
+            let mut message = format!("{}: {}", command, output.status);
+            let error = String::from_utf8_lossy(&output.stderr);
+            if !error.trim().is_empty() {
+                message = format!("{}, {}", message, error.trim_end());
+            }
+            return Err(message);
+        }
+        let output = String::from_utf8_lossy(&output.stdout).into_owned();
+        Ok(Object::String(output))
+    });
+}
+
+/// Runs command with the shell of the platform
+fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.args(["/C", command]);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.args(["-c", command]);
+        shell
+    }
+}
@@ -0,0 +1,57 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+/// Generates the numbers of random(), rand_int() and shuffle() with SplitMix64, which is
+/// fast and good enough for scripts but not for cryptography
+#[derive(Debug, Clone)]
+pub(crate) struct Random {
+    state: u64,
+}
+
+impl Random {
+    /// Starts from seed, or from a different state each time if it is None
+    pub(crate) fn new(seed: Option<u64>) -> Random {
+        let seed = seed.unwrap_or_else(|| RandomState::new().build_hasher().finish());
+        Random { state: seed }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number from 0 to bound - 1, all of them equally likely
+    ///
+    /// # Panics
+    ///
+    /// If bound is 0
+    pub(crate) fn below(&mut self, bound: u64) -> u64 {
+        // the numbers from zone on would make the first ones more likely
+        let zone = u64::MAX - u64::MAX % bound;
+        loop {
+            let number = self.next_u64();
+            if number < zone {
+                return number % bound;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded_numbers_repeat() {
+        let numbers = |seed| {
+            let mut random = Random::new(Some(seed));
+            (0..8).map(|_| random.below(6)).collect::<Vec<u64>>()
+        };
+        assert_eq!(numbers(7), numbers(7));
+        assert_ne!(numbers(7), numbers(8));
+        assert!(numbers(7).iter().all(|&n| n < 6));
+    }
+}
@@ -0,0 +1,98 @@
+use super::Object;
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeSeq, Serializer};
+use std::convert::TryFrom;
+use std::fmt;
+
+/// Objects are serialized as the closest JSON values: integers as numbers,
+/// booleans as booleans, strings as strings and arrays and tuples as sequences
+impl Serialize for Object {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Object::Integer(value) => serializer.serialize_i32(*value),
+            Object::Boolean(value) => serializer.serialize_bool(*value),
+            Object::String(value) => serializer.serialize_str(value),
+            Object::Array(values) | Object::Tuple(values) => {
+                let mut seq = serializer.serialize_seq(Some(values.len()))?;
+                for value in values {
+                    seq.serialize_element(value)?;
+                }
+                seq.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Object {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ObjectVisitor)
+    }
+}
+
+struct ObjectVisitor;
+
+impl<'de> Visitor<'de> for ObjectVisitor {
+    type Value = Object;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a 32 bit integer, boolean, string or sequence")
+    }
+
+    fn visit_bool<E: de::Error>(self, value: bool) -> Result<Object, E> {
+        Ok(Object::Boolean(value))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Object, E> {
+        i32::try_from(value)
+            .map(Object::Integer)
+            .map_err(|_| E::invalid_value(de::Unexpected::Signed(value), &self))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Object, E> {
+        i32::try_from(value)
+            .map(Object::Integer)
+            .map_err(|_| E::invalid_value(de::Unexpected::Unsigned(value), &self))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Object, E> {
+        Ok(Object::String(value.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, value: String) -> Result<Object, E> {
+        Ok(Object::String(value))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Object, A::Error> {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(Object::Array(values))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let object = Object::Array(vec![
+            Object::Integer(-3),
+            Object::Boolean(true),
+            Object::String(String::from("sapo \"🐸\"")),
+            Object::Array(vec![]),
+        ]);
+        let json = serde_json::to_string(&object).unwrap();
+        assert_eq!(json, r#"[-3,true,"sapo \"🐸\"",[]]"#);
+        assert_eq!(serde_json::from_str::<Object>(&json).unwrap(), object);
+    }
+
+    #[test]
+    fn unsupported_values() {
+        assert!(serde_json::from_str::<Object>("4294967296").is_err());
+        assert!(serde_json::from_str::<Object>("1.5").is_err());
+        assert!(serde_json::from_str::<Object>("null").is_err());
+        assert!(serde_json::from_str::<Object>(r#"{"a":1}"#).is_err());
+    }
+}
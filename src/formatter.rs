@@ -0,0 +1,452 @@
+use super::ast::{Ast, ExprId, Expression, Program};
+use crate::parsing::Lexer;
+use crate::token::{Token, TokenType};
+
+/// Calls that don't fit in this many columns get one argument per line
+const MAX_WIDTH: usize = 80;
+const INDENT: usize = 4;
+
+/// Prints the program with canonical spacing, one expression per line terminated by ';'
+pub fn format_program(program: &Program) -> String {
+    let mut buf = String::new();
+    for &statement in &program.statements {
+        buf.push_str(&format_expression(&program.ast, statement, 0, 0));
+        buf.push_str(";\n");
+    }
+    buf
+}
+
+/// Formats the program parsed from source like format_program, keeping the comments
+/// of the source. Comments after a statement on the same line stay there, the others
+/// are put on their own line before the statement that follows them.
+pub fn format_program_with_comments(program: &Program, source: &str) -> String {
+    let mut comments = Lexer::with_comments(source)
+        .filter(|token| token.token_type == TokenType::Comment)
+        .peekable();
+    let mut buf = String::new();
+    // where the previous statement ended
+    let mut previous_end = None;
+    for &statement in &program.statements {
+        let span = program.ast.span(statement);
+        while let Some(comment) = comments.next_if(|c| c.span.start < span.end) {
+            let trailing = comment.span.start < span.start;
+            push_comment(
+                &mut buf,
+                &comment,
+                source,
+                previous_end.filter(|_| trailing),
+            );
+        }
+        buf.push_str(&format_expression(&program.ast, statement, 0, 0));
+        buf.push_str(";\n");
+        previous_end = Some(span.end);
+    }
+    for comment in comments {
+        push_comment(&mut buf, &comment, source, previous_end);
+    }
+    buf
+}
+
+/// Adds the comment at the end of the last line if it was on the same line as previous_end
+/// in the source, otherwise on a line of its own
+fn push_comment(buf: &mut String, comment: &Token, source: &str, previous_end: Option<usize>) {
+    let same_line = previous_end.is_some_and(|end| !source[end..comment.span.start].contains('\n'));
+    if same_line && buf.ends_with('\n') {
+        buf.pop();
+        buf.push(' ');
+    }
+    buf.push_str(&comment.lexeme);
+    buf.push('\n');
+}
+
+/// Formats the expression assuming it starts at the given column of a line indented by indent
+fn format_expression(ast: &Ast, id: ExprId, column: usize, indent: usize) -> String {
+    let flat = unparse(ast, id);
+    if column + flat.len() <= MAX_WIDTH {
+        return flat;
+    }
+    match &ast[id] {
+        Expression::Call {
+            token: _,
+            name,
+            arguments,
+        } if !arguments.is_empty() => {
+            let inner = indent + INDENT;
+            let arguments: Vec<String> = arguments
+                .iter()
+                .map(|a| {
+                    format!(
+                        "{}{}",
+                        " ".repeat(inner),
+                        format_expression(ast, *a, inner, inner)
+                    )
+                })
+                .collect();
+            format!(
+                "{}(\n{}\n{})",
+                name,
+                arguments.join(",\n"),
+                " ".repeat(indent)
+            )
+        }
+        Expression::Grouping { token: _, expr } => {
+            format!("({})", format_expression(ast, *expr, column + 1, indent))
+        }
+        Expression::Assignment {
+            token: _,
+            name,
+            value,
+        } => {
+            let prefix = format!("{} = ", name);
+            let value = format_expression(ast, *value, column + prefix.len(), indent);
+            prefix + &value
+        }
+        Expression::UnaryExpression { token, right } => {
+            let right = format_expression(ast, *right, column + token.lexeme.len(), indent);
+            format!("{}{}", token.lexeme, right)
+        }
+        Expression::BinaryExpression { token, left, right } => {
+            let left = format_expression(ast, *left, column, indent);
+            // the right operand continues the last line of the left one
+            let end = match left.rfind('\n') {
+                Some(i) => left.len() - i - 1,
+                None => column + left.len(),
+            };
+            let right = format_expression(ast, *right, end + token.lexeme.len() + 2, indent);
+            format!("{} {} {}", left, token.lexeme, right)
+        }
+        _ => flat,
+    }
+}
+
+/// Prints the expression as sapo source in a single line, with the parentheses of the
+/// groupings of the tree. Parsing the source gives back the same tree.
+pub fn unparse(ast: &Ast, id: ExprId) -> String {
+    match &ast[id] {
+        // as written, e.g. in hexadecimal
+        Expression::IntegerLiteral { token, value: _ } => token.lexeme.to_string(),
+        Expression::BooleanLiteral { token: _, value } => value.to_string(),
+        Expression::StringLiteral { token: _, value } => format!("\"{}\"", value),
+        Expression::Grouping { token: _, expr } => format!("({})", unparse(ast, *expr)),
+        Expression::Variable { token: _, name } => name.to_string(),
+        // kept as written, it doesn't parse
+        Expression::Error { token: _, source } => source.clone(),
+        Expression::Assignment {
+            token: _,
+            name,
+            value,
+        } => format!("{} = {}", name, unparse(ast, *value)),
+        Expression::Call {
+            token: _,
+            name,
+            arguments,
+        } => {
+            let arguments: Vec<String> = arguments.iter().map(|&a| unparse(ast, a)).collect();
+            format!("{}({})", name, arguments.join(", "))
+        }
+        Expression::UnaryExpression { token, right } => {
+            format!("{}{}", token.lexeme, unparse(ast, *right))
+        }
+        Expression::BinaryExpression { token, left, right } => format!(
+            "{} {} {}",
+            unparse(ast, *left),
+            token.lexeme,
+            unparse(ast, *right)
+        ),
+        Expression::Tuple { token, elements } => {
+            let elements: Vec<String> = elements.iter().map(|&e| unparse(ast, e)).collect();
+            match (&token.token_type, elements.len()) {
+                (TokenType::LeftParen, 1) => format!("({},)", elements[0]),
+                (TokenType::LeftParen, _) => format!("({})", elements.join(", ")),
+                // a, b without parentheses
+                _ => elements.join(", "),
+            }
+        }
+        Expression::MultipleAssignment {
+            token: _,
+            names,
+            value,
+        } => {
+            let names: Vec<&str> = names.iter().map(|n| n.as_str()).collect();
+            format!("{} = {}", names.join(", "), unparse(ast, *value))
+        }
+        Expression::Index {
+            token: _,
+            target,
+            index,
+        } => format!("{}[{}]", unparse(ast, *target), unparse(ast, *index)),
+        Expression::Slice {
+            token: _,
+            target,
+            start,
+            end,
+        } => {
+            let bound = |bound: &Option<ExprId>| bound.map_or(String::new(), |b| unparse(ast, b));
+            format!("{}[{}:{}]", unparse(ast, *target), bound(start), bound(end))
+        }
+        Expression::TypeTest {
+            token: _,
+            value,
+            type_name,
+        } => format!("{} is {}", unparse(ast, *value), type_name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, parse_program, to_source, KEYWORDS};
+    use proptest::prelude::*;
+
+    #[test]
+    fn format_spacing() {
+        assert_fmt(
+            "x=1+  2*(3-y)  ;  -  x;!true==false\n\n",
+            "x = 1 + 2 * (3 - y);\n-x;\n!true == false;\n",
+        );
+    }
+
+    #[test]
+    fn format_calls() {
+        assert_fmt("exit( args( ) ,1)", "exit(args(), 1);\n");
+    }
+
+    #[test]
+    fn format_long_call() {
+        let input = format!("x = f(\"{}\", g(1, 2))", "a".repeat(70));
+        let expected = format!("x = f(\n    \"{}\",\n    g(1, 2)\n);\n", "a".repeat(70));
+        assert_fmt(&input, &expected);
+    }
+
+    #[test]
+    fn format_is_idempotent() {
+        let input = format!("f({}) + 1; y = 2", "x, ".repeat(40) + "x");
+        let once = format_program(&parse_program(&input).unwrap());
+        let twice = format_program(&parse_program(&once).unwrap());
+        assert_eq!(once, twice);
+    }
+
+    proptest! {
+        #[test]
+        fn unparse_round_trip(expected in expression()) {
+            let source = expected.source();
+            let (ast, root) = parse(&source).unwrap();
+            prop_assert_eq!(Node::from_ast(&ast, root), expected);
+            prop_assert_eq!(unparse(&ast, root), source);
+        }
+
+        #[test]
+        fn format_round_trip(statements in prop::collection::vec(expression(), 1..4)) {
+            let source: Vec<String> = statements.iter().map(Node::source).collect();
+            let formatted = format_program(&parse_program(&source.join("; ")).unwrap());
+            let program = parse_program(&formatted).unwrap();
+            let nodes: Vec<Node> = program
+                .statements
+                .iter()
+                .map(|&s| Node::from_ast(&program.ast, s))
+                .collect();
+            prop_assert_eq!(nodes, statements);
+            prop_assert_eq!(format_program(&program), formatted);
+        }
+
+        #[test]
+        fn to_source_round_trip(expected in expression()) {
+            let (ast, root) = parse(&expected.source()).unwrap();
+            let source = to_source(&ast, root);
+            let (ast, root) = parse(&source).unwrap();
+            prop_assert_eq!(
+                Node::from_ast(&ast, root).without_groupings(),
+                expected.without_groupings()
+            );
+            prop_assert_eq!(to_source(&ast, root), source);
+        }
+    }
+
+    /// The tree an expression is expected to parse to, without tokens
+    #[derive(Debug, Clone, PartialEq)]
+    enum Node {
+        Integer(i32),
+        Boolean(bool),
+        String(String),
+        Variable(String),
+        Grouping(Box<Node>),
+        Unary(String, Box<Node>),
+        Binary(String, Box<Node>, Box<Node>),
+        Assignment(String, Box<Node>),
+        Call(String, Vec<Node>),
+    }
+
+    impl Node {
+        fn source(&self) -> String {
+            match self {
+                Node::Integer(value) => value.to_string(),
+                Node::Boolean(value) => value.to_string(),
+                Node::String(value) => format!("\"{}\"", value),
+                Node::Variable(name) => name.clone(),
+                Node::Grouping(expr) => format!("({})", expr.source()),
+                Node::Unary(op, right) => format!("{}{}", op, right.source()),
+                Node::Binary(op, left, right) => {
+                    format!("{} {} {}", left.source(), op, right.source())
+                }
+                Node::Assignment(name, value) => format!("{} = {}", name, value.source()),
+                Node::Call(name, arguments) => {
+                    let arguments: Vec<String> = arguments.iter().map(Node::source).collect();
+                    format!("{}({})", name, arguments.join(", "))
+                }
+            }
+        }
+
+        /// The same tree with the groupings replaced by the expressions inside them
+        fn without_groupings(self) -> Node {
+            let strip = |node: Box<Node>| Box::new(node.without_groupings());
+            match self {
+                Node::Grouping(expr) => expr.without_groupings(),
+                Node::Unary(op, right) => Node::Unary(op, strip(right)),
+                Node::Binary(op, left, right) => Node::Binary(op, strip(left), strip(right)),
+                Node::Assignment(name, value) => Node::Assignment(name, strip(value)),
+                Node::Call(name, arguments) => Node::Call(
+                    name,
+                    arguments.into_iter().map(Node::without_groupings).collect(),
+                ),
+                leaf => leaf,
+            }
+        }
+
+        /// How tightly the expression binds as an operand, see parse_expression
+        fn precedence(&self) -> u8 {
+            match self {
+                Node::Assignment(_, _) => 0,
+                Node::Binary(op, _, _) => binary_precedence(op),
+                Node::Unary(_, _) => 5,
+                _ => 6,
+            }
+        }
+
+        fn from_ast(ast: &Ast, id: ExprId) -> Node {
+            match &ast[id] {
+                Expression::IntegerLiteral { value, .. } => Node::Integer(*value),
+                Expression::BooleanLiteral { value, .. } => Node::Boolean(*value),
+                Expression::StringLiteral { value, .. } => Node::String(value.to_string()),
+                Expression::Variable { name, .. } => Node::Variable(name.to_string()),
+                Expression::Grouping { expr, .. } => {
+                    Node::Grouping(Box::new(Node::from_ast(ast, *expr)))
+                }
+                Expression::UnaryExpression { token, right } => Node::Unary(
+                    token.lexeme.to_string(),
+                    Box::new(Node::from_ast(ast, *right)),
+                ),
+                Expression::BinaryExpression { token, left, right } => Node::Binary(
+                    token.lexeme.to_string(),
+                    Box::new(Node::from_ast(ast, *left)),
+                    Box::new(Node::from_ast(ast, *right)),
+                ),
+                Expression::Assignment { name, value, .. } => {
+                    Node::Assignment(name.to_string(), Box::new(Node::from_ast(ast, *value)))
+                }
+                Expression::Call {
+                    name, arguments, ..
+                } => Node::Call(
+                    name.to_string(),
+                    arguments.iter().map(|&a| Node::from_ast(ast, a)).collect(),
+                ),
+                Expression::Index { .. }
+                | Expression::Slice { .. }
+                | Expression::Tuple { .. }
+                | Expression::MultipleAssignment { .. }
+                | Expression::TypeTest { .. } => {
+                    unreachable!("indexing, tuples and type tests aren't generated")
+                }
+                Expression::Error { .. } => unreachable!("the programs are parsed strictly"),
+            }
+        }
+    }
+
+    fn binary_precedence(op: &str) -> u8 {
+        match op {
+            "==" | "!=" => 1,
+            ">" | ">=" | "<" | "<=" => 2,
+            "+" | "-" => 3,
+            _ => 4,
+        }
+    }
+
+    /// The operand in a grouping if it binds looser than the operator it belongs to
+    fn operand(node: Node, precedence: u8) -> Box<Node> {
+        if node.precedence() < precedence {
+            Box::new(Node::Grouping(Box::new(node)))
+        } else {
+            Box::new(node)
+        }
+    }
+
+    fn name() -> impl Strategy<Value = String> {
+        "[a-z_][a-z0-9_]{0,4}".prop_filter("keywords aren't names", |name| {
+            !KEYWORDS.contains(&name.as_str())
+        })
+    }
+
+    /// Expressions with the groupings required for them to parse to the generated tree,
+    /// binary operators are left associative and assignments right associative
+    fn expression() -> impl Strategy<Value = Node> {
+        let leaf = prop_oneof![
+            (0..=i32::MAX).prop_map(Node::Integer),
+            any::<bool>().prop_map(Node::Boolean),
+            "[a-zA-Z0-9 ]{0,8}".prop_map(Node::String),
+            name().prop_map(Node::Variable),
+        ];
+        leaf.prop_recursive(6, 64, 4, |inner| {
+            let unary = prop::sample::select(vec!["-", "!"]);
+            let binary =
+                prop::sample::select(vec!["==", "!=", ">", ">=", "<", "<=", "+", "-", "*", "/"]);
+            prop_oneof![
+                inner.clone().prop_map(|e| Node::Grouping(Box::new(e))),
+                (unary, inner.clone())
+                    .prop_map(|(op, e)| Node::Unary(op.to_string(), operand(e, 5))),
+                (binary, inner.clone(), inner.clone()).prop_map(|(op, l, r)| {
+                    let precedence = binary_precedence(op);
+                    Node::Binary(
+                        op.to_string(),
+                        operand(l, precedence),
+                        operand(r, precedence + 1),
+                    )
+                }),
+                (name(), inner.clone()).prop_map(|(n, e)| Node::Assignment(n, Box::new(e))),
+                (name(), prop::collection::vec(inner, 0..4)).prop_map(|(n, a)| Node::Call(n, a)),
+            ]
+        })
+    }
+
+    #[test]
+    fn format_comments() {
+        let input = "// header\nx=1; // one\n\n// before y\ny=f(1, // inside\n2);z=3 // z\n// end";
+        let program = parse_program(input).unwrap();
+        let formatted = format_program_with_comments(&program, input);
+        assert_eq!(
+            formatted,
+            concat!(
+                "// header\n",
+                "x = 1; // one\n",
+                "// before y\n",
+                "// inside\n",
+                "y = f(1, 2);\n",
+                "z = 3; // z\n",
+                "// end\n"
+            )
+        );
+        let program = parse_program(&formatted).unwrap();
+        assert_eq!(
+            format_program_with_comments(&program, &formatted),
+            formatted
+        );
+    }
+
+    #[test]
+    fn format_integer_literals() {
+        assert_fmt("0xFF+1_000*0b1", "0xFF + 1_000 * 0b1;\n");
+    }
+
+    fn assert_fmt(input: &str, expected: &str) {
+        let program = parse_program(input).unwrap();
+        assert_eq!(format_program(&program), expected);
+    }
+}
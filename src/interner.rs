@@ -0,0 +1,99 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ptr;
+use std::sync::{Mutex, OnceLock};
+
+/// Names interned so far, they live until the program exits
+fn symbols() -> &'static Mutex<HashSet<&'static str>> {
+    static SYMBOLS: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+    SYMBOLS.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+/// An interned name, e.g. of a variable. There is a single copy of each name, shared by
+/// every program parsed, so symbols are compared and hashed by its address instead of
+/// character by character.
+#[derive(Clone, Copy)]
+pub struct Symbol(&'static str);
+
+impl Symbol {
+    /// The symbol of name, creating it the first time it is interned
+    pub fn intern(name: &str) -> Symbol {
+        let mut symbols = symbols().lock().unwrap();
+        if let Some(&symbol) = symbols.get(name) {
+            return Symbol(symbol);
+        }
+        let symbol: &'static str = Box::leak(name.into());
+        symbols.insert(symbol);
+        Symbol(symbol)
+    }
+
+    /// The symbol of name if it was ever interned, without interning it
+    pub fn lookup(name: &str) -> Option<Symbol> {
+        symbols()
+            .lock()
+            .unwrap()
+            .get(name)
+            .map(|&symbol| Symbol(symbol))
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        self.0
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Self {
+        Symbol::intern(name)
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        ptr::eq(self.0, other.0)
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.as_ptr().hash(state)
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interned_names_are_shared() {
+        let symbol = Symbol::intern("interned");
+        assert_eq!(symbol, Symbol::intern(&String::from("interned")));
+        assert!(ptr::eq(
+            symbol.as_str(),
+            Symbol::intern("interned").as_str()
+        ));
+        assert_ne!(symbol, Symbol::intern("other"));
+        assert_eq!(symbol.to_string(), "interned");
+    }
+
+    #[test]
+    fn lookup_does_not_intern() {
+        assert_eq!(Symbol::lookup("never_interned"), None);
+        let symbol = Symbol::intern("looked_up");
+        assert_eq!(Symbol::lookup("looked_up"), Some(symbol));
+    }
+}
@@ -0,0 +1,369 @@
+use crate::evaluation::{
+    evaluate_async, evaluate_program, Environment, EvalError, EvalOptions, Object,
+};
+use crate::parsing::{parse_program_with_options, Fixity, ParseError, ParseOptions};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+/// Runs programs in an environment that persists between them, so that the variables
+/// assigned by a program can be used by the ones evaluated after it.
+/// This is the API meant for applications embedding sapo. Interpreters are Send,
+/// so they can be moved to the thread that should run the programs.
+#[derive(Debug, Default)]
+pub struct Interpreter {
+    environment: Environment,
+}
+
+#[derive(Debug)]
+pub enum InterpreterError {
+    Parse(ParseError),
+    Eval(EvalError),
+    /// The file with the given path could not be read
+    Io(PathBuf, io::Error),
+}
+
+impl fmt::Display for InterpreterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InterpreterError::Parse(error) => write!(f, "{}", error),
+            InterpreterError::Eval(error) => write!(f, "{}", error),
+            InterpreterError::Io(path, error) => {
+                write!(f, "Could not read {}: {}", path.display(), error)
+            }
+        }
+    }
+}
+
+impl From<ParseError> for InterpreterError {
+    fn from(error: ParseError) -> Self {
+        InterpreterError::Parse(error)
+    }
+}
+
+impl From<EvalError> for InterpreterError {
+    fn from(error: EvalError) -> Self {
+        InterpreterError::Eval(error)
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Interpreter::default()
+    }
+
+    /// Programs are parsed and evaluated within the limits of options,
+    /// its max_depth applies to both
+    pub fn with_options(options: EvalOptions) -> Self {
+        Interpreter {
+            environment: Environment::with_options(options),
+        }
+    }
+
+    /// Evaluates a program, returns the value of its last expression or None if it is empty
+    pub fn eval_str(&mut self, source: &str) -> Result<Option<Object>, InterpreterError> {
+        let program = parse_program_with_options(source, &self.parse_options())?;
+        Ok(evaluate_program(&program, &mut self.environment)?)
+    }
+
+    /// Like eval_str, but yields to the executor while evaluating, see evaluate_async
+    pub async fn eval_str_async(
+        &mut self,
+        source: &str,
+    ) -> Result<Option<Object>, InterpreterError> {
+        let program = parse_program_with_options(source, &self.parse_options())?;
+        Ok(evaluate_async(&program, &mut self.environment).await?)
+    }
+
+    /// Evaluates the program in the file at path
+    pub fn eval_file<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> Result<Option<Object>, InterpreterError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)
+            .map_err(|error| InterpreterError::Io(path.to_path_buf(), error))?;
+        self.eval_str(&source)
+    }
+
+    /// Value of the variable name, None if it was never assigned
+    pub fn get_global(&self, name: &str) -> Option<&Object> {
+        self.environment.get(name)
+    }
+
+    /// Assigns value to the variable name, making it available to the programs evaluated afterwards
+    pub fn set_global(&mut self, name: &str, value: Object) {
+        self.environment.set(name, value);
+    }
+
+    /// Redirects the output of print(), e.g. to capture it
+    pub fn set_output(&mut self, output: Box<dyn Write + Send>) {
+        self.environment.set_output(output);
+    }
+
+    /// Replaces where input() reads lines from, e.g. to pass text to a program
+    pub fn set_input(&mut self, input: Box<dyn BufRead + Send>) {
+        self.environment.set_input(input);
+    }
+
+    /// Makes a Rust function callable from programs, see Environment::register_fn
+    pub fn register_fn<F, E>(&mut self, name: &str, arity: usize, function: F)
+    where
+        F: Fn(&[Object]) -> Result<Object, E> + Send + Sync + 'static,
+        E: fmt::Display,
+    {
+        self.environment.register_fn(name, arity, function);
+    }
+
+    /// Lets programs read and write files, see Environment::enable_io
+    pub fn enable_io(&mut self) {
+        self.environment.enable_io();
+    }
+
+    /// Lets programs read environment variables and run commands,
+    /// see Environment::enable_process
+    pub fn enable_process(&mut self) {
+        self.environment.enable_process();
+    }
+
+    /// Makes symbol an operator of the programs evaluated afterwards,
+    /// see Environment::register_operator
+    pub fn register_operator<F, E>(&mut self, symbol: &str, fixity: Fixity, function: F)
+    where
+        F: Fn(&[Object]) -> Result<Object, E> + Send + Sync + 'static,
+        E: fmt::Display,
+    {
+        self.environment.register_operator(symbol, fixity, function);
+    }
+
+    /// Programs are parsed with the registered operators, within the depth limit of evaluations
+    fn parse_options(&self) -> ParseOptions {
+        ParseOptions {
+            max_depth: self.environment.options().max_depth,
+            operators: self.environment.operators(),
+        }
+    }
+
+    pub fn environment(&self) -> &Environment {
+        &self.environment
+    }
+
+    /// Useful to set the arguments, hooks or cancellation token of the programs
+    pub fn environment_mut(&mut self) -> &mut Environment {
+        &mut self.environment
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::thread;
+
+    #[test]
+    fn globals_persist_between_programs() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_global("x", Object::Integer(2));
+        assert_eq!(
+            interpreter.eval_str("y = x * 3").unwrap(),
+            Some(Object::Integer(6))
+        );
+        assert_eq!(interpreter.eval_str("").unwrap(), None);
+        assert_eq!(interpreter.get_global("y"), Some(&Object::Integer(6)));
+        assert_eq!(interpreter.get_global("z"), None);
+    }
+
+    #[test]
+    fn errors() {
+        let mut interpreter = Interpreter::new();
+        assert!(matches!(
+            interpreter.eval_str("(1"),
+            Err(InterpreterError::Parse(ParseError::MissingBrace(_)))
+        ));
+        assert_eq!(
+            interpreter.eval_str("x").unwrap_err().to_string(),
+            "Error at line 1: Undefined variable 'x' [E0006]"
+        );
+        assert!(matches!(
+            interpreter.eval_file("does/not/exist.sapo"),
+            Err(InterpreterError::Io(_, _))
+        ));
+    }
+
+    #[test]
+    fn register_fn() {
+        let mut interpreter = Interpreter::new();
+        interpreter.register_fn("add", 2, |arguments| match arguments {
+            [Object::Integer(a), Object::Integer(b)] => Ok(Object::Integer(a + b)),
+            _ => Err("expected two Integers"),
+        });
+        assert_eq!(
+            interpreter.eval_str("add(1, add(2, 3))").unwrap(),
+            Some(Object::Integer(6))
+        );
+        assert_eq!(
+            interpreter
+                .eval_str("add(1, true)")
+                .unwrap_err()
+                .to_string(),
+            "Error at line 1: 'add' failed: expected two Integers [E0017]"
+        );
+    }
+
+    #[test]
+    fn enable_io() {
+        let path = std::env::temp_dir().join(format!("sapo-io-{}.txt", std::process::id()));
+        let path = path.to_str().unwrap().replace('\\', "/");
+        let program = format!("write_file(\"{}\", \"a\nb\n\")", path);
+        assert_eq!(
+            Interpreter::new()
+                .eval_str(&program)
+                .unwrap_err()
+                .to_string(),
+            "Error at line 1: Undefined function 'write_file' [E0010]"
+        );
+        let mut interpreter = Interpreter::new();
+        interpreter.enable_io();
+        assert_eq!(
+            interpreter.eval_str(&program).unwrap(),
+            Some(Object::Boolean(true))
+        );
+        let lines = format!("read_lines(\"{}\")", path);
+        assert_eq!(
+            interpreter.eval_str(&lines).unwrap(),
+            Some(Object::Array(vec![
+                Object::String("a".to_string()),
+                Object::String("b".to_string()),
+            ]))
+        );
+        let text = format!("read_file(\"{}\")", path);
+        assert_eq!(
+            interpreter.eval_str(&text).unwrap(),
+            Some(Object::String("a\nb\n".to_string()))
+        );
+        std::fs::remove_file(&path).unwrap();
+        assert!(interpreter.eval_str(&text).is_err());
+    }
+
+    #[test]
+    fn enable_process() {
+        assert!(Interpreter::new().eval_str("cwd()").is_err());
+        let mut interpreter = Interpreter::new();
+        interpreter.enable_process();
+        let cwd = std::env::current_dir().unwrap();
+        assert_eq!(
+            interpreter.eval_str("cwd()").unwrap(),
+            Some(Object::String(cwd.to_string_lossy().into_owned()))
+        );
+        assert_eq!(
+            interpreter
+                .eval_str("set_env(\"SAPO_TEST_VARIABLE\", \"1\"); env(\"SAPO_TEST_VARIABLE\")")
+                .unwrap(),
+            Some(Object::String("1".to_string()))
+        );
+        assert_eq!(
+            interpreter
+                .eval_str("env(\"SAPO_UNSET_VARIABLE\")")
+                .unwrap(),
+            Some(Object::String(String::new()))
+        );
+        assert_eq!(
+            interpreter.eval_str("run(\"echo hi\")").unwrap(),
+            Some(Object::String(
+                if cfg!(windows) { "hi\r\n" } else { "hi\n" }.to_string()
+            ))
+        );
+        assert!(interpreter.eval_str("run(\"exit 3\")").is_err());
+    }
+
+    #[test]
+    fn register_operators() {
+        let mut interpreter = Interpreter::new();
+        interpreter.register_operator("**", Fixity::Infix(5), |operands| match operands {
+            [Object::Integer(a), Object::Integer(b)] => Ok(Object::Integer(a.pow(*b as u32))),
+            _ => Err("expected two Integers"),
+        });
+        interpreter.register_operator("<>", Fixity::Infix(1), |operands| {
+            Ok::<_, String>(Object::Boolean(operands[0] != operands[1]))
+        });
+        interpreter.register_operator("~", Fixity::Prefix, |operands| match operands {
+            [Object::Integer(a)] => Ok(Object::Integer(!a)),
+            _ => Err("expected an Integer"),
+        });
+        assert_eq!(
+            interpreter.eval_str("2 * 3 ** 2 <> ~-19").unwrap(),
+            Some(Object::Boolean(false))
+        );
+        assert_eq!(
+            interpreter.eval_str("-2 ** 2").unwrap(),
+            Some(Object::Integer(4))
+        );
+        assert_eq!(
+            interpreter.eval_str("2 ** true").unwrap_err().to_string(),
+            "Error at line 1: '**' failed: expected two Integers [E0017]"
+        );
+        // only defined as a prefix operator
+        assert_eq!(
+            interpreter.eval_str("1 ~ 2").unwrap_err().to_string(),
+            "ParseError at line 1: Expected ';', but '~' was found. [E0009]"
+        );
+        assert_eq!(
+            Interpreter::new()
+                .eval_str("2 ** 2")
+                .unwrap_err()
+                .to_string(),
+            "ParseError at line 1: Expected expression, but '*' was found. [E0002]"
+        );
+    }
+
+    #[test]
+    fn operators_dont_split_pipes() {
+        let mut interpreter = Interpreter::new();
+        interpreter.register_operator("|", Fixity::Infix(1), |operands| match operands {
+            [Object::Integer(a), Object::Integer(b)] => Ok(Object::Integer(a | b)),
+            _ => Err("expected two Integers"),
+        });
+        assert_eq!(
+            interpreter.eval_str("\" a \" |> trim").unwrap(),
+            Some(Object::String(String::from("a")))
+        );
+        assert_eq!(
+            interpreter.eval_str("1 | 2 |> str").unwrap(),
+            Some(Object::String(String::from("3")))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "'>=' can't be the symbol of an operator")]
+    fn register_built_in_operator() {
+        Interpreter::new().register_operator(">=", Fixity::Prefix, |operands| {
+            Ok::<_, String>(operands[0].clone())
+        });
+    }
+
+    #[test]
+    fn evaluate_in_another_thread() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_global("x", Object::Integer(1));
+        let handle = thread::spawn(move || {
+            interpreter.eval_str("x = x + 1").unwrap();
+            interpreter
+        });
+        let interpreter = handle.join().unwrap();
+        assert_eq!(interpreter.get_global("x"), Some(&Object::Integer(2)));
+    }
+
+    #[test]
+    fn eval_file() {
+        let path = env::temp_dir().join("sapo_interpreter_eval_file.sapo");
+        fs::write(&path, "x = 20;\nx + 1").unwrap();
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter.eval_file(&path).unwrap(),
+            Some(Object::Integer(21))
+        );
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,841 @@
+use super::ast::{Ast, ExprId, Expression, Program};
+use crate::evaluation::BUILTINS;
+use crate::json;
+use crate::token::TokenType;
+
+/// Functions the emitted code calls to behave like sapo where JavaScript doesn't,
+/// their names start with '$' so they can't clash with the names of a program
+const RUNTIME: &str = r#"// sapo runtime
+// like in sapo, arithmetic and comparisons only accept Integers
+function $integers(operator, a, b) {
+    if (typeof a !== "number" || typeof b !== "number") {
+        throw new Error(`Invalid operands for '${operator}'`);
+    }
+}
+
+function $add(a, b) {
+    $integers("+", a, b);
+    return a + b;
+}
+
+function $sub(a, b) {
+    $integers("-", a, b);
+    return a - b;
+}
+
+function $cmp(operator, a, b) {
+    $integers(operator, a, b);
+    switch (operator) {
+        case "<":
+            return a < b;
+        case "<=":
+            return a <= b;
+        case ">":
+            return a > b;
+    }
+    return a >= b;
+}
+
+// == and != compare two Integers or two Booleans
+function $eq(operator, a, b) {
+    if (typeof a !== typeof b || !["number", "boolean"].includes(typeof a)) {
+        throw new Error(`Invalid operands for '${operator}'`);
+    }
+    return (a === b) === (operator === "==");
+}
+
+// equality of sapo values, arrays and tuples are equal if their values are
+function $equal(a, b) {
+    if (Array.isArray(a) && Array.isArray(b)) {
+        return (
+            Object.isFrozen(a) === Object.isFrozen(b) &&
+            a.length === b.length &&
+            a.every((value, i) => $equal(value, b[i]))
+        );
+    }
+    return a === b;
+}
+
+function $div(a, b) {
+    $integers("/", a, b);
+    if (b === 0) {
+        throw new Error("Division by zero");
+    }
+    // integer division truncates towards zero
+    return Math.trunc(a / b);
+}
+
+// a string times an integer repeats it
+function $mul(a, b) {
+    if (typeof a === "string" || typeof b === "string") {
+        const [string, count] = typeof a === "string" ? [a, b] : [b, a];
+        if (count < 0) {
+            throw new Error("A string can't be repeated a negative number of times");
+        }
+        return string.repeat(count);
+    }
+    return a * b;
+}
+
+function $display(value) {
+    if (typeof value === "string") {
+        return `"${value}"`;
+    }
+    if (Array.isArray(value) && Object.isFrozen(value)) {
+        const values = value.map($display).join(", ");
+        return value.length === 1 ? `(${values},)` : `(${values})`;
+    }
+    if (Array.isArray(value)) {
+        return `[${value.map($display).join(", ")}]`;
+    }
+    return String(value);
+}
+
+// tuples are frozen arrays
+function $type(value) {
+    switch (typeof value) {
+        case "number":
+            return "Integer";
+        case "boolean":
+            return "Boolean";
+        case "string":
+            return "String";
+    }
+    return Object.isFrozen(value) ? "Tuple" : "Array";
+}
+
+function $is(value, type) {
+    if (!["Array", "Boolean", "Integer", "String", "Tuple"].includes(type)) {
+        throw new Error(`Unknown type '${type}'`);
+    }
+    return $type(value) === type;
+}
+
+function $int(value) {
+    if (typeof value === "boolean") {
+        return value ? 1 : 0;
+    }
+    if (typeof value === "string") {
+        const integer = Number(value.trim());
+        if (!/^[+-]?[0-9]+$/.test(value.trim()) || integer !== (integer | 0)) {
+            throw new Error(`Can't convert ${$display(value)} to Integer`);
+        }
+        return integer;
+    }
+    if (typeof value !== "number") {
+        throw new Error("Invalid argument for 'int', expected Integer, Boolean or String");
+    }
+    return value;
+}
+
+function $str(value) {
+    return typeof value === "string" ? value : $display(value);
+}
+
+function $bool(value) {
+    if (typeof value === "number") {
+        return value !== 0;
+    }
+    if (typeof value === "string") {
+        switch (value.trim()) {
+            case "true":
+                return true;
+            case "false":
+                return false;
+        }
+        throw new Error(`Can't convert ${$display(value)} to Boolean`);
+    }
+    if (typeof value !== "boolean") {
+        throw new Error("Invalid argument for 'bool', expected Integer, Boolean or String");
+    }
+    return value;
+}
+
+// see format() in builtins.rs
+function $format(template, ...values) {
+    let next = 0;
+    const result = template.replace(/\{\{|\}\}|\{([^}]*)\}|[{}]/g, (match, spec) => {
+        if (match === "{{" || match === "}}") {
+            return match[0];
+        }
+        if (match === "{" || match === "}") {
+            const description = match === "{" ? "'{' without a closing '}'" : "'}' without an opening '{'";
+            throw new Error(`Invalid format string: ${description}`);
+        }
+        const parts = spec === undefined ? null : /^(?::([<>])?(0)?([0-9]*))?$/.exec(spec);
+        if (parts === null) {
+            throw new Error(`Invalid format string: invalid placeholder '${match}'`);
+        }
+        if (next === values.length) {
+            throw new Error("Invalid format string: more placeholders than values");
+        }
+        const [, align, zeros, digits] = parts;
+        const value = values[next++];
+        const width = Number(digits || 0);
+        if (width > 1048576) {
+            throw new Error(`Invalid format string: width ${digits} is larger than the maximum of 1048576`);
+        }
+        if (typeof value === "number" && zeros && !align) {
+            const digits = String(Math.abs(value)).padStart(width - (value < 0 ? 1 : 0), "0");
+            return value < 0 ? `-${digits}` : digits;
+        }
+        const text = $str(value);
+        const right = align ? align === ">" : typeof value === "number";
+        return right ? text.padStart(width) : text.padEnd(width);
+    });
+    if (next < values.length) {
+        throw new Error("Invalid format string: more values than placeholders");
+    }
+    return result;
+}
+
+// objects become arrays of [key, value] tuples
+function $json_parse(text) {
+    const convert = (value) => {
+        if (value === null) {
+            throw new Error("Invalid JSON: null has no value in sapo");
+        }
+        if (typeof value === "number" && !Number.isInteger(value)) {
+            throw new Error("Invalid JSON: only integers are supported");
+        }
+        if (typeof value === "number" && value !== (value | 0)) {
+            throw new Error(`Invalid JSON: ${value} is out of the range of Integer`);
+        }
+        if (Array.isArray(value)) {
+            return value.map(convert);
+        }
+        if (typeof value === "object") {
+            return Object.entries(value).map(([k, v]) => Object.freeze([k, convert(v)]));
+        }
+        return value;
+    };
+    let value;
+    try {
+        value = JSON.parse(text);
+    } catch (error) {
+        throw new Error(`Invalid JSON: ${error.message}`);
+    }
+    return convert(value);
+}
+
+function $json_stringify(value, pretty = false) {
+    if (typeof pretty !== "boolean") {
+        throw new Error("Invalid argument for 'json_stringify', expected Boolean");
+    }
+    return JSON.stringify(value, null, pretty ? 2 : undefined);
+}
+
+// compiled once like in sapo, the syntax of JavaScript's regular expressions is close
+// to the one of the regex crate
+const $patterns = new Map();
+
+function $pattern(pattern) {
+    if (!$patterns.has(pattern)) {
+        try {
+            $patterns.set(pattern, new RegExp(pattern, "gu"));
+        } catch (error) {
+            throw new Error(`Invalid regular expression: ${error.message}`);
+        }
+    }
+    const regex = $patterns.get(pattern);
+    regex.lastIndex = 0;
+    return regex;
+}
+
+function $regex_match(text, pattern) {
+    return $pattern(pattern).test(text);
+}
+
+function $regex_find_all(text, pattern) {
+    return [...text.matchAll($pattern(pattern))].map((match) => match[0]);
+}
+
+function $regex_replace(text, pattern, replacement) {
+    return text.replace($pattern(pattern), replacement);
+}
+
+function $join(array, separator) {
+    return array.map($str).join(separator);
+}
+
+function $now() {
+    return Math.floor(Date.now() / 1000);
+}
+
+function $clock() {
+    return Math.floor(performance.now());
+}
+
+// blocks like in sapo, which browsers only allow in workers
+function $sleep(milliseconds) {
+    if (milliseconds < 0) {
+        throw new Error("Invalid argument for 'sleep', expected non-negative Integer");
+    }
+    Atomics.wait(new Int32Array(new SharedArrayBuffer(4)), 0, 0, milliseconds);
+    return milliseconds;
+}
+
+// there is no seed, Math.random() can't be seeded
+function $random() {
+    return Math.floor(Math.random() * 2147483648);
+}
+
+function $rand_int(low, high) {
+    if (low > high) {
+        throw new Error("Invalid argument for 'rand_int', expected lo <= hi");
+    }
+    return low + Math.floor(Math.random() * (high - low + 1));
+}
+
+function $shuffle(array) {
+    const values = [...array];
+    for (let i = values.length - 1; i > 0; i--) {
+        const j = Math.floor(Math.random() * (i + 1));
+        [values[i], values[j]] = [values[j], values[i]];
+    }
+    return values;
+}
+
+function $print(value) {
+    console.log(typeof value === "string" ? value : $display(value));
+    return value;
+}
+
+function $assert(condition, message) {
+    if (!condition) {
+        throw new Error(`Assertion failed: ${message}`);
+    }
+    return true;
+}
+
+function $assert_eq(left, right) {
+    if ($display(left) !== $display(right)) {
+        throw new Error(`Assertion failed: ${$display(left)} != ${$display(right)}`);
+    }
+    return true;
+}
+
+// reads stdin a byte at a time so that the rest is left for the next call,
+// browsers ask for the line with a prompt
+function $input() {
+    if (typeof process === "undefined") {
+        return prompt() ?? "";
+    }
+    const fs = require("fs");
+    const buffer = Buffer.alloc(1);
+    const bytes = [];
+    while (fs.readSync(0, buffer, 0, 1, null) === 1 && buffer[0] !== 10) {
+        bytes.push(buffer[0]);
+    }
+    return Buffer.from(bytes).toString("utf8").replace(/\r$/, "");
+}
+
+function $args() {
+    return typeof process === "undefined" ? [] : process.argv.slice(2);
+}
+
+function $exit(code) {
+    if (typeof process === "undefined") {
+        throw new Error(`exit(${code})`);
+    }
+    process.exit(code);
+}
+
+function $contains(value, element) {
+    if (Array.isArray(value)) {
+        return value.some((v) => $equal(v, element));
+    }
+    return value.includes(element);
+}
+
+// the element is evaluated first, like in sapo
+function $in(element, value) {
+    if (typeof value === "string" && typeof element !== "string") {
+        throw new Error("Invalid operands for 'in'");
+    }
+    return $contains(value, element);
+}
+
+// like sapo, $functions has the built-ins by name and is emitted after the runtime
+function $keys(values, name) {
+    if (typeof name !== "string") {
+        throw new Error("Invalid argument, expected String naming a function");
+    }
+    if (!Object.hasOwn($functions, name)) {
+        throw new Error(`Undefined function '${name}'`);
+    }
+    return values.map((value) => [$functions[name](value), value]);
+}
+
+function $sort_by(array, name) {
+    const keyed = $keys(array, name);
+    if (keyed.some(([key]) => $type(key) !== $type(keyed[0][0]) || typeof key === "object")) {
+        throw new Error("Invalid argument, expected function returning Integers, Booleans or Strings");
+    }
+    keyed.sort(([a], [b]) => (a < b ? -1 : a > b ? 1 : 0));
+    return keyed.map(([, value]) => value);
+}
+
+function $group_by(array, name) {
+    const groups = [];
+    for (const [key, value] of $keys(array, name)) {
+        const group = groups.find(([k]) => $equal(k, key));
+        if (group) {
+            group[1].push(value);
+        } else {
+            groups.push(Object.freeze([key, [value]]));
+        }
+    }
+    return groups;
+}
+
+function $zip(left, right) {
+    const length = Math.min(left.length, right.length);
+    return left.slice(0, length).map((value, i) => Object.freeze([value, right[i]]));
+}
+
+function $enumerate(array) {
+    return array.map((value, i) => Object.freeze([i, value]));
+}
+
+function $range(start, end) {
+    if (end === undefined) {
+        [start, end] = [0, start];
+    }
+    return Array.from({ length: Math.max(end - start, 0) }, (_, i) => start + i);
+}
+
+function $index(array, index) {
+    const position = index < 0 ? array.length + index : index;
+    if (position < 0 || position >= array.length) {
+        throw new Error(`Index ${index} is out of bounds for length ${array.length}`);
+    }
+    return position;
+}
+
+// the values a multiple assignment assigns to its count names
+function $unpack(value, count) {
+    const values = Array.isArray(value) ? value : [value];
+    if (values.length !== count) {
+        throw new Error(`Expected ${count} values to assign, but ${values.length} given`);
+    }
+    return values;
+}
+
+// strings are indexed by char rather than by UTF-16 code unit
+function $at(value, index) {
+    const items = typeof value === "string" ? [...value] : value;
+    return items[$index(items, index)];
+}
+
+function $slice(value, start, end) {
+    const items = typeof value === "string" ? [...value] : value;
+    // a bound can also be the length, null is the start or the end
+    const bound = (bound, missing) => {
+        if (bound === null || bound === items.length) {
+            return bound === null ? missing : bound;
+        }
+        return $index(items, bound);
+    };
+    const from = bound(start, 0);
+    const sliced = items.slice(from, Math.max(from, bound(end, items.length)));
+    if (typeof value === "string") {
+        return sliced.join("");
+    }
+    return Object.isFrozen(value) ? Object.freeze(sliced) : sliced;
+}
+
+// arrays are values in sapo, so these return a changed copy
+function $insert(array, index, value) {
+    const copy = [...array];
+    copy.splice(index === array.length ? index : $index(array, index), 0, value);
+    return copy;
+}
+
+function $pop(array) {
+    return array.slice(0, $index(array, -1));
+}
+
+function $push(array, value) {
+    return [...array, value];
+}
+
+function $remove(array, index) {
+    const copy = [...array];
+    copy.splice($index(array, index), 1);
+    return copy;
+}
+
+function $reverse(array) {
+    return [...array].reverse();
+}
+
+function $sort(array) {
+    return [...array].sort((a, b) => (a < b ? -1 : a > b ? 1 : 0));
+}
+
+function $lower(string) {
+    return string.toLowerCase();
+}
+
+function $replace(string, from, to) {
+    return string.replaceAll(from, to);
+}
+
+function $split(string, separator) {
+    // an empty separator splits into chars rather than UTF-16 code units
+    return separator === "" ? [...string] : string.split(separator);
+}
+
+function $trim(string) {
+    return string.trim();
+}
+
+function $upper(string) {
+    return string.toUpperCase();
+}
+
+// operators registered by an application embedding sapo are only implemented there
+function $operator(symbol) {
+    throw new Error(`Invalid operator '${symbol}'`);
+}
+
+// statements with syntax errors fail when they are reached, like in sapo
+function $syntax_error(source) {
+    throw new Error(`Syntax error in '${source}'`);
+}
+
+// prints the value of the program like `sapo run`
+function $show(value) {
+    console.log($display(value));
+}
+"#;
+
+/// Words that can't be used as variable names in strict mode JavaScript
+const RESERVED: [&str; 46] = [
+    "arguments",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "eval",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+];
+
+/// Lowers the program to a JavaScript script that runs it and prints its value like
+/// `sapo run`, e.g. with node. The program is wrapped in a block so that its variables
+/// don't shadow the globals used by the runtime. Integers are JavaScript numbers,
+/// so unlike in sapo they don't overflow at 32 bits.
+pub fn emit_js(program: &Program) -> String {
+    let mut buf = format!("\"use strict\";\n\n{}", RUNTIME);
+    if program.is_empty() {
+        return buf;
+    }
+    let functions: Vec<String> = BUILTINS.iter().map(|b| format!("{0}: ${0}", b)).collect();
+    buf.push_str(&format!(
+        "\nconst $functions = {{ {} }};\n",
+        functions.join(", ")
+    ));
+    buf.push_str("\n{\n");
+    let mut names = Vec::new();
+    for &statement in &program.statements {
+        collect_assigned_names(&program.ast, statement, &mut names);
+    }
+    if !names.is_empty() {
+        let names: Vec<String> = names.iter().map(|n| identifier(n)).collect();
+        buf.push_str(&format!("    let {};\n", names.join(", ")));
+    }
+    for (i, &statement) in program.statements.iter().enumerate() {
+        let code = emit_expression(&program.ast, statement);
+        if i + 1 == program.len() {
+            buf.push_str(&format!("    $show({});\n", code));
+        } else {
+            buf.push_str(&format!("    {};\n", code));
+        }
+    }
+    buf.push_str("}\n");
+    buf
+}
+
+fn emit_expression(ast: &Ast, id: ExprId) -> String {
+    match &ast[id] {
+        Expression::IntegerLiteral { token: _, value } => value.to_string(),
+        Expression::BooleanLiteral { token: _, value } => value.to_string(),
+        Expression::StringLiteral { token: _, value } => json::quote(value),
+        Expression::Grouping { token: _, expr } => format!("({})", emit_expression(ast, *expr)),
+        Expression::Variable { token: _, name } => identifier(name.as_str()),
+        Expression::Error { token: _, source } => format!("$syntax_error({})", json::quote(source)),
+        // tuples are frozen arrays
+        Expression::Tuple { token: _, elements } => {
+            let elements: Vec<String> = elements.iter().map(|&e| emit_expression(ast, e)).collect();
+            format!("Object.freeze([{}])", elements.join(", "))
+        }
+        Expression::MultipleAssignment {
+            token: _,
+            names,
+            value,
+        } => {
+            let names: Vec<String> = names.iter().map(|n| identifier(n.as_str())).collect();
+            format!(
+                "[{}] = $unpack({}, {})",
+                names.join(", "),
+                emit_expression(ast, *value),
+                names.len()
+            )
+        }
+        Expression::TypeTest {
+            token: _,
+            value,
+            type_name,
+        } => format!(
+            "$is({}, {})",
+            emit_expression(ast, *value),
+            json::quote(type_name.as_str())
+        ),
+        Expression::Index {
+            token: _,
+            target,
+            index,
+        } => format!(
+            "$at({}, {})",
+            emit_expression(ast, *target),
+            emit_expression(ast, *index)
+        ),
+        Expression::Slice {
+            token: _,
+            target,
+            start,
+            end,
+        } => {
+            let bound = |bound: &Option<ExprId>| match bound {
+                Some(bound) => emit_expression(ast, *bound),
+                None => String::from("null"),
+            };
+            let target = emit_expression(ast, *target);
+            format!("$slice({}, {}, {})", target, bound(start), bound(end))
+        }
+        Expression::Assignment {
+            token: _,
+            name,
+            value,
+        } => format!(
+            "{} = {}",
+            identifier(name.as_str()),
+            emit_expression(ast, *value)
+        ),
+        Expression::Call {
+            token: _,
+            name,
+            arguments,
+        } => {
+            let arguments: Vec<String> =
+                arguments.iter().map(|&a| emit_expression(ast, a)).collect();
+            let function = match name.as_str() {
+                name if BUILTINS.contains(&name) => format!("${}", name),
+                name => identifier(name),
+            };
+            format!("{}({})", function, arguments.join(", "))
+        }
+        Expression::UnaryExpression { token, right } if token.token_type == TokenType::Operator => {
+            let operand = emit_expression(ast, *right);
+            format!("$operator({}, {})", json::quote(&token.lexeme), operand)
+        }
+        Expression::UnaryExpression { token, right } => {
+            let operand = emit_expression(ast, *right);
+            match ast[*right] {
+                // --x would be a decrement
+                Expression::UnaryExpression { .. } => format!("{}({})", token.lexeme, operand),
+                _ => format!("{}{}", token.lexeme, operand),
+            }
+        }
+        Expression::BinaryExpression { token, left, right }
+            if token.token_type == TokenType::Star
+                && !(is_integer(ast, *left) && is_integer(ast, *right)) =>
+        {
+            let left = emit_expression(ast, *left);
+            let right = emit_expression(ast, *right);
+            format!("$mul({}, {})", left, right)
+        }
+        Expression::BinaryExpression { token, left, right } => {
+            let left = emit_expression(ast, *left);
+            let right = emit_expression(ast, *right);
+            match token.token_type {
+                TokenType::Slash => format!("$div({}, {})", left, right),
+                TokenType::Plus => format!("$add({}, {})", left, right),
+                TokenType::Minus => format!("$sub({}, {})", left, right),
+                TokenType::Equals | TokenType::BangEquals => {
+                    format!("$eq({}, {}, {})", json::quote(&token.lexeme), left, right)
+                }
+                TokenType::Smaller
+                | TokenType::SmallerEquals
+                | TokenType::Greater
+                | TokenType::GreaterEquals => {
+                    format!("$cmp({}, {}, {})", json::quote(&token.lexeme), left, right)
+                }
+                TokenType::In => format!("$in({}, {})", left, right),
+                TokenType::Operator => format!(
+                    "$operator({}, {}, {})",
+                    json::quote(&token.lexeme),
+                    left,
+                    right
+                ),
+                _ => format!("{} {} {}", left, token.lexeme, right),
+            }
+        }
+    }
+}
+
+/// Whether the expression can only be an integer, so that '*' doesn't need to repeat strings
+fn is_integer(ast: &Ast, id: ExprId) -> bool {
+    match &ast[id] {
+        Expression::IntegerLiteral { .. } => true,
+        Expression::Grouping { expr, .. } => is_integer(ast, *expr),
+        Expression::UnaryExpression { token, .. } => token.token_type == TokenType::Minus,
+        Expression::BinaryExpression { token, left, right } => match token.token_type {
+            TokenType::Minus | TokenType::Plus | TokenType::Slash => true,
+            TokenType::Star => is_integer(ast, *left) && is_integer(ast, *right),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Names of the variables assigned by the expression, in evaluation order
+fn collect_assigned_names(ast: &Ast, id: ExprId, names: &mut Vec<String>) {
+    for child in ast[id].children() {
+        collect_assigned_names(ast, child, names);
+    }
+    let assigned = match &ast[id] {
+        Expression::Assignment { name, .. } => std::slice::from_ref(name),
+        Expression::MultipleAssignment { names, .. } => names.as_slice(),
+        _ => &[],
+    };
+    for name in assigned {
+        if !names.iter().any(|n| n == name.as_str()) {
+            names.push(name.to_string());
+        }
+    }
+}
+
+/// A JavaScript identifier for the sapo name. Reserved words are prefixed with '$' and
+/// characters JavaScript doesn't allow in identifiers are replaced by their code point.
+fn identifier(name: &str) -> String {
+    let valid = name
+        .chars()
+        .all(|c| c.is_alphabetic() || c.is_ascii_digit() || c == '_');
+    let valid_start = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_');
+    if valid && valid_start && !RESERVED.contains(&name) {
+        return name.to_string();
+    }
+    let mut identifier = String::from("$");
+    for c in name.chars() {
+        if c.is_alphabetic() || c.is_ascii_digit() || c == '_' {
+            identifier.push(c);
+        } else {
+            identifier.push_str(&format!("${:x}$", c as u32));
+        }
+    }
+    identifier
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_program;
+
+    #[test]
+    fn emit_program() {
+        let program = parse_program("x = 7 / -2;\nprint(x == -3);\nargs()").unwrap();
+        let code = emit_js(&program);
+        assert!(code.starts_with("\"use strict\";\n\n// sapo runtime\n"));
+        assert!(code.ends_with(concat!(
+            "{\n",
+            "    let x;\n",
+            "    x = $div(7, -2);\n",
+            "    $print($eq(\"==\", x, -3));\n",
+            "    $show($args());\n",
+            "}\n"
+        )));
+    }
+
+    #[test]
+    fn emit_expressions() {
+        assert_eq!(emit("--(1 + 2) * 3"), "-(-($add(1, 2))) * 3");
+        assert_eq!(emit("!true != false"), "$eq(\"!=\", !true, false)");
+        assert_eq!(emit("x - 1 <= y"), "$cmp(\"<=\", $sub(x, 1), y)");
+        assert_eq!(emit("\"ab\" * (x * 2)"), "$mul(\"ab\", ($mul(x, 2)))");
+        assert_eq!(emit("x = y = \"a\\b\""), "x = y = \"a\\\\b\"");
+        assert_eq!(emit("f(1, g())"), "f(1, g())");
+        assert_eq!(emit("x[-1][1:]"), "$slice($at(x, -1), 1, null)");
+        assert_eq!(
+            emit("\"a\" in s == true"),
+            "$eq(\"==\", $in(\"a\", s), true)"
+        );
+        assert_eq!(emit("type(x) is String"), "$is($type(x), \"String\")");
+        assert_eq!(
+            emit("a, b = b, (1,)"),
+            "[a, b] = $unpack(Object.freeze([b, Object.freeze([1])]), 2)"
+        );
+    }
+
+    #[test]
+    fn rename_invalid_identifiers() {
+        assert_eq!(identifier("sapo_1"), "sapo_1");
+        assert_eq!(identifier("ñandú"), "ñandú");
+        assert_eq!(identifier("class"), "$class");
+        assert_eq!(identifier("x²"), "$x$b2$");
+    }
+
+    #[test]
+    fn emit_empty_program() {
+        let code = emit_js(&parse_program("").unwrap());
+        assert_eq!(code, format!("\"use strict\";\n\n{}", RUNTIME));
+    }
+
+    fn emit(input: &str) -> String {
+        let (ast, root) = crate::parse(input).unwrap();
+        emit_expression(&ast, root)
+    }
+}
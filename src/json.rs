@@ -0,0 +1,280 @@
+/// Quotes and escapes a string so it can be embedded in a JSON document
+pub(crate) fn quote(s: &str) -> String {
+    let mut buf = String::with_capacity(s.len() + 2);
+    buf.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => buf.push_str("\\\""),
+            '\\' => buf.push_str("\\\\"),
+            '\n' => buf.push_str("\\n"),
+            '\r' => buf.push_str("\\r"),
+            '\t' => buf.push_str("\\t"),
+            c if (c as u32) < 0x20 => buf.push_str(&format!("\\u{:04x}", c as u32)),
+            c => buf.push(c),
+        }
+    }
+    buf.push('"');
+    buf
+}
+
+/// A parsed JSON document, numbers can only be integers
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum Value {
+    Null,
+    Bool(bool),
+    Number(i64),
+    String(String),
+    Array(Vec<Value>),
+    /// The members in the order they were written
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// The member key of an object, None if there is no such member or this isn't an object
+    pub(crate) fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+/// Documents nested deeper than this are rejected instead of overflowing the stack
+const MAX_DEPTH: usize = 1024;
+
+/// Parses a JSON document, errors are a description of the problem
+pub(crate) fn parse(input: &str) -> Result<Value, String> {
+    let mut reader = Reader {
+        input: input.as_bytes(),
+        position: 0,
+    };
+    let value = reader.value(0)?;
+    reader.skip_whitespace();
+    if reader.position < input.len() {
+        return Err(reader.error("expected end of input"));
+    }
+    Ok(value)
+}
+
+struct Reader<'a> {
+    input: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn value(&mut self, depth: usize) -> Result<Value, String> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("document is nested too deeply"));
+        }
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'n') => self.keyword("null", Value::Null),
+            Some(b't') => self.keyword("true", Value::Bool(true)),
+            Some(b'f') => self.keyword("false", Value::Bool(false)),
+            Some(b'"') => self.string().map(Value::String),
+            Some(b'[') => {
+                self.position += 1;
+                let mut values = Vec::new();
+                if !self.consume(b']') {
+                    loop {
+                        values.push(self.value(depth + 1)?);
+                        if self.consume(b']') {
+                            break;
+                        }
+                        self.expect(b',')?;
+                    }
+                }
+                Ok(Value::Array(values))
+            }
+            Some(b'{') => {
+                self.position += 1;
+                let mut members = Vec::new();
+                if !self.consume(b'}') {
+                    loop {
+                        self.skip_whitespace();
+                        let key = self.string()?;
+                        self.expect(b':')?;
+                        members.push((key, self.value(depth + 1)?));
+                        if self.consume(b'}') {
+                            break;
+                        }
+                        self.expect(b',')?;
+                    }
+                }
+                Ok(Value::Object(members))
+            }
+            Some(b'-') | Some(b'0'..=b'9') => self.number(),
+            _ => Err(self.error("expected a value")),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Value) -> Result<Value, String> {
+        if !self.input[self.position..].starts_with(keyword.as_bytes()) {
+            return Err(self.error("expected a value"));
+        }
+        self.position += keyword.len();
+        Ok(value)
+    }
+
+    fn number(&mut self) -> Result<Value, String> {
+        let start = self.position;
+        self.consume(b'-');
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.position += 1;
+        }
+        if let Some(b'.') | Some(b'e') | Some(b'E') = self.peek() {
+            return Err(self.error("only integers are supported"));
+        }
+        let text = std::str::from_utf8(&self.input[start..self.position]).unwrap_or_default();
+        text.parse()
+            .map(Value::Number)
+            .map_err(|_| self.error("invalid number"))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        if !self.consume(b'"') {
+            return Err(self.error("expected a string"));
+        }
+        let mut bytes = Vec::new();
+        loop {
+            let byte = match self.peek() {
+                Some(byte) => byte,
+                None => return Err(self.error("unterminated string")),
+            };
+            self.position += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escaped = self
+                        .peek()
+                        .ok_or_else(|| self.error("unterminated string"))?;
+                    self.position += 1;
+                    let c = match escaped {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode_escape()?,
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    let mut buf = [0; 4];
+                    bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                }
+                byte => bytes.push(byte),
+            }
+        }
+        String::from_utf8(bytes).map_err(|_| self.error("invalid UTF-8"))
+    }
+
+    /// The character of a \uXXXX escape, including surrogate pairs
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.hex4()?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if !self.input[self.position..].starts_with(b"\\u") {
+                return Err(self.error("invalid surrogate pair"));
+            }
+            self.position += 2;
+            let low = self.hex4()?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(self.error("invalid surrogate pair"));
+            }
+            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| self.error("invalid escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let digits = self
+            .input
+            .get(self.position..self.position + 4)
+            .and_then(|d| std::str::from_utf8(d).ok())
+            .and_then(|d| u32::from_str_radix(d, 16).ok())
+            .ok_or_else(|| self.error("invalid escape"))?;
+        self.position += 4;
+        Ok(digits)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ') | Some(b'\n') | Some(b'\r') | Some(b'\t') = self.peek() {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.position).copied()
+    }
+
+    /// Skips whitespace and the byte if it is next, returns whether it was
+    fn consume(&mut self, byte: u8) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(byte) {
+            self.position += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        if self.consume(byte) {
+            return Ok(());
+        }
+        Err(self.error(&format!("expected '{}'", byte as char)))
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("{} at byte {}", message, self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quote_strings() {
+        assert_eq!(quote("sapo"), r#""sapo""#);
+        assert_eq!(quote("a \"b\"\n\\"), r#""a \"b\"\n\\""#);
+        assert_eq!(quote("\u{1}"), r#""\u0001""#);
+    }
+
+    #[test]
+    fn parse_documents() {
+        let value = parse(r#" {"a": [1, -20, true, null], "b": "x\"\u00f1\ud83d\udc38"} "#);
+        assert_eq!(
+            value,
+            Ok(Value::Object(vec![
+                (
+                    String::from("a"),
+                    Value::Array(vec![
+                        Value::Number(1),
+                        Value::Number(-20),
+                        Value::Bool(true),
+                        Value::Null
+                    ])
+                ),
+                (String::from("b"), Value::String(String::from("x\"ñ🐸")))
+            ]))
+        );
+        assert_eq!(
+            parse(&quote("a\u{1}\n")),
+            Ok(Value::String(String::from("a\u{1}\n")))
+        );
+    }
+
+    #[test]
+    fn parse_invalid_documents() {
+        for input in &["", "[1,", "{\"a\" 1}", "1.5", "[1] 2", "\"\\x\"", "tru"] {
+            assert!(parse(input).is_err(), "{}", input);
+        }
+        let error = parse("\"\\ud83d\\u0041\"").unwrap_err();
+        assert!(error.contains("invalid surrogate pair"), "{}", error);
+        let deep = "[".repeat(MAX_DEPTH + 2) + &"]".repeat(MAX_DEPTH + 2);
+        assert!(parse(&deep).is_err());
+    }
+}
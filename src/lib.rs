@@ -1,10 +1,29 @@
 // Loads the contents of src/parsing/mod.rs as if it were defined here
 pub mod token;
+pub mod interner;
 pub mod ast;
+pub mod cst;
 pub mod ast_printer;
+pub mod formatter;
+pub mod javascript;
 pub mod parsing;
 pub mod evaluation;
+pub mod diagnostics;
+pub mod interpreter;
+pub mod playground;
+#[cfg(feature = "capi")]
+pub mod capi;
+mod json;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "wasm")]
+mod wasm;
 
 pub use parsing::*;
 pub use evaluation::*;
 pub use ast_printer::*;
+pub use formatter::*;
+pub use javascript::*;
+pub use diagnostics::*;
+pub use interpreter::*;
+pub use playground::*;
@@ -1,27 +1,710 @@
+use sapo::{Diagnostic, Environment, EvalError, EvalOptions, Severity};
+use std::env;
+use std::fmt;
+use std::fs;
 use std::io;
-use std::io::Write;
+use std::io::{IsTerminal, Read};
+use std::process;
+use std::time::Duration;
+
+mod debug;
+mod repl;
+mod serve;
+mod testing;
+
+#[derive(Debug, PartialEq, Default)]
+pub enum ErrorFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+/// What programs can access besides their arguments and output
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Capabilities {
+    /// Files, with --allow-io
+    io: bool,
+    /// Environment variables and commands, with --allow-process
+    process: bool,
+}
+
+impl Capabilities {
+    pub fn enable(self, environment: &mut Environment) {
+        if self.io {
+            environment.enable_io();
+        }
+        if self.process {
+            environment.enable_process();
+        }
+    }
+}
+
+/// Options accepted before the program, the ones after it are part of its ARGS
+#[derive(Debug, PartialEq, Default)]
+struct Options {
+    error_format: ErrorFormat,
+    no_color: bool,
+    show_ast: bool,
+    capabilities: Capabilities,
+}
+
+/// Where the program to run comes from
+#[derive(Debug, PartialEq)]
+enum Source {
+    File(String),
+    Stdin,
+    Eval(String),
+}
+
+/// Output of the tokenize command
+enum TokenFormat {
+    /// One token per line in aligned columns
+    Table,
+    Json,
+}
+
+/// Output of the ast command
+enum AstFormat {
+    /// One S-expression per line
+    Sexpr,
+    Json,
+    /// Indented tree of nodes
+    Tree,
+    /// Indented tree with the line of each node
+    TreeLines,
+}
+
+/// Language the emit command translates programs to
+enum EmitTarget {
+    JavaScript,
+}
+
+/// Report of the coverage command
+enum CoverageFormat {
+    Lcov,
+    /// The source with the hit count of every line
+    Annotate,
+}
+
+enum Command {
+    /// The program and the arguments passed to it
+    Run(Source, Vec<String>),
+    Repl,
+    /// The program and the arguments passed to it
+    Debug(Source, Vec<String>),
+    /// The program, the arguments passed to it and the report format
+    Coverage(Source, Vec<String>, CoverageFormat),
+    Tokenize(Source, TokenFormat),
+    Ast(Source, AstFormat),
+    Check(Source),
+    /// The program and whether to only check if it is formatted
+    Fmt(Source, bool),
+    Emit(Source, EmitTarget),
+    /// Test files or directories to look for them in
+    Test(Vec<String>),
+    /// Address to listen on and limits of each program
+    Serve(String, EvalOptions),
+    Explain(String),
+    Help,
+    Version,
+}
+
+const HELP: &str = "Sapo Programming Language 🐸
+
+Usage: sapo [OPTIONS] [COMMAND]
+       sapo [OPTIONS] [FILE | - | -e CODE] [ARGS...]
+
+Commands:
+  run       Run a program
+  repl      Start an interactive session (default)
+  debug     Run a program step by step, with breakpoints
+  coverage  Run a program and print which lines were executed,
+            --format=lcov|annotate before the program
+  tokenize  Print the tokens of a program, --format=table|json
+  ast       Print the abstract syntax tree of a program,
+            --format=sexpr|json|tree|tree-lines
+  check     Report errors and warnings of a program without running it,
+            including undefined variables and functions
+  fmt       Print the program formatted, with --check only exit with 1 if
+            it isn't formatted
+  emit      Translate the program to another language, --target=js prints
+            a JavaScript script that runs it
+  test      Run the *_test.sapo files in the given files and directories,
+            or in the current one, and print which ones failed. Tests
+            check their results with assert(cond, message) and
+            assert_eq(left, right)
+  serve     Evaluate programs sent with POST to /eval and respond with
+            their result as JSON, --address=HOST:PORT (127.0.0.1:8080).
+            Each program is limited by --max-steps=N (100000),
+            --max-memory=BYTES (67108864) and --timeout=MS (5000)
+
+Every command except repl, serve and test reads the program from FILE, from stdin if '-'
+is given, or from CODE when using -e/--eval. When running or debugging a
+program, ARGS are available to it through args(). Options must be given before
+the program, or before '--' to pass a FILE starting with '-'. The arguments
+after the program are passed to it unchanged, except for a '--' right after it.
+
+Options:
+  -e, --eval CODE               Use CODE as the program
+      --allow-io                Let programs read and write files with
+                                read_file, read_lines and write_file
+      --allow-process           Let programs use environment variables and
+                                run commands with env, set_env, cwd and run
+      --error-format=human|json Format of errors and warnings
+      --no-color                Don't color the output of the REPL, also
+                                disabled by setting NO_COLOR
+      --show-ast                Print the syntax tree of every input of the
+                                REPL before evaluating it
+      --explain CODE            Describe the error with the given code
+  -h, --help                    Print this help
+  -V, --version                 Print version information";
 
 fn main() {
-    println!("Sapo Programming Language 🐸");
-    loop {
-        print!("🐸> ");
-        io::stdout().flush().unwrap();
-
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).unwrap();
-
-        match input.trim() {
-            "exit" => break,
-            _ => match sapo::parse(input) {
-                Ok(ast) => match sapo::evaluate(&*ast) {
-                    Ok(result) => {
-                        // println!("{}", sapo::ast_printer::print_ast(ast));
-                        println!("{}", result);
+    let (options, args) = parse_options(env::args().skip(1).collect());
+    let Options {
+        error_format,
+        no_color,
+        show_ast,
+        capabilities,
+    } = options;
+    // see https://no-color.org
+    let color = !no_color && env::var_os("NO_COLOR").is_none_or(|v| v.is_empty());
+    let command = match parse_command(args) {
+        Some(command) => command,
+        None => exit_with_usage(),
+    };
+    match command {
+        Command::Run(source, arguments) => {
+            let (name, code) = read_source(source);
+            run(&name, code, arguments, capabilities, &error_format)
+        }
+        Command::Debug(source, arguments) => {
+            let (name, code) = read_source(source);
+            debug::run(&name, code, arguments, capabilities)
+        }
+        Command::Repl => {
+            let color = color && io::stdout().is_terminal();
+            repl::run(&error_format, color, show_ast, capabilities)
+        }
+        Command::Coverage(source, arguments, format) => {
+            let (name, code) = read_source(source);
+            coverage(&name, code, arguments, format, capabilities, &error_format)
+        }
+        Command::Tokenize(source, format) => {
+            let (_, code) = read_source(source);
+            tokenize(code, format)
+        }
+        Command::Ast(source, format) => {
+            let (name, code) = read_source(source);
+            ast(&name, code, format, &error_format)
+        }
+        Command::Check(source) => {
+            let (name, code) = read_source(source);
+            check(&name, code, &error_format)
+        }
+        Command::Fmt(source, check_only) => {
+            let (name, code) = read_source(source);
+            fmt(&name, code, check_only, &error_format)
+        }
+        Command::Emit(source, target) => {
+            let (name, code) = read_source(source);
+            emit(&name, code, target, &error_format)
+        }
+        Command::Test(paths) => testing::run(paths),
+        Command::Serve(address, options) => serve::run(&address, options),
+        Command::Explain(code) => explain(&code),
+        Command::Help => println!("{}", HELP),
+        Command::Version => println!("sapo {}", env!("CARGO_PKG_VERSION")),
+    }
+}
+
+/// Separates the options that apply to every command from the arguments of the command.
+/// Once the program to run is found the rest are ARGS of the program. '--' ends the
+/// options, it is kept for parse_source to read the program after it.
+fn parse_options(args: Vec<String>) -> (Options, Vec<String>) {
+    let mut options = Options::default();
+    let mut rest = Vec::new();
+    let mut args = args.into_iter();
+    let mut first = true;
+    // commands that don't run a program have no ARGS, their options can go anywhere
+    let mut runs_program = true;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--error-format=human" => options.error_format = ErrorFormat::Human,
+            "--error-format=json" => options.error_format = ErrorFormat::Json,
+            "--no-color" => options.no_color = true,
+            "--show-ast" => options.show_ast = true,
+            "--allow-io" => options.capabilities.io = true,
+            "--allow-process" => options.capabilities.process = true,
+            "--" => {
+                rest.push(arg);
+                break;
+            }
+            "-e" | "--eval" | "-" => {
+                let eval = arg != "-";
+                first = false;
+                rest.push(arg);
+                if eval {
+                    rest.extend(args.next());
+                }
+                if runs_program {
+                    break;
+                }
+            }
+            flag if flag.starts_with('-') => rest.push(arg),
+            "run" | "debug" | "coverage" if first => {
+                first = false;
+                rest.push(arg);
+            }
+            "repl" | "tokenize" | "ast" | "check" | "fmt" | "emit" | "test" | "serve" if first => {
+                first = false;
+                runs_program = false;
+                rest.push(arg);
+            }
+            _ => {
+                first = false;
+                rest.push(arg);
+                if runs_program {
+                    break;
+                }
+            }
+        }
+    }
+    rest.extend(args);
+    (options, rest)
+}
+
+fn parse_command(args: Vec<String>) -> Option<Command> {
+    let mut args = args.into_iter();
+    let command = match args.next() {
+        None => Command::Repl,
+        Some(arg) => match arg.as_str() {
+            "run" => {
+                let source = parse_source(args.next()?, &mut args)?;
+                return Some(Command::Run(source, program_arguments(args)));
+            }
+            "repl" => Command::Repl,
+            "debug" => {
+                let source = parse_source(args.next()?, &mut args)?;
+                return Some(Command::Debug(source, program_arguments(args)));
+            }
+            "coverage" => {
+                let mut arg = args.next()?;
+                let format = match arg.strip_prefix("--format=") {
+                    None | Some("lcov") => CoverageFormat::Lcov,
+                    Some("annotate") => CoverageFormat::Annotate,
+                    Some(_) => return None,
+                };
+                if arg.starts_with("--format=") {
+                    arg = args.next()?;
+                }
+                let source = parse_source(arg, &mut args)?;
+                return Some(Command::Coverage(source, program_arguments(args), format));
+            }
+            "tokenize" => {
+                let (source, format) = parse_source_and_option("--format=", &mut args)?;
+                let format = match format.as_deref() {
+                    None | Some("table") => TokenFormat::Table,
+                    Some("json") => TokenFormat::Json,
+                    Some(_) => return None,
+                };
+                Command::Tokenize(source, format)
+            }
+            "ast" => {
+                let (source, format) = parse_source_and_option("--format=", &mut args)?;
+                let format = match format.as_deref() {
+                    None | Some("sexpr") => AstFormat::Sexpr,
+                    Some("json") => AstFormat::Json,
+                    Some("tree") => AstFormat::Tree,
+                    Some("tree-lines") => AstFormat::TreeLines,
+                    Some(_) => return None,
+                };
+                Command::Ast(source, format)
+            }
+            "check" => Command::Check(parse_source(args.next()?, &mut args)?),
+            "fmt" => {
+                let mut arg = args.next()?;
+                let check_only = arg == "--check";
+                if check_only {
+                    arg = args.next()?;
+                }
+                Command::Fmt(parse_source(arg, &mut args)?, check_only)
+            }
+            "emit" => {
+                let (source, target) = parse_source_and_option("--target=", &mut args)?;
+                let target = match target.as_deref() {
+                    None | Some("js") => EmitTarget::JavaScript,
+                    Some(_) => return None,
+                };
+                Command::Emit(source, target)
+            }
+            "test" => {
+                let paths: Vec<String> = args.collect();
+                if paths.iter().any(|p| p.starts_with('-')) {
+                    return None;
+                }
+                return Some(Command::Test(paths));
+            }
+            "serve" => {
+                let mut address = String::from("127.0.0.1:8080");
+                let mut options = EvalOptions {
+                    max_steps: Some(100_000),
+                    max_memory: Some(64 << 20),
+                    timeout: Some(Duration::from_secs(5)),
+                    ..EvalOptions::default()
+                };
+                for arg in args.by_ref() {
+                    if let Some(value) = arg.strip_prefix("--address=") {
+                        address = value.to_string();
+                    } else if let Some(value) = arg.strip_prefix("--max-steps=") {
+                        options.max_steps = Some(value.parse().ok()?);
+                    } else if let Some(value) = arg.strip_prefix("--max-memory=") {
+                        options.max_memory = Some(value.parse().ok()?);
+                    } else if let Some(value) = arg.strip_prefix("--timeout=") {
+                        options.timeout = Some(Duration::from_millis(value.parse().ok()?));
+                    } else {
+                        return None;
                     }
-                    Err(error) => println!("{}", error),
-                },
-                Err(error) => println!("{}", error),
-            },
+                }
+                Command::Serve(address, options)
+            }
+            "--explain" => Command::Explain(args.next()?),
+            "-h" | "--help" => Command::Help,
+            "-V" | "--version" => Command::Version,
+            // running a program is the default when no command is given
+            _ => {
+                let source = parse_source(arg, &mut args)?;
+                return Some(Command::Run(source, program_arguments(args)));
+            }
+        },
+    };
+    match args.next() {
+        Some(_) => None,
+        None => Some(command),
+    }
+}
+
+fn parse_source<I>(arg: String, args: &mut I) -> Option<Source>
+where
+    I: Iterator<Item = String>,
+{
+    match arg.as_str() {
+        "-e" | "--eval" => Some(Source::Eval(args.next()?)),
+        "-" => Some(Source::Stdin),
+        // the end of the options, the program is a file even if its name starts with '-'
+        "--" => Some(Source::File(args.next()?)),
+        flag if flag.starts_with('-') => None,
+        _ => Some(Source::File(arg)),
+    }
+}
+
+/// The ARGS after the program, without the '--' that can separate them from it
+fn program_arguments(args: impl Iterator<Item = String>) -> Vec<String> {
+    let mut args = args.peekable();
+    args.next_if(|arg| arg == "--");
+    args.collect()
+}
+
+/// Parses the source of a command that also accepts an option like --format=VALUE,
+/// before or after the source
+fn parse_source_and_option<I>(prefix: &str, args: &mut I) -> Option<(Source, Option<String>)>
+where
+    I: Iterator<Item = String>,
+{
+    let mut source = None;
+    let mut value = None;
+    while let Some(arg) = args.next() {
+        match arg.strip_prefix(prefix) {
+            Some(v) => value = Some(v.to_string()),
+            None if source.is_none() => source = Some(parse_source(arg, args)?),
+            None => return None,
+        }
+    }
+    Some((source?, value))
+}
+
+/// Returns the name used to prefix errors and the contents of the source
+fn read_source(source: Source) -> (String, String) {
+    let (name, result) = match source {
+        Source::File(path) => {
+            let result = fs::read_to_string(&path);
+            (path, result)
+        }
+        Source::Stdin => {
+            let mut code = String::new();
+            let result = io::stdin().read_to_string(&mut code).map(|_| code);
+            (String::from("<stdin>"), result)
+        }
+        Source::Eval(code) => (String::from("<eval>"), Ok(code)),
+    };
+    match result {
+        Ok(code) => (name, code),
+        Err(error) => {
+            eprintln!("{}: {}", name, error);
+            process::exit(1);
+        }
+    }
+}
+
+fn exit_with_usage() -> ! {
+    eprintln!("{}", HELP);
+    process::exit(1);
+}
+
+fn explain(code: &str) {
+    match sapo::explain(code) {
+        Some(explanation) => print!("{}", explanation),
+        None => {
+            eprintln!("Unknown error code '{}'", code);
+            process::exit(1);
+        }
+    }
+}
+
+/// Runs a whole program, name is used to prefix errors
+fn run(
+    name: &str,
+    code: String,
+    arguments: Vec<String>,
+    capabilities: Capabilities,
+    error_format: &ErrorFormat,
+) {
+    let program = match sapo::parse_program(&code) {
+        Ok(program) => program,
+        Err(error) => {
+            report_in_file(name, &error, error_format);
+            process::exit(1);
+        }
+    };
+    for &statement in &program.statements {
+        for warning in sapo::check_warnings(&program.ast, statement) {
+            report_in_file(name, &warning, error_format);
+        }
+    }
+    let mut environment = Environment::new();
+    environment.set_arguments(arguments);
+    capabilities.enable(&mut environment);
+    match sapo::evaluate_program(&program, &mut environment) {
+        Ok(Some(result)) => println!("{}", result),
+        Ok(None) => {}
+        Err(EvalError::Exit(_, code)) => process::exit(code),
+        Err(error) => {
+            report_in_file(name, &error, error_format);
+            process::exit(1);
+        }
+    }
+}
+
+/// Runs the program without printing its result, then prints the coverage report
+fn coverage(
+    name: &str,
+    code: String,
+    arguments: Vec<String>,
+    format: CoverageFormat,
+    capabilities: Capabilities,
+    error_format: &ErrorFormat,
+) {
+    let program = match sapo::parse_program(&code) {
+        Ok(program) => program,
+        Err(error) => {
+            report_in_file(name, &error, error_format);
+            process::exit(1);
+        }
+    };
+    let coverage = sapo::Coverage::new();
+    let mut environment = Environment::new();
+    environment.set_arguments(arguments);
+    capabilities.enable(&mut environment);
+    // keeps the output of the program out of the report
+    environment.set_output(Box::new(io::stderr()));
+    environment.add_hook(Box::new(coverage.clone()));
+    let status = match sapo::evaluate_program(&program, &mut environment) {
+        Ok(_) => 0,
+        Err(EvalError::Exit(_, code)) => code,
+        Err(error) => {
+            report_in_file(name, &error, error_format);
+            1
+        }
+    };
+    match format {
+        CoverageFormat::Lcov => print!("{}", coverage.to_lcov(name, &program)),
+        CoverageFormat::Annotate => print!("{}", coverage.annotate(&code, &program)),
+    }
+    process::exit(status);
+}
+
+fn tokenize(code: String, format: TokenFormat) {
+    let tokens: Vec<_> = sapo::Lexer::with_comments(&code).collect();
+    match format {
+        TokenFormat::Table => {
+            println!("{:<6}{:<16}LEXEME", "LINE", "TYPE");
+            for token in &tokens {
+                print_token(token);
+            }
+        }
+        TokenFormat::Json => println!("{}", sapo::token::tokens_to_json(&tokens)),
+    }
+}
+
+fn print_token(token: &sapo::token::Token) {
+    println!(
+        "{:<6}{:<16}{}",
+        token.line,
+        format!("{:?}", token.token_type),
+        token.lexeme
+    );
+}
+
+fn ast(name: &str, code: String, format: AstFormat, error_format: &ErrorFormat) {
+    match sapo::parse_program(&code) {
+        Ok(program) => match format {
+            AstFormat::Sexpr => {
+                for &statement in &program.statements {
+                    println!("{}", sapo::print_ast(&program.ast, statement));
+                }
+            }
+            // a program is an array of expressions
+            AstFormat::Json => {
+                println!("{}", program.to_json());
+            }
+            AstFormat::Tree => {
+                for &statement in &program.statements {
+                    print!("{}", sapo::print_ast_tree(&program.ast, statement));
+                }
+            }
+            AstFormat::TreeLines => {
+                for &statement in &program.statements {
+                    let tree = sapo::print_ast_tree_with_lines(&program.ast, statement);
+                    print!("{}", tree);
+                }
+            }
+        },
+        Err(error) => {
+            report_in_file(name, &error, error_format);
+            process::exit(1);
+        }
+    }
+}
+
+fn check(name: &str, code: String, error_format: &ErrorFormat) {
+    let diagnostics = sapo::check(&code);
+    for diagnostic in &diagnostics {
+        match error_format {
+            ErrorFormat::Human => eprintln!("{}: {}", name, diagnostic),
+            ErrorFormat::Json => eprintln!("{}", diagnostic.to_json()),
+        }
+    }
+    if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+        process::exit(1);
+    }
+}
+
+fn fmt(name: &str, code: String, check_only: bool, error_format: &ErrorFormat) {
+    let program = match sapo::parse_program(&code) {
+        Ok(program) => program,
+        Err(error) => {
+            report_in_file(name, &error, error_format);
+            process::exit(1);
+        }
+    };
+    let mut formatted = sapo::format_program_with_comments(&program, &code);
+    // the lexer skips the shebang, so it's not part of the program
+    if let Some(shebang) = code.lines().next().filter(|l| l.starts_with("#!")) {
+        formatted = format!("{}\n{}", shebang, formatted);
+    }
+    if !check_only {
+        print!("{}", formatted);
+    } else if formatted != code {
+        eprintln!("{}: not formatted", name);
+        process::exit(1);
+    }
+}
+
+fn emit(name: &str, code: String, target: EmitTarget, error_format: &ErrorFormat) {
+    let program = match sapo::parse_program(&code) {
+        Ok(program) => program,
+        Err(error) => {
+            report_in_file(name, &error, error_format);
+            process::exit(1);
+        }
+    };
+    match target {
+        EmitTarget::JavaScript => print!("{}", sapo::emit_js(&program)),
+    }
+}
+
+fn report_in_file<D>(path: &str, diagnostic: &D, error_format: &ErrorFormat)
+where
+    D: fmt::Display,
+    for<'a> &'a D: Into<Diagnostic>,
+{
+    match error_format {
+        ErrorFormat::Human => eprintln!("{}: {}", path, diagnostic),
+        ErrorFormat::Json => eprintln!("{}", diagnostic.into().to_json()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> (Options, Vec<String>) {
+        parse_options(args.iter().map(|arg| arg.to_string()).collect())
+    }
+
+    #[test]
+    fn options_before_the_program() {
+        let (options, args) = parse(&["--allow-io", "run", "--no-color", "a.sapo", "x"]);
+        assert!(options.capabilities.io && options.no_color);
+        assert_eq!(args, ["run", "a.sapo", "x"]);
+        let (options, args) = parse(&["check", "a.sapo", "--error-format=json"]);
+        assert_eq!(options.error_format, ErrorFormat::Json);
+        assert_eq!(args, ["check", "a.sapo"]);
+    }
+
+    #[test]
+    fn arguments_of_the_program() {
+        let (options, args) = parse(&["a.sapo", "--allow-process", "--no-color"]);
+        assert_eq!(options, Options::default());
+        assert_eq!(args, ["a.sapo", "--allow-process", "--no-color"]);
+        let (options, args) = parse(&["-e", "cwd()", "x", "--allow-process"]);
+        assert_eq!(options, Options::default());
+        assert_eq!(args, ["-e", "cwd()", "x", "--allow-process"]);
+        let (options, args) = parse(&["run", "-", "--", "--show-ast"]);
+        assert_eq!(options, Options::default());
+        assert_eq!(args, ["run", "-", "--", "--show-ast"]);
+        let (options, args) = parse(&["--show-ast", "--", "--allow-io", "a.sapo"]);
+        assert!(options.show_ast && !options.capabilities.io);
+        assert_eq!(args, ["--", "--allow-io", "a.sapo"]);
+    }
+
+    #[test]
+    fn end_of_options() {
+        let command = |args: &[&str]| {
+            let (_, rest) = parse(args);
+            match parse_command(rest) {
+                Some(Command::Run(source, arguments)) => (source, arguments),
+                _ => panic!("not a run command: {:?}", args),
+            }
         };
+        let file = |name: &str| Source::File(name.to_string());
+        let (source, arguments) = command(&["--allow-io", "--", "-a.sapo", "--no-color"]);
+        assert_eq!(
+            (source, arguments),
+            (file("-a.sapo"), vec![String::from("--no-color")])
+        );
+        let (source, arguments) = command(&["a.sapo", "--", "--", "x"]);
+        assert_eq!(
+            (source, arguments),
+            (file("a.sapo"), vec![String::from("--"), String::from("x")])
+        );
+        let (source, arguments) = command(&["run", "-e", "args()", "x", "--"]);
+        assert_eq!(
+            (source, arguments),
+            (
+                Source::Eval(String::from("args()")),
+                vec![String::from("x"), String::from("--")]
+            )
+        );
     }
 }
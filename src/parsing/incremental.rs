@@ -0,0 +1,311 @@
+use super::lexer::Lexer;
+use super::parser::{parse_statement_with_recovery, ParseError, ParseOptions};
+use crate::ast::{Ast, ExprId, Program};
+use crate::token::{Span, Token, TokenType};
+use std::mem;
+use std::ops::Range;
+
+/// The source of a program being edited, e.g. in an editor, and its syntax tree.
+/// An edit only re-lexes and re-parses the statements it changes: the ones before it are
+/// kept and the ones after it are moved to their new position in the source. Statements
+/// with syntax errors are Expression::Error nodes, like with parse_program_with_recovery.
+#[derive(Debug)]
+pub struct Document {
+    source: String,
+    options: ParseOptions,
+    program: Program,
+    /// The syntax error and the ';' of each statement of the program
+    statements: Vec<Statement>,
+}
+
+#[derive(Debug)]
+struct Statement {
+    error: Option<ParseError>,
+    /// None for the last statement if it has no ';'
+    separator: Option<Token>,
+}
+
+impl Document {
+    pub fn new(source: &str) -> Self {
+        Document::with_options(source, ParseOptions::default())
+    }
+
+    pub fn with_options(source: &str, options: ParseOptions) -> Self {
+        let mut document = Document {
+            source: source.to_string(),
+            options,
+            program: Program::default(),
+            statements: Vec::new(),
+        };
+        document.edit(0..0, "");
+        document
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    /// The syntax errors of the program, in source order
+    pub fn errors(&self) -> Vec<&ParseError> {
+        self.statements
+            .iter()
+            .filter_map(|s| s.error.as_ref())
+            .collect()
+    }
+
+    /// Replaces the byte range of the source with replacement and updates the syntax tree.
+    /// Parsing starts again at the first statement the edit touches and stops at the first
+    /// ';' after the edit that was also a statement boundary before it.
+    ///
+    /// # Panics
+    ///
+    /// If the range is out of bounds or doesn't start and end at char boundaries
+    pub fn edit(&mut self, range: Range<usize>, replacement: &str) {
+        let first = self
+            .statements
+            .iter()
+            .position(|s| match &s.separator {
+                Some(separator) => separator.span.end > range.start,
+                None => true,
+            })
+            .unwrap_or(self.statements.len());
+        let mut source = self.source.clone();
+        source.replace_range(range.clone(), replacement);
+        let shift = Shift::new(&self.source, &source, &range, replacement.len());
+        self.source = source;
+
+        let old = mem::take(&mut self.program);
+        let mut suffix = mem::take(&mut self.statements);
+        let prefix: Vec<Statement> = suffix.drain(..first).collect();
+        for &id in &old.statements[..first] {
+            let id = copy_expression(&old.ast, id, &mut self.program.ast, &Shift::default());
+            self.program.statements.push(id);
+        }
+        self.statements = prefix;
+
+        let lexer = Lexer::with_operators(&self.source, &self.options.operators);
+        let lexer = match self.statements.last().and_then(|s| s.separator.as_ref()) {
+            Some(separator) => lexer.resume_after(separator),
+            None => lexer,
+        };
+        let mut tokens = lexer.peekable();
+        let edit_end = range.start + replacement.len();
+        // index in suffix of the first statement kept after the edit
+        let mut kept = None;
+        while tokens.peek().is_some() {
+            let statement = parse_statement_with_recovery(
+                &mut tokens,
+                &self.source,
+                &mut self.program.ast,
+                &self.options,
+            );
+            self.program.statements.push(statement.id);
+            let end = statement.separator.as_ref().map(|s| s.span.end);
+            self.statements.push(Statement {
+                error: statement.error,
+                separator: statement.separator,
+            });
+            // after a ';' that was there before the edit, the rest of the source is unchanged
+            if let Some(end) = end.filter(|&end| end >= edit_end) {
+                let old_end = end + range.len() - replacement.len();
+                let found = suffix.binary_search_by_key(&old_end, |s| match &s.separator {
+                    Some(separator) => separator.span.end,
+                    None => usize::MAX,
+                });
+                if let Ok(i) = found {
+                    kept = Some(i + 1);
+                    break;
+                }
+            }
+        }
+
+        let kept = match kept {
+            Some(kept) => kept,
+            None => return,
+        };
+        let old_statements = &old.statements[first + kept..];
+        for (mut statement, &id) in suffix.into_iter().skip(kept).zip(old_statements) {
+            let id = copy_expression(&old.ast, id, &mut self.program.ast, &shift);
+            self.program.statements.push(id);
+            if let Some(error) = &mut statement.error {
+                shift.token(error.token_mut());
+            }
+            if let Some(separator) = &mut statement.separator {
+                shift.token(separator);
+            }
+            self.statements.push(statement);
+        }
+    }
+}
+
+/// Adds the expression id of from and its subexpressions to to, moved by shift
+fn copy_expression(from: &Ast, id: ExprId, to: &mut Ast, shift: &Shift) -> ExprId {
+    let mut expression = from[id]
+        .clone()
+        .map_children(|child| copy_expression(from, child, to, shift));
+    shift.token(expression.token_mut());
+    to.add_with_span(expression, shift.span(from.span(id)))
+}
+
+/// How the positions after an edit move, the default one doesn't move them
+#[derive(Default)]
+struct Shift {
+    /// Bytes removed and inserted by the edit
+    removed: usize,
+    inserted: usize,
+    /// Line breaks removed and inserted by the edit
+    removed_lines: i32,
+    inserted_lines: i32,
+    /// Old offset of the end of the line the edit ends on, the columns of the tokens
+    /// before it change from old_column to new_column
+    line_end: usize,
+    old_column: usize,
+    new_column: usize,
+}
+
+impl Shift {
+    fn new(old: &str, new: &str, range: &Range<usize>, inserted: usize) -> Shift {
+        // a char more on each side, which is the same before and after the edit,
+        // so that a "\r\n" split by the edit isn't counted as two line breaks
+        let start = range.start.saturating_sub(1);
+        let removed_lines = line_breaks(&old.as_bytes()[start..(range.end + 1).min(old.len())]);
+        let end = range.start + inserted;
+        let inserted_lines = line_breaks(&new.as_bytes()[start..(end + 1).min(new.len())]);
+        let line_end = old[range.end..]
+            .find(['\n', '\r'])
+            .map_or(usize::MAX, |i| range.end + i);
+        Shift {
+            removed: range.len(),
+            inserted,
+            removed_lines,
+            inserted_lines,
+            line_end,
+            old_column: column(old, range.end),
+            new_column: column(new, end),
+        }
+    }
+
+    fn span(&self, span: Span) -> Span {
+        Span::new(self.offset(span.start), self.offset(span.end))
+    }
+
+    fn offset(&self, offset: usize) -> usize {
+        offset + self.inserted - self.removed
+    }
+
+    /// Moves a token from after the edit, the EOF tokens of errors don't have a position
+    fn token(&self, token: &mut Token) {
+        if token.token_type == TokenType::EOF {
+            return;
+        }
+        if token.span.start < self.line_end {
+            token.column = token.column + self.new_column - self.old_column;
+        }
+        token.line += self.inserted_lines - self.removed_lines;
+        token.span = self.span(token.span);
+    }
+}
+
+/// Number of lines ended by "\n", "\r\n" or "\r" in text
+fn line_breaks(text: &[u8]) -> i32 {
+    let mut count = 0;
+    for (i, &byte) in text.iter().enumerate() {
+        if byte == b'\n' || (byte == b'\r' && text.get(i + 1) != Some(&b'\n')) {
+            count += 1;
+        }
+    }
+    count
+}
+
+/// Column of the char at offset, counted in chars from 1 like the ones of tokens
+fn column(text: &str, offset: usize) -> usize {
+    let line = text[..offset]
+        .chars()
+        .rev()
+        .take_while(|&c| c != '\n' && c != '\r');
+    line.count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsing::parse_program_with_recovery;
+    use proptest::prelude::*;
+
+    #[test]
+    fn edit_statements() {
+        let mut document = Document::new("x = 1;\ny = x +\n 2;\nz = y");
+        document.edit(11..11, "(");
+        assert_same_as_parsing(&document);
+        assert_eq!(document.source(), "x = 1;\ny = (x +\n 2;\nz = y");
+        let errors: Vec<String> = document.errors().iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            errors,
+            ["ParseError at line 3: Expected ')', but ';' was found. [E0001]"]
+        );
+        document.edit(18..18, ")");
+        assert!(document.errors().is_empty());
+        document.edit(0..7, "");
+        document.edit(document.source().len()..document.source().len(), " * 2;\n");
+        assert_same_as_parsing(&document);
+        assert_eq!(document.source(), "y = (x +\n 2);\nz = y * 2;\n");
+    }
+
+    proptest! {
+        #[test]
+        fn edits_parse_like_the_whole_source(
+            source in source(),
+            edits in prop::collection::vec((any::<prop::sample::Index>(), 0..8usize, source()), 1..4),
+        ) {
+            let mut document = Document::new(&source);
+            for (start, length, replacement) in edits {
+                let start = start.index(document.source().len() + 1);
+                let end = (start + length).min(document.source().len());
+                document.edit(start..end, &replacement);
+                assert_same_as_parsing(&document);
+            }
+        }
+    }
+
+    /// Sources made of fragments that change how the text after them is lexed and parsed
+    fn source() -> impl Strategy<Value = String> {
+        let fragments = prop::sample::select(vec![
+            "x", "1", " ", "=", "+", "(", ")", ";", "\n", "\r", "\r\n", "\"", "//", "f(", ",", "[",
+            "]", ":",
+        ]);
+        prop::collection::vec(fragments, 0..16).prop_map(|fragments| fragments.concat())
+    }
+
+    fn assert_same_as_parsing(document: &Document) {
+        let (program, errors) =
+            parse_program_with_recovery(document.source(), &ParseOptions::default());
+        assert_eq!(
+            without_unused_expressions(document.program()),
+            without_unused_expressions(&program),
+            "{:?}",
+            document.source()
+        );
+        let errors: Vec<String> = errors.iter().map(|e| format!("{:?}", e)).collect();
+        let document_errors: Vec<String> = document
+            .errors()
+            .iter()
+            .map(|e| format!("{:?}", e))
+            .collect();
+        assert_eq!(document_errors, errors, "{:?}", document.source());
+    }
+
+    /// The program without the expressions that aren't part of a statement,
+    /// like the targets of assignments, which the parser adds to the tree
+    fn without_unused_expressions(program: &Program) -> Program {
+        let mut copy = Program::default();
+        for &id in &program.statements {
+            let id = copy_expression(&program.ast, id, &mut copy.ast, &Shift::default());
+            copy.statements.push(id);
+        }
+        copy
+    }
+}
@@ -1,32 +1,81 @@
-use crate::token::Token;
-use crate::token::TokenType;
-use std::collections::HashMap;
+use super::parser::CustomOperator;
+use crate::token::{Span, Token, TokenType};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::num::ParseIntError;
+use std::sync::Arc;
 
+/// The char after the end of the input. The input can contain it too, so the end is
+/// detected by position, see Lexer::at_end.
 const EOF: char = '\u{0}';
 
-struct Keyword {
-    token_type: TokenType,
-    lexeme: &'static str,
-}
+/// Built-in tokens longer than one char, a custom operator that is a prefix of one
+/// of them, like "|" of "|>", is only read when the built-in token doesn't match
+const MULTI_CHAR_TOKENS: [&str; 6] = ["|>", "==", "!=", ">=", "<=", "//"];
 
-pub struct Lexer {
-    input: Vec<char>,
+/// Reads the tokens of a program, which it borrows. The tokens don't borrow from it.
+pub struct Lexer<'a> {
+    input: &'a str,
+    /// Byte offset of the current char
     position: usize,
+    /// Byte offset of the char after the current one
     next_position: usize,
     current_char: char,
-    keywords: HashMap<String, Keyword>,
+    keywords: HashMap<&'static str, TokenType>,
+    /// Text of the tokens lexed so far, tokens with the same text share it
+    lexemes: HashSet<Arc<str>>,
     current_line: i32,
+    /// Column of the current char in its line, in chars from 1
+    current_column: usize,
+    /// Whether comments are returned as tokens instead of being skipped
+    comments: bool,
+    /// Lexed as Operator tokens, before the built-in operators
+    operators: &'a [CustomOperator],
 }
 
-impl Lexer {
-    pub fn new(input: String) -> Self {
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a str) -> Self {
+        let start = shebang_length(input);
         Lexer {
-            input: input.chars().collect::<Vec<_>>(),
+            input,
             position: 0,
-            next_position: 0,
+            next_position: start,
             current_char: EOF,
             keywords: initialize_keywords(),
+            lexemes: HashSet::new(),
             current_line: 1,
+            current_column: 0,
+            comments: false,
+            operators: &[],
+        }
+    }
+
+    /// A lexer that also returns the comments of the input, as Comment tokens,
+    /// for tools that need to keep them. The parser doesn't accept them.
+    pub fn with_comments(input: &'a str) -> Self {
+        Lexer {
+            comments: true,
+            ..Lexer::new(input)
+        }
+    }
+
+    /// A lexer that also reads the given operators, the longest one matching the input
+    /// is read even if the input starts with a built-in operator, e.g. "**" rather than "*".
+    /// Longer built-in operators win, e.g. "|>" rather than an operator "|".
+    pub fn with_operators(input: &'a str, operators: &'a [CustomOperator]) -> Self {
+        Lexer {
+            operators,
+            ..Lexer::new(input)
+        }
+    }
+
+    /// Continues lexing after token, which the lexer would have read from the same input
+    pub(crate) fn resume_after(self, token: &Token) -> Self {
+        Lexer {
+            next_position: token.span.end,
+            current_line: token.line,
+            current_column: token.column,
+            ..self
         }
     }
 
@@ -34,69 +83,130 @@ impl Lexer {
         self.advance();
         //move to first non-whitespace character
         self.advance_until(|c| !c.is_whitespace());
-        match self.current_char {
-            EOF => Token::new(TokenType::EOF, "EOF".to_string(), self.current_line),
+        while !self.comments && self.current_char == '/' && self.peek() == '/' {
+            self.skip_comment();
+            self.advance();
+            self.advance_until(|c| !c.is_whitespace());
+        }
+        let start = self.position;
+        let (line, column) = (self.current_line, self.current_column);
+        let rest = &self.input[start..];
+        let built_in = MULTI_CHAR_TOKENS
+            .iter()
+            .filter(|token| rest.starts_with(*token))
+            .map(|token| token.len())
+            .max()
+            .unwrap_or(0);
+        let operator = self
+            .operators
+            .iter()
+            .map(|o| o.symbol.as_str())
+            .filter(|symbol| rest.starts_with(symbol) && symbol.len() > built_in)
+            .max_by_key(|symbol| symbol.len());
+        let mut token = match self.current_char {
+            _ if self.at_end() => Token::new(TokenType::EOF, "EOF", self.current_line),
+            _ if operator.is_some() => {
+                for _ in operator.into_iter().flat_map(str::chars).skip(1) {
+                    self.advance();
+                }
+                Token::new(
+                    TokenType::Operator,
+                    self.lexeme(start, self.next_position),
+                    self.current_line,
+                )
+            }
             c if is_digit(c) => self.read_number(),
-            c if is_alpha(c) => self.read_identifier(),
+            c if is_identifier_start(c) => self.read_identifier(),
             '"' => self.read_string(),
             '-' => Token::new(
                 TokenType::Minus,
-                self.current_char.to_string(),
+                self.lexeme(start, self.next_position),
                 self.current_line,
             ),
             '+' => Token::new(
                 TokenType::Plus,
-                self.current_char.to_string(),
+                self.lexeme(start, self.next_position),
                 self.current_line,
             ),
             '*' => Token::new(
                 TokenType::Star,
-                self.current_char.to_string(),
+                self.lexeme(start, self.next_position),
                 self.current_line,
             ),
+            '/' if self.peek() == '/' => {
+                self.skip_comment();
+                Token::new(
+                    TokenType::Comment,
+                    self.lexeme(start, self.next_position),
+                    self.current_line,
+                )
+            }
             '/' => Token::new(
                 TokenType::Slash,
-                self.current_char.to_string(),
+                self.lexeme(start, self.next_position),
                 self.current_line,
             ),
             '(' => Token::new(
                 TokenType::LeftParen,
-                self.current_char.to_string(),
+                self.lexeme(start, self.next_position),
                 self.current_line,
             ),
             ')' => Token::new(
                 TokenType::RightParen,
-                self.current_char.to_string(),
+                self.lexeme(start, self.next_position),
                 self.current_line,
             ),
             '{' => Token::new(
                 TokenType::LeftBrace,
-                self.current_char.to_string(),
+                self.lexeme(start, self.next_position),
                 self.current_line,
             ),
             '}' => Token::new(
                 TokenType::RightBrace,
-                self.current_char.to_string(),
+                self.lexeme(start, self.next_position),
+                self.current_line,
+            ),
+            '[' => Token::new(
+                TokenType::LeftBracket,
+                self.lexeme(start, self.next_position),
+                self.current_line,
+            ),
+            ']' => Token::new(
+                TokenType::RightBracket,
+                self.lexeme(start, self.next_position),
+                self.current_line,
+            ),
+            ':' => Token::new(
+                TokenType::Colon,
+                self.lexeme(start, self.next_position),
                 self.current_line,
             ),
             '=' => {
                 if self.matches('=') {
-                    Token::new(TokenType::Equals, "==".to_string(), self.current_line)
+                    Token::new(
+                        TokenType::Equals,
+                        self.lexeme(start, self.next_position),
+                        self.current_line,
+                    )
                 } else {
                     Token::new(
                         TokenType::Assignment,
-                        self.current_char.to_string(),
+                        self.lexeme(start, self.next_position),
                         self.current_line,
                     )
                 }
             }
             '!' => {
                 if self.matches('=') {
-                    Token::new(TokenType::BangEquals, "!=".to_string(), self.current_line)
+                    Token::new(
+                        TokenType::BangEquals,
+                        self.lexeme(start, self.next_position),
+                        self.current_line,
+                    )
                 } else {
                     Token::new(
                         TokenType::Bang,
-                        self.current_char.to_string(),
+                        self.lexeme(start, self.next_position),
                         self.current_line,
                     )
                 }
@@ -105,13 +215,13 @@ impl Lexer {
                 if self.matches('=') {
                     Token::new(
                         TokenType::SmallerEquals,
-                        "<=".to_string(),
+                        self.lexeme(start, self.next_position),
                         self.current_line,
                     )
                 } else {
                     Token::new(
                         TokenType::Smaller,
-                        self.current_char.to_string(),
+                        self.lexeme(start, self.next_position),
                         self.current_line,
                     )
                 }
@@ -120,38 +230,65 @@ impl Lexer {
                 if self.matches('=') {
                     Token::new(
                         TokenType::GreaterEquals,
-                        ">=".to_string(),
+                        self.lexeme(start, self.next_position),
                         self.current_line,
                     )
                 } else {
                     Token::new(
                         TokenType::Greater,
-                        self.current_char.to_string(),
+                        self.lexeme(start, self.next_position),
                         self.current_line,
                     )
                 }
             }
+            '|' if self.peek() == '>' => {
+                self.advance();
+                Token::new(
+                    TokenType::Pipe,
+                    self.lexeme(start, self.next_position),
+                    self.current_line,
+                )
+            }
             ';' => Token::new(
                 TokenType::Semicolon,
-                self.current_char.to_string(),
+                self.lexeme(start, self.next_position),
+                self.current_line,
+            ),
+            ',' => Token::new(
+                TokenType::Comma,
+                self.lexeme(start, self.next_position),
                 self.current_line,
             ),
             _ => Token::new(
                 TokenType::InvalidToken,
-                self.current_char.to_string(),
+                self.lexeme(start, self.next_position),
                 self.current_line,
             ),
-        }
+        };
+        token.span = Span::new(start, self.next_position);
+        // tokens spanning several lines, i.e. strings, are on the line they start
+        token.line = line;
+        token.column = column;
+        token
     }
 
     fn read_string(&mut self) -> Token {
-        // advance opening '"'
-        self.advance();
-        let start = self.position;
-        self.advance_while(|c| c != '"');
+        let quote = self.position;
+        let start = self.next_position;
+        while self.peek() != '"' {
+            if self.next_position >= self.input.len() {
+                // unterminated, the whole rest of the input is invalid
+                return Token::new(
+                    TokenType::InvalidToken,
+                    self.lexeme(quote, self.input.len()),
+                    self.current_line,
+                );
+            }
+            self.advance();
+        }
         let t = Token::new(
             TokenType::StringLiteral,
-            self.extract_substring(start, self.position + 1),
+            self.lexeme(start, self.next_position),
             self.current_line,
         );
         // advance closing '"'
@@ -159,26 +296,58 @@ impl Lexer {
         t
     }
 
+    /// Advances to the last char of the comment starting at the current char,
+    /// comments end at the end of the line
+    fn skip_comment(&mut self) {
+        while self.peek() != '\n' && self.next_position < self.input.len() {
+            self.advance();
+        }
+    }
+
+    /// Reads a decimal, 0x hexadecimal, 0o octal or 0b binary integer, digits can be
+    /// separated by '_'. Malformed literals like 0x or 0b12 are an InvalidToken.
     fn read_number(&mut self) -> Token {
         let start = self.position;
-        self.advance_while(is_digit);
+        let valid = match radix(self.current_char, self.peek()) {
+            Some(radix) => {
+                // the prefix, then everything that could be part of the literal
+                self.advance();
+                while is_identifier_continue(self.peek()) {
+                    self.advance();
+                }
+                let digits = &self.input[start + 2..self.next_position];
+                digits.chars().any(|c| c != '_')
+                    && digits.chars().all(|c| c == '_' || c.is_digit(radix))
+            }
+            None => {
+                self.advance_while(|c| is_digit(c) || c == '_');
+                !self.input[start..self.next_position].ends_with('_')
+            }
+        };
+        let token_type = if valid {
+            TokenType::IntegerLiteral
+        } else {
+            TokenType::InvalidToken
+        };
         Token::new(
-            TokenType::IntegerLiteral,
-            self.extract_substring(start, self.position + 1),
+            token_type,
+            self.lexeme(start, self.next_position),
             self.current_line,
         )
     }
 
     fn read_identifier(&mut self) -> Token {
         let start = self.position;
-        self.advance_while(is_alpha);
-        let identifier = self.extract_substring(start, self.position + 1);
-        match self.keywords.get(&identifier) {
-            Some(Keyword { token_type, lexeme }) => {
-                Token::new(token_type.clone(), lexeme.to_string(), self.current_line)
-            }
-            None => Token::new(TokenType::Identifier, identifier, self.current_line),
-        }
+        self.advance_while(is_identifier_continue);
+        let token_type = match self.keywords.get(&self.input[start..self.next_position]) {
+            Some(token_type) => token_type.clone(),
+            None => TokenType::Identifier,
+        };
+        Token::new(
+            token_type,
+            self.lexeme(start, self.next_position),
+            self.current_line,
+        )
     }
 
     /// Advances the lexer until the current char passes the predicate
@@ -188,7 +357,7 @@ impl Lexer {
     where
         P: Fn(char) -> bool,
     {
-        while self.current_char != EOF && !predicate(self.current_char) {
+        while !self.at_end() && !predicate(self.current_char) {
             self.advance();
         }
     }
@@ -200,28 +369,41 @@ impl Lexer {
     where
         P: Fn(char) -> bool,
     {
-        while self.current_char != EOF && predicate(self.current_char) && predicate(self.peek()) {
+        while !self.at_end() && predicate(self.current_char) && predicate(self.peek()) {
             self.advance();
         }
     }
 
     fn advance(&mut self) {
-        self.current_char = match self.input.get(self.next_position) {
-            Some(&c) => c,
-            None => EOF,
-        };
+        self.current_char = self.peek();
         self.position = self.next_position;
-        self.next_position += 1;
+        // past the end the position stays at the end of the input
+        self.next_position =
+            (self.next_position + self.current_char.len_utf8()).min(self.input.len());
 
-        if self.current_char == '\n' {
+        // lines end with "\n", "\r\n" or a "\r" on its own
+        let new_line = match self.current_char {
+            '\n' => true,
+            '\r' => self.peek() != '\n',
+            _ => false,
+        };
+        if new_line {
             self.current_line += 1;
+            self.current_column = 0;
+        } else {
+            self.current_column += 1;
         }
     }
 
+    /// Whether the current char is past the end of the input
+    fn at_end(&self) -> bool {
+        self.position >= self.input.len()
+    }
+
     fn peek(&self) -> char {
-        match self.input.get(self.next_position) {
-            Some(&c) => c,
-            _ => EOF,
+        match self.input.get(self.next_position..) {
+            Some(rest) => rest.chars().next().unwrap_or(EOF),
+            None => EOF,
         }
     }
 
@@ -236,12 +418,163 @@ impl Lexer {
         false
     }
 
-    fn extract_substring(&self, from: usize, to: usize) -> String {
-        (&self.input[from..to]).iter().collect()
+    /// The input between the byte offsets from and to, only allocated
+    /// the first time it is found
+    fn lexeme(&mut self, from: usize, to: usize) -> Arc<str> {
+        let text = &self.input[from..to];
+        if let Some(lexeme) = self.lexemes.get(text) {
+            return Arc::clone(lexeme);
+        }
+        let lexeme: Arc<str> = Arc::from(text);
+        self.lexemes.insert(Arc::clone(&lexeme));
+        lexeme
     }
 }
 
-impl Iterator for Lexer {
+/// Text the lexer couldn't read a token from, the lexer itself reports it as an InvalidToken
+#[derive(Debug, PartialEq, Clone)]
+pub enum LexError {
+    UnexpectedCharacter(Token),
+    /// A string without the closing '"', the token is the rest of the input
+    UnterminatedString(Token),
+    /// An integer literal with invalid digits for its radix, or without any
+    MalformedInteger(Token),
+}
+
+impl LexError {
+    /// The error an InvalidToken produced by the lexer stands for
+    pub(crate) fn from_invalid_token(token: Token) -> LexError {
+        if token.lexeme.starts_with('"') {
+            LexError::UnterminatedString(token)
+        } else if token.lexeme.starts_with(is_digit) {
+            LexError::MalformedInteger(token)
+        } else {
+            LexError::UnexpectedCharacter(token)
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            LexError::UnexpectedCharacter(_) => "E0020",
+            LexError::UnterminatedString(_) => "E0021",
+            LexError::MalformedInteger(_) => "E0022",
+        }
+    }
+
+    pub fn token(&self) -> &Token {
+        match self {
+            LexError::UnexpectedCharacter(t)
+            | LexError::UnterminatedString(t)
+            | LexError::MalformedInteger(t) => t,
+        }
+    }
+
+    pub(crate) fn token_mut(&mut self) -> &mut Token {
+        match self {
+            LexError::UnexpectedCharacter(t)
+            | LexError::UnterminatedString(t)
+            | LexError::MalformedInteger(t) => t,
+        }
+    }
+
+    /// Description of the error without location or code
+    pub fn message(&self) -> String {
+        match self {
+            LexError::UnexpectedCharacter(t) => {
+                // control chars like NUL wouldn't be visible
+                let character: String = t
+                    .lexeme
+                    .chars()
+                    .map(|c| {
+                        if c.is_control() {
+                            c.escape_default().to_string()
+                        } else {
+                            c.to_string()
+                        }
+                    })
+                    .collect();
+                format!("Unexpected character '{}'.", character)
+            }
+            LexError::UnterminatedString(_) => String::from("Unterminated string."),
+            LexError::MalformedInteger(t) => format!("Malformed integer literal '{}'.", t.lexeme),
+        }
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "LexError at line {}: {} [{}]",
+            self.token().line,
+            self.message(),
+            self.code()
+        )
+    }
+}
+
+/// The tokens of the input, or the first text that isn't a valid token
+pub fn tokenize(input: &str) -> Result<Vec<Token>, LexError> {
+    Lexer::new(input)
+        .map(|token| match token.token_type {
+            TokenType::InvalidToken => Err(LexError::from_invalid_token(token)),
+            _ => Ok(token),
+        })
+        .collect()
+}
+
+/// Whether symbol can be the text of a CustomOperator: punctuation that isn't a built-in
+/// operator, doesn't contain delimiters like parentheses or quotes and doesn't start a comment
+pub(crate) fn is_operator_symbol(symbol: &str) -> bool {
+    let mut tokens = Lexer::new(symbol);
+    let built_in = match (tokens.next(), tokens.next()) {
+        (Some(token), None) => token.token_type != TokenType::InvalidToken,
+        _ => false,
+    };
+    !symbol.is_empty()
+        && !built_in
+        && !symbol.starts_with("//")
+        && symbol
+            .chars()
+            .all(|c| c.is_ascii_punctuation() && !"\"(),;[]{}_".contains(c))
+}
+
+/// The radix of an integer literal starting with the chars first and second,
+/// None if it is a decimal one
+fn radix(first: char, second: char) -> Option<u32> {
+    match (first, second) {
+        ('0', 'x') => Some(16),
+        ('0', 'o') => Some(8),
+        ('0', 'b') => Some(2),
+        _ => None,
+    }
+}
+
+/// The value of an integer literal read by the lexer, an error if it doesn't fit in an
+/// i32 or, for tokens that weren't read by the lexer, isn't a number
+pub(crate) fn integer_value(lexeme: &str) -> Result<i32, ParseIntError> {
+    let mut chars = lexeme.chars();
+    let (radix, digits) = match (chars.next(), chars.next()) {
+        (Some(first), Some(second)) => match radix(first, second) {
+            Some(radix) => (radix, &lexeme[2..]),
+            None => (10, lexeme),
+        },
+        _ => (10, lexeme),
+    };
+    let digits = digits.replace('_', "");
+    i32::from_str_radix(&digits, radix)
+}
+
+/// Length in bytes of the "#!..." line at the start of executable scripts, without
+/// the newline so that line numbers stay correct. 0 if there is none.
+fn shebang_length(input: &str) -> usize {
+    if !input.starts_with("#!") {
+        return 0;
+    }
+    input.find('\n').unwrap_or(input.len())
+}
+
+impl Iterator for Lexer<'_> {
     type Item = Token;
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -254,62 +587,63 @@ impl Iterator for Lexer {
     }
 }
 
-fn initialize_keywords() -> HashMap<String, Keyword> {
+/// Reserved words of the language
+pub const KEYWORDS: [&str; 5] = ["if", "in", "is", "true", "false"];
+
+fn initialize_keywords() -> HashMap<&'static str, TokenType> {
     let mut keywords = HashMap::new();
-    keywords.insert(
-        "if".to_string(),
-        Keyword {
-            token_type: TokenType::If,
-            lexeme: "if",
-        },
-    );
-    keywords.insert(
-        "true".to_string(),
-        Keyword {
-            token_type: TokenType::BooleanLiteral,
-            lexeme: "true",
-        },
-    );
-    keywords.insert(
-        "false".to_string(),
-        Keyword {
-            token_type: TokenType::BooleanLiteral,
-            lexeme: "false",
-        },
-    );
+    keywords.insert("if", TokenType::If);
+    keywords.insert("in", TokenType::In);
+    keywords.insert("is", TokenType::Is);
+    keywords.insert("true", TokenType::BooleanLiteral);
+    keywords.insert("false", TokenType::BooleanLiteral);
     keywords
 }
 
-fn is_alpha(c: char) -> bool {
-    c.is_alphanumeric() || c == '_'
+/// Identifiers follow Unicode's UAX #31 like Rust's, with '_' allowed as the first char
+fn is_identifier_start(c: char) -> bool {
+    unicode_ident::is_xid_start(c) || c == '_'
+}
+
+fn is_identifier_continue(c: char) -> bool {
+    unicode_ident::is_xid_continue(c)
 }
 
 fn is_digit(c: char) -> bool {
-    c.is_digit(10)
+    c.is_ascii_digit()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// The token without its span and column, to compare it with one created by Token::new
+    fn without_span(token: Token) -> Token {
+        Token {
+            column: 0,
+            span: Span::default(),
+            ..token
+        }
+    }
+
     #[test]
     fn peek_token() {
-        let l = Lexer::new(String::from("true false"));
+        let l = Lexer::new("true false");
         let mut p = l.into_iter().peekable();
         assert_eq!(
-            *p.peek().unwrap(),
+            without_span(p.peek().unwrap().clone()),
             Token::new(TokenType::BooleanLiteral, "true".to_string(), 1)
         );
         assert_eq!(
-            p.next().unwrap(),
+            without_span(p.next().unwrap()),
             Token::new(TokenType::BooleanLiteral, "true".to_string(), 1)
         );
         assert_eq!(
-            *p.peek().unwrap(),
+            without_span(p.peek().unwrap().clone()),
             Token::new(TokenType::BooleanLiteral, "false".to_string(), 1)
         );
         assert_eq!(
-            p.next().unwrap(),
+            without_span(p.next().unwrap()),
             Token::new(TokenType::BooleanLiteral, "false".to_string(), 1)
         );
         assert_eq!(p.next(), None)
@@ -317,21 +651,21 @@ mod tests {
 
     #[test]
     fn lex_boolean_expressions() {
-        let mut l = Lexer::new(String::from("true false !true"));
+        let mut l = Lexer::new("true false !true");
         assert_eq!(
-            l.next().unwrap(),
+            without_span(l.next().unwrap()),
             Token::new(TokenType::BooleanLiteral, "true".to_string(), 1)
         );
         assert_eq!(
-            l.next().unwrap(),
+            without_span(l.next().unwrap()),
             Token::new(TokenType::BooleanLiteral, "false".to_string(), 1)
         );
         assert_eq!(
-            l.next().unwrap(),
+            without_span(l.next().unwrap()),
             Token::new(TokenType::Bang, "!".to_string(), 1)
         );
         assert_eq!(
-            l.next().unwrap(),
+            without_span(l.next().unwrap()),
             Token::new(TokenType::BooleanLiteral, "true".to_string(), 1)
         );
         assert_eq!(l.next(), None)
@@ -339,33 +673,33 @@ mod tests {
 
     #[test]
     fn lex_comparison_operators() {
-        let mut l = Lexer::new(String::from("= == != <= >= <>".to_string()));
+        let mut l = Lexer::new("= == != <= >= <>");
         assert_eq!(
-            l.next().unwrap(),
+            without_span(l.next().unwrap()),
             Token::new(TokenType::Assignment, "=".to_string(), 1)
         );
         assert_eq!(
-            l.next().unwrap(),
+            without_span(l.next().unwrap()),
             Token::new(TokenType::Equals, "==".to_string(), 1)
         );
         assert_eq!(
-            l.next().unwrap(),
+            without_span(l.next().unwrap()),
             Token::new(TokenType::BangEquals, "!=".to_string(), 1)
         );
         assert_eq!(
-            l.next().unwrap(),
+            without_span(l.next().unwrap()),
             Token::new(TokenType::SmallerEquals, "<=".to_string(), 1)
         );
         assert_eq!(
-            l.next().unwrap(),
+            without_span(l.next().unwrap()),
             Token::new(TokenType::GreaterEquals, ">=".to_string(), 1)
         );
         assert_eq!(
-            l.next().unwrap(),
+            without_span(l.next().unwrap()),
             Token::new(TokenType::Smaller, "<".to_string(), 1)
         );
         assert_eq!(
-            l.next().unwrap(),
+            without_span(l.next().unwrap()),
             Token::new(TokenType::Greater, ">".to_string(), 1)
         );
         assert_eq!(l.next(), None)
@@ -373,29 +707,29 @@ mod tests {
 
     #[test]
     fn lex_parenthesis() {
-        let mut l = Lexer::new(String::from("({}( ))"));
+        let mut l = Lexer::new("({}( ))");
         assert_eq!(
-            l.next().unwrap(),
+            without_span(l.next().unwrap()),
             Token::new(TokenType::LeftParen, "(".to_string(), 1)
         );
         assert_eq!(
-            l.next().unwrap(),
+            without_span(l.next().unwrap()),
             Token::new(TokenType::LeftBrace, "{".to_string(), 1)
         );
         assert_eq!(
-            l.next().unwrap(),
+            without_span(l.next().unwrap()),
             Token::new(TokenType::RightBrace, "}".to_string(), 1)
         );
         assert_eq!(
-            l.next().unwrap(),
+            without_span(l.next().unwrap()),
             Token::new(TokenType::LeftParen, "(".to_string(), 1)
         );
         assert_eq!(
-            l.next().unwrap(),
+            without_span(l.next().unwrap()),
             Token::new(TokenType::RightParen, ")".to_string(), 1)
         );
         assert_eq!(
-            l.next().unwrap(),
+            without_span(l.next().unwrap()),
             Token::new(TokenType::RightParen, ")".to_string(), 1)
         );
         assert_eq!(l.next(), None)
@@ -403,21 +737,21 @@ mod tests {
 
     #[test]
     fn lex_arithmetic_operators() {
-        let mut l = Lexer::new(String::from(" + - */"));
+        let mut l = Lexer::new(" + - */");
         assert_eq!(
-            l.next().unwrap(),
+            without_span(l.next().unwrap()),
             Token::new(TokenType::Plus, "+".to_string(), 1)
         );
         assert_eq!(
-            l.next().unwrap(),
+            without_span(l.next().unwrap()),
             Token::new(TokenType::Minus, "-".to_string(), 1)
         );
         assert_eq!(
-            l.next().unwrap(),
+            without_span(l.next().unwrap()),
             Token::new(TokenType::Star, "*".to_string(), 1)
         );
         assert_eq!(
-            l.next().unwrap(),
+            without_span(l.next().unwrap()),
             Token::new(TokenType::Slash, "/".to_string(), 1)
         );
         assert_eq!(l.next(), None)
@@ -425,53 +759,142 @@ mod tests {
 
     #[test]
     fn lex_empty_string() {
-        let mut l = Lexer::new(String::from(""));
+        let mut l = Lexer::new("");
         assert_eq!(l.next(), None)
     }
 
     #[test]
     fn lex_whitespace() {
-        let mut l = Lexer::new(String::from("\r \t \n   "));
+        let mut l = Lexer::new("\r \t \n   ");
         assert_eq!(l.next(), None)
     }
 
     #[test]
     fn lex_integral_literals() {
         let input = "5 88989 -2928";
-        let mut l = Lexer::new(String::from(input));
+        let mut l = Lexer::new(input);
         assert_eq!(
-            l.next().unwrap(),
+            without_span(l.next().unwrap()),
             Token::new(TokenType::IntegerLiteral, "5".to_string(), 1)
         );
         assert_eq!(
-            l.next().unwrap(),
+            without_span(l.next().unwrap()),
             Token::new(TokenType::IntegerLiteral, "88989".to_string(), 1)
         );
         assert_eq!(
-            l.next().unwrap(),
+            without_span(l.next().unwrap()),
             Token::new(TokenType::Minus, "-".to_string(), 1)
         );
         assert_eq!(
-            l.next().unwrap(),
+            without_span(l.next().unwrap()),
             Token::new(TokenType::IntegerLiteral, "2928".to_string(), 1)
         );
         assert_eq!(l.next(), None)
     }
 
+    #[test]
+    fn lex_custom_operators() {
+        let operators: Vec<CustomOperator> = ["**", "*~", "<=>"]
+            .iter()
+            .map(|symbol| CustomOperator {
+                symbol: symbol.to_string(),
+                fixity: crate::parsing::Fixity::Infix(1),
+            })
+            .collect();
+        let tokens: Vec<(TokenType, String)> = Lexer::with_operators("a***b <=> *~<=", &operators)
+            .map(|t| (t.token_type, t.lexeme.to_string()))
+            .collect();
+        let expected = [
+            (TokenType::Identifier, "a"),
+            (TokenType::Operator, "**"),
+            (TokenType::Star, "*"),
+            (TokenType::Identifier, "b"),
+            (TokenType::Operator, "<=>"),
+            (TokenType::Operator, "*~"),
+            (TokenType::SmallerEquals, "<="),
+        ];
+        let expected: Vec<(TokenType, String)> = expected
+            .iter()
+            .map(|(t, lexeme)| (t.clone(), lexeme.to_string()))
+            .collect();
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn operator_symbols() {
+        for symbol in &["**", "<>", "~", "|", "=>", "%", "^"] {
+            assert!(is_operator_symbol(symbol), "{}", symbol);
+        }
+        for symbol in &[
+            "", "*", "==", "<=", "!", "x", "+1", "//", "(", "$,", "a b", ":", "[]", "|>",
+        ] {
+            assert!(!is_operator_symbol(symbol), "{}", symbol);
+        }
+    }
+
+    #[test]
+    fn lex_integer_radixes() {
+        let input = "0xFF 0o755 0b1010 1_000_000 0x_f_ 007 0B1";
+        let tokens: Vec<(TokenType, String)> = Lexer::new(input)
+            .map(|t| (t.token_type, t.lexeme.to_string()))
+            .collect();
+        let expected = [
+            (TokenType::IntegerLiteral, "0xFF"),
+            (TokenType::IntegerLiteral, "0o755"),
+            (TokenType::IntegerLiteral, "0b1010"),
+            (TokenType::IntegerLiteral, "1_000_000"),
+            (TokenType::IntegerLiteral, "0x_f_"),
+            (TokenType::IntegerLiteral, "007"),
+            // prefixes are lowercase
+            (TokenType::IntegerLiteral, "0"),
+            (TokenType::Identifier, "B1"),
+        ];
+        let expected: Vec<(TokenType, String)> = expected
+            .iter()
+            .map(|(t, l)| (t.clone(), l.to_string()))
+            .collect();
+        assert_eq!(tokens, expected);
+
+        let values: Vec<Option<i32>> = ["0xFF", "0o755", "0b1010", "1_000_000", "0x7fff_ffff"]
+            .iter()
+            .map(|l| integer_value(l).ok())
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                Some(255),
+                Some(493),
+                Some(10),
+                Some(1_000_000),
+                Some(i32::MAX)
+            ]
+        );
+        assert!(integer_value("0x8000_0000").is_err());
+    }
+
+    #[test]
+    fn lex_malformed_integers() {
+        for input in &["0x", "0x_", "0b102", "0o8", "0xfg", "1_"] {
+            let error = tokenize(input).unwrap_err();
+            assert_eq!(error.code(), "E0022", "{}", input);
+            assert_eq!(&*error.token().lexeme, *input);
+        }
+    }
+
     #[test]
     fn lex_identifiers() {
         let input = "_x x_x_x78 Yh0A99";
-        let mut l = Lexer::new(String::from(input.to_string()));
+        let mut l = Lexer::new(input);
         assert_eq!(
-            l.next_token(),
+            without_span(l.next_token()),
             Token::new(TokenType::Identifier, "_x".to_string(), 1)
         );
         assert_eq!(
-            l.next_token(),
+            without_span(l.next_token()),
             Token::new(TokenType::Identifier, "x_x_x78".to_string(), 1)
         );
         assert_eq!(
-            l.next_token(),
+            without_span(l.next_token()),
             Token::new(TokenType::Identifier, "Yh0A99".to_string(), 1)
         );
         assert_eq!(l.next(), None)
@@ -479,48 +902,277 @@ mod tests {
 
     #[test]
     fn lex_invalid_tokens() {
-        let mut l = Lexer::new(String::from("#"));
+        let mut l = Lexer::new("#");
         assert_eq!(
-            l.next_token(),
+            without_span(l.next_token()),
             Token::new(TokenType::InvalidToken, "#".to_string(), 1)
         );
         assert_eq!(l.next(), None)
     }
 
+    #[test]
+    fn tokenize_input() {
+        let types: Vec<TokenType> = tokenize("x = \"a\";")
+            .unwrap()
+            .into_iter()
+            .map(|t| t.token_type)
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                TokenType::Identifier,
+                TokenType::Assignment,
+                TokenType::StringLiteral,
+                TokenType::Semicolon
+            ]
+        );
+        assert_eq!(tokenize("").unwrap(), vec![]);
+
+        let error = tokenize("1 +\n #").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "LexError at line 2: Unexpected character '#'. [E0020]"
+        );
+        assert_eq!(error.token().span, Span::new(5, 6));
+        let error = tokenize("1;\0 exit(1)").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "LexError at line 1: Unexpected character '\\u{0}'. [E0020]"
+        );
+        let error = tokenize("print(\"abc").unwrap_err();
+        assert_eq!(error.code(), "E0021");
+        assert_eq!(&*error.token().lexeme, "\"abc");
+    }
+
     #[test]
     fn lex_strings() {
-        let mut l = Lexer::new(String::from("\"bla bla bla\"  "));
+        let mut l = Lexer::new("\"bla bla bla\"  ");
         assert_eq!(
-            l.next_token(),
+            without_span(l.next_token()),
             Token::new(TokenType::StringLiteral, "bla bla bla".to_string(), 1)
         );
         assert_eq!(l.next(), None)
     }
 
+    #[test]
+    fn lex_empty_and_unterminated_strings() {
+        let mut l = Lexer::new("\"\" \"abc");
+        assert_eq!(
+            without_span(l.next_token()),
+            Token::new(TokenType::StringLiteral, "".to_string(), 1)
+        );
+        assert_eq!(
+            without_span(l.next_token()),
+            Token::new(TokenType::InvalidToken, "\"abc".to_string(), 1)
+        );
+        assert_eq!(l.next(), None)
+    }
+
     #[test]
     fn lex_if() {
-        let mut l = Lexer::new(String::from("if"));
+        let mut l = Lexer::new("if");
         assert_eq!(
-            l.next_token(),
+            without_span(l.next_token()),
             Token::new(TokenType::If, "if".to_string(), 1)
         );
         assert_eq!(l.next(), None)
     }
 
+    #[test]
+    fn lex_in() {
+        let tokens: Vec<TokenType> = Lexer::new("x in index").map(|t| t.token_type).collect();
+        assert_eq!(
+            tokens,
+            [TokenType::Identifier, TokenType::In, TokenType::Identifier]
+        );
+    }
+
+    #[test]
+    fn lex_pipe() {
+        let tokens: Vec<TokenType> = Lexer::new("x |> f |").map(|t| t.token_type).collect();
+        assert_eq!(
+            tokens,
+            [
+                TokenType::Identifier,
+                TokenType::Pipe,
+                TokenType::Identifier,
+                TokenType::InvalidToken
+            ]
+        );
+    }
+
     #[test]
     fn lex_semicolon() {
-        let mut l = Lexer::new(String::from("47;"));
+        let mut l = Lexer::new("47;");
         assert_eq!(
-            l.next_token(),
+            without_span(l.next_token()),
             Token::new(TokenType::IntegerLiteral, "47".to_string(), 1)
         );
         assert_eq!(
-            l.next_token(),
+            without_span(l.next_token()),
             Token::new(TokenType::Semicolon, ";".to_string(), 1)
         );
         assert_eq!(l.next(), None)
     }
 
+    #[test]
+    fn lex_comments() {
+        let input = "// start\nx = 1 // one\n//\n/ 2 //";
+        let types: Vec<TokenType> = Lexer::new(input).map(|t| t.token_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                TokenType::Identifier,
+                TokenType::Assignment,
+                TokenType::IntegerLiteral,
+                TokenType::Slash,
+                TokenType::IntegerLiteral
+            ]
+        );
+
+        let comments: Vec<(String, i32, Span)> = Lexer::with_comments(input)
+            .filter(|t| t.token_type == TokenType::Comment)
+            .map(|t| (t.lexeme.to_string(), t.line, t.span))
+            .collect();
+        assert_eq!(
+            comments,
+            vec![
+                (String::from("// start"), 1, Span::new(0, 8)),
+                (String::from("// one"), 2, Span::new(15, 21)),
+                (String::from("//"), 3, Span::new(22, 24)),
+                (String::from("//"), 4, Span::new(29, 31)),
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_shebang() {
+        let mut l = Lexer::new("#!/usr/bin/env sapo\n1");
+        assert_eq!(
+            without_span(l.next_token()),
+            Token::new(TokenType::IntegerLiteral, "1".to_string(), 2)
+        );
+        assert_eq!(l.next(), None);
+
+        let mut l = Lexer::new("#!/usr/bin/env sapo");
+        assert_eq!(l.next(), None);
+    }
+
+    #[test]
+    fn tokens_share_lexemes() {
+        let tokens: Vec<Token> = Lexer::new("x + x + \"x\"").collect();
+        assert!(Arc::ptr_eq(&tokens[0].lexeme, &tokens[2].lexeme));
+        assert!(Arc::ptr_eq(&tokens[1].lexeme, &tokens[3].lexeme));
+        assert!(Arc::ptr_eq(&tokens[0].lexeme, &tokens[4].lexeme));
+    }
+
+    #[test]
+    fn token_spans() {
+        let spans: Vec<Span> = Lexer::new("ñandú = \"a\"; 42").map(|t| t.span).collect();
+        let expected = [(0, 7), (8, 9), (10, 13), (13, 14), (15, 17)];
+        let expected: Vec<Span> = expected.iter().map(|&(s, e)| Span::new(s, e)).collect();
+        assert_eq!(spans, expected);
+    }
+
+    #[test]
+    fn lex_multibyte_characters() {
+        let mut l = Lexer::new("ñandú = \"🐸 sapo\"\n€");
+        assert_eq!(
+            without_span(l.next_token()),
+            Token::new(TokenType::Identifier, "ñandú".to_string(), 1)
+        );
+        assert_eq!(
+            without_span(l.next_token()),
+            Token::new(TokenType::Assignment, "=".to_string(), 1)
+        );
+        assert_eq!(
+            without_span(l.next_token()),
+            Token::new(TokenType::StringLiteral, "🐸 sapo".to_string(), 1)
+        );
+        assert_eq!(
+            without_span(l.next_token()),
+            Token::new(TokenType::InvalidToken, "€".to_string(), 2)
+        );
+        assert_eq!(l.next(), None)
+    }
+
+    #[test]
+    fn lex_unicode_identifiers() {
+        let lexemes: Vec<(TokenType, String)> = Lexer::new("_ñ1 x٣ ٣x 𝑥 a€")
+            .map(|t| (t.token_type, t.lexeme.to_string()))
+            .collect();
+        let expected = [
+            (TokenType::Identifier, "_ñ1"),
+            (TokenType::Identifier, "x٣"),
+            // digits of other scripts can't start identifiers either
+            (TokenType::InvalidToken, "٣"),
+            (TokenType::Identifier, "x"),
+            (TokenType::Identifier, "𝑥"),
+            (TokenType::Identifier, "a"),
+            (TokenType::InvalidToken, "€"),
+        ];
+        let expected: Vec<(TokenType, String)> = expected
+            .iter()
+            .map(|(t, l)| (t.clone(), l.to_string()))
+            .collect();
+        assert_eq!(lexemes, expected);
+    }
+
+    #[test]
+    fn lines_and_columns() {
+        let input = "x = 1;\r\nñandú=\"a\nb\"\r🐸  y\n\t z";
+        let positions: Vec<(String, i32, usize)> = Lexer::new(input)
+            .map(|t| (t.lexeme.to_string(), t.line, t.column))
+            .collect();
+        let expected = [
+            ("x", 1, 1),
+            ("=", 1, 3),
+            ("1", 1, 5),
+            (";", 1, 6),
+            ("ñandú", 2, 1),
+            ("=", 2, 6),
+            ("a\nb", 2, 7),
+            ("🐸", 4, 1),
+            ("y", 4, 4),
+            ("z", 5, 3),
+        ];
+        let expected: Vec<(String, i32, usize)> = expected
+            .iter()
+            .map(|&(l, line, column)| (l.to_string(), line, column))
+            .collect();
+        assert_eq!(positions, expected);
+    }
+
+    #[test]
+    fn lex_comma() {
+        let mut l = Lexer::new("f(1, 2)");
+        assert_eq!(
+            without_span(l.next_token()),
+            Token::new(TokenType::Identifier, "f".to_string(), 1)
+        );
+        assert_eq!(
+            without_span(l.next_token()),
+            Token::new(TokenType::LeftParen, "(".to_string(), 1)
+        );
+        assert_eq!(
+            without_span(l.next_token()),
+            Token::new(TokenType::IntegerLiteral, "1".to_string(), 1)
+        );
+        assert_eq!(
+            without_span(l.next_token()),
+            Token::new(TokenType::Comma, ",".to_string(), 1)
+        );
+        assert_eq!(
+            without_span(l.next_token()),
+            Token::new(TokenType::IntegerLiteral, "2".to_string(), 1)
+        );
+        assert_eq!(
+            without_span(l.next_token()),
+            Token::new(TokenType::RightParen, ")".to_string(), 1)
+        );
+        assert_eq!(l.next(), None)
+    }
+
     #[test]
     fn lex_combined() {
         let input = r#"
@@ -532,7 +1184,7 @@ mod tests {
 
         "#;
 
-        let mut l = Lexer::new(input.to_string());
+        let mut l = Lexer::new(input);
 
         let expected_tokens = [
             Token::new(TokenType::Identifier, "x".to_string(), 2),
@@ -553,7 +1205,7 @@ mod tests {
         ];
 
         for expected in expected_tokens.iter() {
-            let actual = l.next_token();
+            let actual = without_span(l.next_token());
             assert_eq!(expected, &actual);
         }
         assert_eq!(l.next(), None)
@@ -1,4 +1,8 @@
+mod incremental;
 mod lexer;
 mod parser;
 
+pub use incremental::Document;
+pub(crate) use lexer::is_operator_symbol;
+pub use lexer::{tokenize, LexError, Lexer, KEYWORDS};
 pub use parser::*;
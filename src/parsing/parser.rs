@@ -1,34 +1,138 @@
-use super::lexer::Lexer;
+use super::lexer::{integer_value, LexError, Lexer};
 use crate::ast;
-use crate::token::{Token, TokenType};
+use crate::ast::{Ast, ExprId};
+use crate::interner::Symbol;
+use crate::token::{Span, Token, TokenType};
 use std::fmt;
 use std::iter::Peekable;
+use std::num::IntErrorKind;
 
 #[derive(Debug)]
 pub enum ParseError {
     MissingBrace(Token),
+    MissingBracket(Token),
     MissingExpression(Token),
+    MissingTypeName(Token),
+    MissingFunctionName(Token),
+    UnexpectedToken(Token),
+    InvalidAssignmentTarget(Token),
+    MissingSemicolon(Token),
+    NestingTooDeep(Token),
+    IntegerTooLarge(Token),
+    /// A literal token whose text isn't a literal of its type, only possible with
+    /// tokens passed to parse_tokens
+    InvalidLiteral(Token),
+    /// Text that isn't a token was found where the parser expected one
+    Lex(LexError),
 }
 
-impl fmt::Display for ParseError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl ParseError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseError::MissingBrace(_) => "E0001",
+            ParseError::MissingBracket(_) => "E0026",
+            ParseError::MissingExpression(_) => "E0002",
+            ParseError::MissingTypeName(_) => "E0028",
+            ParseError::MissingFunctionName(_) => "E0034",
+            ParseError::UnexpectedToken(_) => "E0007",
+            ParseError::InvalidAssignmentTarget(_) => "E0008",
+            ParseError::MissingSemicolon(_) => "E0009",
+            ParseError::NestingTooDeep(_) => "E0014",
+            ParseError::IntegerTooLarge(_) => "E0018",
+            ParseError::InvalidLiteral(_) => "E0036",
+            ParseError::Lex(error) => error.code(),
+        }
+    }
+
+    /// The token at which the error was detected
+    pub fn token(&self) -> &Token {
+        match self {
+            ParseError::MissingBrace(t)
+            | ParseError::MissingBracket(t)
+            | ParseError::MissingExpression(t)
+            | ParseError::MissingTypeName(t)
+            | ParseError::MissingFunctionName(t)
+            | ParseError::UnexpectedToken(t)
+            | ParseError::InvalidAssignmentTarget(t)
+            | ParseError::MissingSemicolon(t)
+            | ParseError::NestingTooDeep(t)
+            | ParseError::IntegerTooLarge(t)
+            | ParseError::InvalidLiteral(t) => t,
+            ParseError::Lex(error) => error.token(),
+        }
+    }
+
+    pub(crate) fn token_mut(&mut self) -> &mut Token {
+        match self {
+            ParseError::MissingBrace(t)
+            | ParseError::MissingBracket(t)
+            | ParseError::MissingExpression(t)
+            | ParseError::MissingTypeName(t)
+            | ParseError::MissingFunctionName(t)
+            | ParseError::UnexpectedToken(t)
+            | ParseError::InvalidAssignmentTarget(t)
+            | ParseError::MissingSemicolon(t)
+            | ParseError::NestingTooDeep(t)
+            | ParseError::IntegerTooLarge(t)
+            | ParseError::InvalidLiteral(t) => t,
+            ParseError::Lex(error) => error.token_mut(),
+        }
+    }
+
+    /// Description of the error without location or code
+    pub fn message(&self) -> String {
         match self {
-            ParseError::MissingBrace(t) => write!(
-                f,
-                "ParseError at {}: Expected ')', but '{}' was found.",
-                &get_location_of_error(t),
-                t.lexeme
+            ParseError::MissingBrace(t) => format!("Expected ')', but '{}' was found.", t.lexeme),
+            ParseError::MissingBracket(t) => format!("Expected ']', but '{}' was found.", t.lexeme),
+            ParseError::MissingExpression(t) => {
+                format!("Expected expression, but '{}' was found.", t.lexeme)
+            }
+            ParseError::MissingTypeName(t) => {
+                format!("Expected a type name, but '{}' was found.", t.lexeme)
+            }
+            ParseError::MissingFunctionName(t) => {
+                format!("Expected a function name, but '{}' was found.", t.lexeme)
+            }
+            ParseError::UnexpectedToken(t) => {
+                format!("Expected end of input, but '{}' was found.", t.lexeme)
+            }
+            ParseError::InvalidAssignmentTarget(_) => {
+                String::from("Invalid assignment target, expected a variable.")
+            }
+            ParseError::MissingSemicolon(t) => {
+                format!("Expected ';', but '{}' was found.", t.lexeme)
+            }
+            ParseError::NestingTooDeep(_) => String::from("Expression is nested too deeply."),
+            ParseError::IntegerTooLarge(t) => format!(
+                "Integer literal '{}' is too large, the maximum is {}.",
+                t.lexeme,
+                i32::MAX
             ),
-            ParseError::MissingExpression(t) => write!(
-                f,
-                "ParseError at {}: Expected expression, but '{}' was found.",
-                get_location_of_error(&t),
-                t.lexeme
+            ParseError::InvalidLiteral(t) => format!(
+                "'{}' is not a valid {}.",
+                t.lexeme,
+                match t.token_type {
+                    TokenType::IntegerLiteral => "Integer literal",
+                    _ => "Boolean literal",
+                }
             ),
+            ParseError::Lex(error) => error.message(),
         }
     }
 }
 
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ParseError at {}: {} [{}]",
+            get_location_of_error(self.token()),
+            self.message(),
+            self.code()
+        )
+    }
+}
+
 fn get_location_of_error(token: &Token) -> String {
     if token.token_type == TokenType::EOF {
         String::from("end of file")
@@ -37,114 +141,731 @@ fn get_location_of_error(token: &Token) -> String {
     }
 }
 
-type ParsedExpressionResult = Result<Box<ast::Expression>, ParseError>;
+/// The tree of the expression and its root
+type ParsedExpressionResult = Result<(Ast, ExprId), ParseError>;
 
-pub fn parse(input: String) -> ParsedExpressionResult {
-    parse_expression(&mut Lexer::new(input).peekable())
+/// How deep the tree of an expression can be by default, e.g. "-(1 + 2)" has a depth of 3
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    /// Deeper expressions are rejected with an error, so that evaluating or
+    /// printing them can't overflow the stack
+    pub max_depth: usize,
+    /// Operators defined by the application embedding sapo, in addition to the built-in ones
+    pub operators: Vec<CustomOperator>,
 }
 
-fn parse_expression<I>(tokens: &mut Peekable<I>) -> ParsedExpressionResult
-where
-    I: Iterator<Item = Token>,
-{
-    parse_equality(tokens)
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            max_depth: DEFAULT_MAX_DEPTH,
+            operators: Vec::new(),
+        }
+    }
 }
 
-fn parse_equality<I>(tokens: &mut Peekable<I>) -> ParsedExpressionResult
-where
-    I: Iterator<Item = Token>,
-{
-    const EQUALITY_TOKENS: [TokenType; 2] = [TokenType::Equals, TokenType::BangEquals];
-    let mut left = parse_comparison(tokens)?;
-    while let Some(token) = match_token(tokens, &EQUALITY_TOKENS) {
-        let right = parse_comparison(tokens)?;
-        left = Box::new(ast::Expression::BinaryExpression { token, left, right });
+/// An operator defined by the application embedding sapo, parsed into a unary or binary
+/// expression whose token is an Operator. See Environment::register_operator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomOperator {
+    /// Punctuation that isn't a built-in operator, e.g. "**" or "<>"
+    pub symbol: String,
+    pub fixity: Fixity,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fixity {
+    /// Written before its operand, it binds as tight as the built-in unary operators
+    Prefix,
+    /// Written between its operands, with the given precedence. It is left associative,
+    /// 1 binds as tight as '==' and 4 as tight as '*', higher values bind tighter than
+    /// any built-in binary operator but still looser than prefix operators.
+    Infix(u8),
+}
+
+pub fn parse(input: &str) -> ParsedExpressionResult {
+    parse_with_options(input, &ParseOptions::default())
+}
+
+pub fn parse_with_options(input: &str, options: &ParseOptions) -> ParsedExpressionResult {
+    let mut tokens = Lexer::with_operators(input, &options.operators).peekable();
+    let mut ast = Ast::new();
+    let root = parse_expression(&mut tokens, &mut ast, options)?;
+    if let Some(token) = tokens.next() {
+        return Err(unexpected(token, ParseError::UnexpectedToken));
+    }
+    Ok((ast, root))
+}
+
+/// Parses a sequence of expressions separated by ';', the last ';' is optional
+pub fn parse_program(input: &str) -> Result<ast::Program, ParseError> {
+    parse_program_with_options(input, &ParseOptions::default())
+}
+
+/// Same as parse, for callers written when it took ownership of the input
+#[deprecated(since = "0.1.0", note = "use parse, which borrows the input")]
+pub fn parse_string(input: String) -> ParsedExpressionResult {
+    parse(&input)
+}
+
+/// Same as parse_program, for callers written when it took ownership of the input
+#[deprecated(since = "0.1.0", note = "use parse_program, which borrows the input")]
+pub fn parse_program_string(input: String) -> Result<ast::Program, ParseError> {
+    parse_program(&input)
+}
+
+pub fn parse_program_with_options(
+    input: &str,
+    options: &ParseOptions,
+) -> Result<ast::Program, ParseError> {
+    parse_tokens_with_options(Lexer::with_operators(input, &options.operators), options)
+}
+
+/// Parses a program from tokens that don't need to come from the lexer, e.g. tokens
+/// synthesized by a tool. Comments are skipped, the stream ends without an EOF token.
+pub fn parse_tokens(tokens: impl Iterator<Item = Token>) -> Result<ast::Program, ParseError> {
+    parse_tokens_with_options(tokens, &ParseOptions::default())
+}
+
+pub fn parse_tokens_with_options(
+    tokens: impl Iterator<Item = Token>,
+    options: &ParseOptions,
+) -> Result<ast::Program, ParseError> {
+    let mut tokens = tokens
+        .filter(|t| t.token_type != TokenType::Comment)
+        .peekable();
+    let mut program = ast::Program::default();
+    while tokens.peek().is_some() {
+        let statement = parse_expression(&mut tokens, &mut program.ast, options)?;
+        program.statements.push(statement);
+        if match_token(&mut tokens, &[TokenType::Semicolon]).is_none() && tokens.peek().is_some() {
+            let token = next_token(&mut tokens);
+            return Err(unexpected(token, ParseError::MissingSemicolon));
+        }
+    }
+    Ok(program)
+}
+
+/// Parses a program even if it has syntax errors, for tools like editors and linters that
+/// work on the rest of it. Each statement that can't be parsed becomes an Expression::Error
+/// and parsing continues after the ';' ending it. Returns the errors in source order.
+pub fn parse_program_with_recovery(
+    input: &str,
+    options: &ParseOptions,
+) -> (ast::Program, Vec<ParseError>) {
+    let mut tokens = Lexer::with_operators(input, &options.operators).peekable();
+    let mut program = ast::Program::default();
+    let mut errors = Vec::new();
+    while tokens.peek().is_some() {
+        let statement =
+            parse_statement_with_recovery(&mut tokens, input, &mut program.ast, options);
+        program.statements.push(statement.id);
+        errors.extend(statement.error);
     }
-    Ok(left)
+    (program, errors)
 }
 
-fn parse_comparison<I>(tokens: &mut Peekable<I>) -> ParsedExpressionResult
+/// A statement parsed by parse_statement_with_recovery
+pub(crate) struct RecoveredStatement {
+    pub(crate) id: ExprId,
+    /// Why the statement is an Expression::Error
+    pub(crate) error: Option<ParseError>,
+    /// The ';' ending the statement, None for the last one if it has none
+    pub(crate) separator: Option<Token>,
+}
+
+/// Parses the statement the tokens of input start with, and the ';' ending it
+pub(crate) fn parse_statement_with_recovery<I>(
+    tokens: &mut Peekable<I>,
+    input: &str,
+    ast: &mut Ast,
+    options: &ParseOptions,
+) -> RecoveredStatement
 where
     I: Iterator<Item = Token>,
 {
-    const COMPARISON_TOKENS: [TokenType; 4] = [
-        TokenType::Greater,
-        TokenType::GreaterEquals,
-        TokenType::Smaller,
-        TokenType::SmallerEquals,
-    ];
-    let mut left = parse_term(tokens)?;
-    while let Some(token) = match_token(tokens, &COMPARISON_TOKENS) {
-        let right = parse_term(tokens)?;
-        left = Box::new(ast::Expression::BinaryExpression { token, left, right });
+    let mut statement = Vec::new();
+    while let Some(token) = tokens.next_if(|t| t.token_type != TokenType::Semicolon) {
+        statement.push(token);
+    }
+    let separator = match_token(tokens, &[TokenType::Semicolon]);
+    let len = ast.len();
+    // errors at the end of the statement are found at the ';', like when parsing strictly
+    let mut statement_tokens = statement.iter().chain(&separator).cloned().peekable();
+    let parsed =
+        parse_expression(&mut statement_tokens, ast, options).and_then(|id| match statement_tokens
+            .next()
+        {
+            Some(token) if token.token_type != TokenType::Semicolon => {
+                Err(unexpected(token, ParseError::MissingSemicolon))
+            }
+            _ => Ok(id),
+        });
+    let error = match parsed {
+        Ok(id) => {
+            return RecoveredStatement {
+                id,
+                error: None,
+                separator,
+            }
+        }
+        Err(error) => error,
+    };
+    // the expressions parsed before the error aren't part of the tree
+    ast.truncate(len);
+    let span = match (statement.first(), statement.last(), &separator) {
+        (Some(first), Some(last), _) => first.span.merge(last.span),
+        (None, _, Some(separator)) => Span::new(separator.span.start, separator.span.start),
+        _ => error.token().span,
+    };
+    let expression = ast::Expression::Error {
+        token: error.token().clone(),
+        source: input[span.start..span.end].to_string(),
+    };
+    RecoveredStatement {
+        id: ast.add_with_span(expression, span),
+        error: Some(error),
+        separator,
     }
-    Ok(left)
 }
 
-fn parse_term<I>(tokens: &mut Peekable<I>) -> ParsedExpressionResult
+/// An operation waiting for one of its operands while parsing an expression
+enum Frame {
+    /// A unary operator waiting for its operand
+    Unary(Token),
+    /// The left operand of a binary operator and its precedence, waiting for the right operand
+    Binary(Operand, Token, u8),
+    /// The target and the '=' of an assignment, waiting for the value
+    Assignment(Operand, Token),
+    /// A '(' waiting for the expression inside and the closing ')'
+    Grouping(Token),
+    /// The name of a function and the arguments parsed so far, with the depth of the deepest one
+    Call(Token, Vec<ExprId>, usize),
+    /// The '(' or the first ',' of a tuple, its elements parsed so far with the depth of the
+    /// deepest one and whether it is in parentheses, waiting for the next element
+    Tuple(Token, Vec<ExprId>, usize, bool),
+    /// The target and the '[' of an indexing, waiting for the index or the start of a slice
+    Index(Operand, Token),
+    /// The target, the '[' and the start of a slice, waiting for its end
+    Slice(Operand, Token, Option<Operand>),
+}
+
+/// A parsed expression and the depth of its tree, literals and variables have a depth of 0
+struct Operand {
+    expression: ExprId,
+    depth: usize,
+}
+
+/// Parses an expression with a Pratt parser that keeps an explicit stack of operations
+/// instead of recursing, so that no input can overflow the native stack. Indexing binds
+/// the tightest, then prefix operators bind tighter than any binary operator, binary operators are applied according to their
+/// precedence in BINARY_OPERATORS (and 'is' like the comparisons), the ',' of tuples without parentheses binds looser
+/// (they are only allowed in statements and assignments) and assignments bind the loosest
+/// (they are right associative and the target must be a variable or a tuple of them).
+fn parse_expression<I>(
+    tokens: &mut Peekable<I>,
+    ast: &mut Ast,
+    options: &ParseOptions,
+) -> Result<ExprId, ParseError>
 where
     I: Iterator<Item = Token>,
 {
-    const TERM_TOKENS: [TokenType; 2] = [TokenType::Minus, TokenType::Plus];
-    let mut left = parse_factor(tokens)?;
-    while let Some(token) = match_token(tokens, &TERM_TOKENS) {
-        let right = parse_factor(tokens)?;
-        left = Box::new(ast::Expression::BinaryExpression { token, left, right });
+    let max_depth = options.max_depth;
+    let mut stack = Vec::new();
+    'operand: loop {
+        // prefix operators, groupings and calls are pushed until an operand is found
+        let mut operand = loop {
+            if let Some(token) = match_prefix_operator(tokens, options) {
+                // stuff like !! and even -- is allowed by the grammar...
+                stack.push(Frame::Unary(token));
+            } else if let Some(token) = match_token(tokens, &[TokenType::LeftParen]) {
+                stack.push(Frame::Grouping(token));
+            } else if let Some(token) = match_token(tokens, &[TokenType::Identifier]) {
+                let name = Symbol::intern(&token.lexeme);
+                if match_token(tokens, &[TokenType::LeftParen]).is_none() {
+                    break leaf(ast, ast::Expression::Variable { token, name });
+                }
+                if let Some(closing) = match_token(tokens, &[TokenType::RightParen]) {
+                    let arguments = Vec::new();
+                    let call = leaf(
+                        ast,
+                        ast::Expression::Call {
+                            token,
+                            name,
+                            arguments,
+                        },
+                    );
+                    ast.cover(call.expression, closing.span);
+                    break call;
+                }
+                stack.push(Frame::Call(token, Vec::new(), 0));
+            } else {
+                break leaf(ast, parse_literal(tokens)?);
+            }
+        };
+
+        // apply the operations waiting for the operand, as long as the next token allows it
+        loop {
+            if let Some(token) = match_token(tokens, &[TokenType::LeftBracket]) {
+                if match_token(tokens, &[TokenType::Colon]).is_none() {
+                    stack.push(Frame::Index(operand, token));
+                    continue 'operand;
+                }
+                match match_token(tokens, &[TokenType::RightBracket]) {
+                    Some(closing) => {
+                        operand = slice(ast, operand, token, None, None, closing, max_depth)?;
+                        continue;
+                    }
+                    None => {
+                        stack.push(Frame::Slice(operand, token, None));
+                        continue 'operand;
+                    }
+                }
+            }
+            while let Some(Frame::Unary(_)) = stack.last() {
+                if let Some(Frame::Unary(token)) = stack.pop() {
+                    let depth = operand.depth;
+                    let right = operand.expression;
+                    operand = nest(
+                        ast,
+                        ast::Expression::UnaryExpression { token, right },
+                        depth,
+                        max_depth,
+                    )?;
+                }
+            }
+            if let Some(token) = match_token(tokens, &[TokenType::Is]) {
+                operand = reduce_binary(ast, &mut stack, operand, TYPE_TEST_PRECEDENCE, max_depth)?;
+                operand = type_test(tokens, ast, operand, token, max_depth)?;
+                continue;
+            }
+            // x |> f(y) is f(x, y), x being everything on the left up to an enclosing
+            // grouping, call, indexing or assignment
+            if match_token(tokens, &[TokenType::Pipe]).is_some() {
+                operand = reduce_binary(ast, &mut stack, operand, 0, max_depth)?;
+                let token = match match_token(tokens, &[TokenType::Identifier]) {
+                    Some(token) => token,
+                    None => {
+                        let token = next_token(tokens);
+                        return Err(unexpected(token, ParseError::MissingFunctionName));
+                    }
+                };
+                let arguments = vec![operand.expression];
+                let mut closing = None;
+                if match_token(tokens, &[TokenType::LeftParen]).is_some() {
+                    closing = match_token(tokens, &[TokenType::RightParen]);
+                    if closing.is_none() {
+                        stack.push(Frame::Call(token, arguments, operand.depth));
+                        continue 'operand;
+                    }
+                }
+                let name = Symbol::intern(&token.lexeme);
+                let call = ast::Expression::Call {
+                    token,
+                    name,
+                    arguments,
+                };
+                operand = nest(ast, call, operand.depth, max_depth)?;
+                if let Some(closing) = closing {
+                    ast.cover(operand.expression, closing.span);
+                }
+                continue;
+            }
+            if let Some((token, precedence)) = match_binary_operator(tokens, options) {
+                operand = reduce_binary(ast, &mut stack, operand, precedence, max_depth)?;
+                stack.push(Frame::Binary(operand, token, precedence));
+                continue 'operand;
+            }
+            operand = reduce_binary(ast, &mut stack, operand, 0, max_depth)?;
+            // a, b is a tuple outside of groupings, calls and indexings
+            let in_statement = stack.iter().all(|frame| {
+                matches!(
+                    frame,
+                    Frame::Assignment(_, _) | Frame::Tuple(_, _, _, false)
+                )
+            });
+            if in_statement {
+                if let Some(comma) = match_token(tokens, &[TokenType::Comma]) {
+                    match stack.last_mut() {
+                        Some(Frame::Tuple(_, elements, depth, false)) => {
+                            elements.push(operand.expression);
+                            *depth = (*depth).max(operand.depth);
+                        }
+                        _ => stack.push(Frame::Tuple(
+                            comma,
+                            vec![operand.expression],
+                            operand.depth,
+                            false,
+                        )),
+                    }
+                    continue 'operand;
+                }
+            }
+            if let Some(Frame::Tuple(_, _, _, false)) = stack.last() {
+                if let Some(Frame::Tuple(token, mut elements, depth, _)) = stack.pop() {
+                    let depth = depth.max(operand.depth);
+                    elements.push(operand.expression);
+                    let tuple = ast::Expression::Tuple { token, elements };
+                    operand = nest(ast, tuple, depth, max_depth)?;
+                }
+            }
+            if let Some(token) = match_token(tokens, &[TokenType::Assignment]) {
+                stack.push(Frame::Assignment(operand, token));
+                continue 'operand;
+            }
+            while let Some(Frame::Assignment(_, _)) = stack.last() {
+                if let Some(Frame::Assignment(target, token)) = stack.pop() {
+                    operand = assignment(ast, target, token, operand, max_depth)?;
+                }
+            }
+            match stack.pop() {
+                None => return Ok(operand.expression),
+                // (a, ...) is a tuple
+                Some(Frame::Grouping(token))
+                    if match_token(tokens, &[TokenType::Comma]).is_some() =>
+                {
+                    let elements = vec![operand.expression];
+                    match match_token(tokens, &[TokenType::RightParen]) {
+                        Some(closing) => {
+                            let tuple = ast::Expression::Tuple { token, elements };
+                            operand = nest(ast, tuple, operand.depth, max_depth)?;
+                            ast.cover(operand.expression, closing.span);
+                        }
+                        None => {
+                            stack.push(Frame::Tuple(token, elements, operand.depth, true));
+                            continue 'operand;
+                        }
+                    }
+                }
+                Some(Frame::Tuple(token, mut elements, depth, _)) => {
+                    let depth = depth.max(operand.depth);
+                    elements.push(operand.expression);
+                    // a trailing ',' is allowed
+                    let comma = match_token(tokens, &[TokenType::Comma]).is_some();
+                    let closing = match match_token(tokens, &[TokenType::RightParen]) {
+                        Some(closing) => closing,
+                        None if comma => {
+                            stack.push(Frame::Tuple(token, elements, depth, true));
+                            continue 'operand;
+                        }
+                        None => {
+                            let token = next_token(tokens);
+                            return Err(unexpected(token, ParseError::MissingBrace));
+                        }
+                    };
+                    let tuple = ast::Expression::Tuple { token, elements };
+                    operand = nest(ast, tuple, depth, max_depth)?;
+                    ast.cover(operand.expression, closing.span);
+                }
+                Some(Frame::Grouping(token)) => {
+                    let closing = match match_token(tokens, &[TokenType::RightParen]) {
+                        Some(closing) => closing,
+                        None => {
+                            let token = next_token(tokens);
+                            return Err(unexpected(token, ParseError::MissingBrace));
+                        }
+                    };
+                    let depth = operand.depth;
+                    let expr = operand.expression;
+                    operand = nest(
+                        ast,
+                        ast::Expression::Grouping { token, expr },
+                        depth,
+                        max_depth,
+                    )?;
+                    ast.cover(operand.expression, closing.span);
+                }
+                Some(Frame::Call(token, mut arguments, depth)) => {
+                    let depth = depth.max(operand.depth);
+                    arguments.push(operand.expression);
+                    if match_token(tokens, &[TokenType::Comma]).is_some() {
+                        stack.push(Frame::Call(token, arguments, depth));
+                        continue 'operand;
+                    }
+                    let closing = match match_token(tokens, &[TokenType::RightParen]) {
+                        Some(closing) => closing,
+                        None => {
+                            let token = next_token(tokens);
+                            return Err(unexpected(token, ParseError::MissingBrace));
+                        }
+                    };
+                    let name = Symbol::intern(&token.lexeme);
+                    let call = ast::Expression::Call {
+                        token,
+                        name,
+                        arguments,
+                    };
+                    operand = nest(ast, call, depth, max_depth)?;
+                    ast.cover(operand.expression, closing.span);
+                }
+                Some(Frame::Index(target, token)) => {
+                    if match_token(tokens, &[TokenType::Colon]).is_some() {
+                        let closing = match match_token(tokens, &[TokenType::RightBracket]) {
+                            Some(closing) => closing,
+                            None => {
+                                stack.push(Frame::Slice(target, token, Some(operand)));
+                                continue 'operand;
+                            }
+                        };
+                        let start = Some(operand);
+                        operand = slice(ast, target, token, start, None, closing, max_depth)?;
+                    } else {
+                        let closing = closing_bracket(tokens)?;
+                        let depth = target.depth.max(operand.depth);
+                        let index = ast::Expression::Index {
+                            token,
+                            target: target.expression,
+                            index: operand.expression,
+                        };
+                        operand = nest(ast, index, depth, max_depth)?;
+                        ast.cover(operand.expression, closing.span);
+                    }
+                }
+                Some(Frame::Slice(target, token, start)) => {
+                    let closing = closing_bracket(tokens)?;
+                    let end = Some(operand);
+                    operand = slice(ast, target, token, start, end, closing, max_depth)?;
+                }
+                Some(_) => unreachable!("operators are applied before closing a grouping or call"),
+            }
+        }
+    }
+}
+
+/// Prefix operators, they bind tighter than any binary operator
+pub(crate) const UNARY_OPERATORS: [TokenType; 2] = [TokenType::Bang, TokenType::Minus];
+
+/// Binary operators and their precedence, higher precedence binds tighter and all of
+/// them are left associative. New operators only need an entry here (and a token).
+pub(crate) const BINARY_OPERATORS: [(TokenType, u8); 11] = [
+    (TokenType::Equals, 1),
+    (TokenType::BangEquals, 1),
+    (TokenType::Greater, 2),
+    (TokenType::GreaterEquals, 2),
+    (TokenType::Smaller, 2),
+    (TokenType::SmallerEquals, 2),
+    (TokenType::In, 2),
+    (TokenType::Minus, 3),
+    (TokenType::Plus, 3),
+    (TokenType::Star, 4),
+    (TokenType::Slash, 4),
+];
+
+/// Precedence of the unary operators, higher than that of any binary operator
+pub(crate) const UNARY_PRECEDENCE: u8 = 5;
+
+/// Precedence of 'is', the same as that of the comparison operators
+pub(crate) const TYPE_TEST_PRECEDENCE: u8 = 2;
+
+/// The precedence of a binary operator, None if the token isn't one
+pub(crate) fn precedence(operator: &TokenType) -> Option<u8> {
+    BINARY_OPERATORS
+        .iter()
+        .find(|(token_type, _)| token_type == operator)
+        .map(|&(_, precedence)| precedence)
+}
+
+/// Applies the binary operators on top of the stack whose precedence is at least min_precedence,
+/// operand becomes the right operand of the last one of them
+fn reduce_binary(
+    ast: &mut Ast,
+    stack: &mut Vec<Frame>,
+    mut operand: Operand,
+    min_precedence: u8,
+    max_depth: usize,
+) -> Result<Operand, ParseError> {
+    while let Some(&Frame::Binary(_, _, precedence)) = stack.last() {
+        if precedence < min_precedence {
+            break;
+        }
+        if let Some(Frame::Binary(left, token, _)) = stack.pop() {
+            let depth = left.depth.max(operand.depth);
+            let expression = ast::Expression::BinaryExpression {
+                token,
+                left: left.expression,
+                right: operand.expression,
+            };
+            operand = nest(ast, expression, depth, max_depth)?;
+        }
     }
-    Ok(left)
+    Ok(operand)
+}
+
+/// Adds target[start:end], token is the '[' and closing the ']'
+fn slice(
+    ast: &mut Ast,
+    target: Operand,
+    token: Token,
+    start: Option<Operand>,
+    end: Option<Operand>,
+    closing: Token,
+    max_depth: usize,
+) -> Result<Operand, ParseError> {
+    let depth = [Some(&target), start.as_ref(), end.as_ref()]
+        .iter()
+        .flatten()
+        .map(|operand| operand.depth)
+        .max()
+        .unwrap_or(0);
+    let slice = ast::Expression::Slice {
+        token,
+        target: target.expression,
+        start: start.map(|start| start.expression),
+        end: end.map(|end| end.expression),
+    };
+    let slice = nest(ast, slice, depth, max_depth)?;
+    ast.cover(slice.expression, closing.span);
+    Ok(slice)
 }
 
-fn parse_factor<I>(tokens: &mut Peekable<I>) -> ParsedExpressionResult
+fn closing_bracket<I>(tokens: &mut Peekable<I>) -> Result<Token, ParseError>
 where
     I: Iterator<Item = Token>,
 {
-    const FACTOR_TOKENS: [TokenType; 2] = [TokenType::Star, TokenType::Slash];
-    let mut left = parse_unary_operation(tokens)?;
-    while let Some(token) = match_token(tokens, &FACTOR_TOKENS) {
-        let right = parse_unary_operation(tokens)?;
-        left = Box::new(ast::Expression::BinaryExpression { token, left, right });
+    match match_token(tokens, &[TokenType::RightBracket]) {
+        Some(closing) => Ok(closing),
+        None => Err(unexpected(next_token(tokens), ParseError::MissingBracket)),
     }
-    Ok(left)
 }
 
-fn parse_unary_operation<I>(tokens: &mut Peekable<I>) -> ParsedExpressionResult
+/// Adds value is Type, token is the 'is' and the name of the type comes next
+fn type_test<I>(
+    tokens: &mut Peekable<I>,
+    ast: &mut Ast,
+    value: Operand,
+    token: Token,
+    max_depth: usize,
+) -> Result<Operand, ParseError>
 where
     I: Iterator<Item = Token>,
 {
-    const UNARY_OPERATORS: [TokenType; 2] = [TokenType::Bang, TokenType::Minus];
-    if let Some(token) = match_token(tokens, &UNARY_OPERATORS) {
-        // stuff like !! and even -- is allowed by the grammar...
-        let right = parse_unary_operation(tokens)?;
-        return Ok(Box::new(ast::Expression::UnaryExpression { token, right }));
+    let name = match match_token(tokens, &[TokenType::Identifier]) {
+        Some(name) => name,
+        None => return Err(unexpected(next_token(tokens), ParseError::MissingTypeName)),
+    };
+    let expression = ast::Expression::TypeTest {
+        token,
+        value: value.expression,
+        type_name: Symbol::intern(&name.lexeme),
+    };
+    let type_test = nest(ast, expression, value.depth, max_depth)?;
+    ast.cover(type_test.expression, name.span);
+    Ok(type_test)
+}
+
+fn assignment(
+    ast: &mut Ast,
+    target: Operand,
+    token: Token,
+    value: Operand,
+    max_depth: usize,
+) -> Result<Operand, ParseError> {
+    match ast[target.expression] {
+        ast::Expression::Variable { token: _, name } => {
+            let depth = value.depth;
+            let value = value.expression;
+            let assignment = nest(
+                ast,
+                ast::Expression::Assignment { token, name, value },
+                depth,
+                max_depth,
+            )?;
+            // the span starts at the target, which isn't a subexpression
+            ast.cover(assignment.expression, ast.span(target.expression));
+            Ok(assignment)
+        }
+        ast::Expression::Tuple { ref elements, .. } => {
+            let names = elements
+                .iter()
+                .map(|&element| match ast[element] {
+                    ast::Expression::Variable { token: _, name } => Some(name),
+                    _ => None,
+                })
+                .collect::<Option<Vec<Symbol>>>()
+                .ok_or_else(|| ParseError::InvalidAssignmentTarget(token.clone()))?;
+            let depth = value.depth;
+            let value = value.expression;
+            let assignment = nest(
+                ast,
+                ast::Expression::MultipleAssignment {
+                    token,
+                    names,
+                    value,
+                },
+                depth,
+                max_depth,
+            )?;
+            ast.cover(assignment.expression, ast.span(target.expression));
+            Ok(assignment)
+        }
+        _ => Err(ParseError::InvalidAssignmentTarget(token)),
     }
-    parse_primary_expr(tokens)
 }
 
-fn parse_primary_expr<I>(tokens: &mut Peekable<I>) -> ParsedExpressionResult
+fn leaf(ast: &mut Ast, expression: ast::Expression) -> Operand {
+    Operand {
+        expression: ast.add(expression),
+        depth: 0,
+    }
+}
+
+/// Wraps an expression whose deepest child has the given depth
+fn nest(
+    ast: &mut Ast,
+    expression: ast::Expression,
+    child_depth: usize,
+    max_depth: usize,
+) -> Result<Operand, ParseError> {
+    let depth = child_depth + 1;
+    if depth > max_depth {
+        return Err(ParseError::NestingTooDeep(expression.token().clone()));
+    }
+    Ok(Operand {
+        expression: ast.add(expression),
+        depth,
+    })
+}
+
+fn parse_literal<I>(tokens: &mut Peekable<I>) -> Result<ast::Expression, ParseError>
 where
     I: Iterator<Item = Token>,
 {
     if let Some(token) = match_token(tokens, &[TokenType::IntegerLiteral]) {
-        let value = token.lexeme.parse::<i32>().unwrap();
-        return Ok(Box::new(ast::Expression::IntegerLiteral { token, value }));
+        let value = match integer_value(&token.lexeme) {
+            Ok(value) => value,
+            Err(error) if *error.kind() == IntErrorKind::PosOverflow => {
+                return Err(ParseError::IntegerTooLarge(token))
+            }
+            Err(_) => return Err(ParseError::InvalidLiteral(token)),
+        };
+        return Ok(ast::Expression::IntegerLiteral { token, value });
     }
     if let Some(token) = match_token(tokens, &[TokenType::BooleanLiteral]) {
-        let value = token.lexeme.parse::<bool>().unwrap();
-        return Ok(Box::new(ast::Expression::BooleanLiteral { token, value }));
+        let value = match token.lexeme.parse::<bool>() {
+            Ok(value) => value,
+            Err(_) => return Err(ParseError::InvalidLiteral(token)),
+        };
+        return Ok(ast::Expression::BooleanLiteral { token, value });
     }
     if let Some(token) = match_token(tokens, &[TokenType::StringLiteral]) {
         let value = token.lexeme.clone();
-        return Ok(Box::new(ast::Expression::StringLiteral { token, value }));
-    }
-    if let Some(token) = match_token(tokens, &[TokenType::LeftParen]) {
-        let expr = parse_expression(tokens)?;
-        if let None = match_token(tokens, &[TokenType::RightParen]) {
-            return Err(ParseError::MissingBrace(next_token(tokens)));
-        };
-        return Ok(Box::new(ast::Expression::Grouping { token, expr }));
+        return Ok(ast::Expression::StringLiteral { token, value });
     }
 
-    Err(ParseError::MissingExpression(next_token(tokens)))
+    Err(unexpected(
+        next_token(tokens),
+        ParseError::MissingExpression,
+    ))
+}
+
+/// The error for finding token where something else was expected, unless the token
+/// isn't valid, in which case that is the error
+fn unexpected(token: Token, error: fn(Token) -> ParseError) -> ParseError {
+    match token.token_type {
+        TokenType::InvalidToken => ParseError::Lex(LexError::from_invalid_token(token)),
+        _ => error(token),
+    }
 }
 
 fn next_token<I>(tokens: &mut Peekable<I>) -> Token
@@ -157,12 +878,46 @@ where
         .clone()
 }
 
+fn match_prefix_operator<I>(tokens: &mut Peekable<I>, options: &ParseOptions) -> Option<Token>
+where
+    I: Iterator<Item = Token>,
+{
+    let token = tokens.peek()?;
+    let is_operator = match token.token_type {
+        TokenType::Operator => options
+            .operators
+            .iter()
+            .any(|o| o.symbol == *token.lexeme && o.fixity == Fixity::Prefix),
+        ref token_type => UNARY_OPERATORS.contains(token_type),
+    };
+    if is_operator {
+        tokens.next()
+    } else {
+        None
+    }
+}
+
+fn match_binary_operator<I>(tokens: &mut Peekable<I>, options: &ParseOptions) -> Option<(Token, u8)>
+where
+    I: Iterator<Item = Token>,
+{
+    let token = tokens.peek()?;
+    let precedence = match token.token_type {
+        TokenType::Operator => options.operators.iter().find_map(|o| match o.fixity {
+            Fixity::Infix(precedence) if o.symbol == *token.lexeme => Some(precedence),
+            _ => None,
+        }),
+        ref token_type => precedence(token_type),
+    }?;
+    tokens.next().map(|token| (token, precedence))
+}
+
 fn match_token<I>(tokens: &mut Peekable<I>, types_to_match: &[TokenType]) -> Option<Token>
 where
     I: Iterator<Item = Token>,
 {
     if let Some(next_token) = tokens.peek() {
-        if let Some(_) = types_to_match.iter().find(|&t| *t == next_token.token_type) {
+        if types_to_match.contains(&next_token.token_type) {
             return tokens.next();
         }
     }
@@ -225,6 +980,107 @@ mod tests {
         assert_ast("7 * 9 - 3", "(- (* (IntLit 7) (IntLit 9)) (IntLit 3))")
     }
 
+    #[test]
+    fn precedence_table() {
+        for (operator, _) in &BINARY_OPERATORS {
+            let symbol = operator.symbol().unwrap();
+            // operators of the same precedence are left associative
+            let input = format!("1 {0} 2 {0} 3", symbol);
+            let expected = format!("({0} ({0} (IntLit 1) (IntLit 2)) (IntLit 3))", symbol);
+            assert_ast(&input, &expected);
+            // and bind looser than unary operators
+            let input = format!("-1 {} !2", symbol);
+            let expected = format!("({} (- (IntLit 1)) (! (IntLit 2)))", symbol);
+            assert_ast(&input, &expected);
+        }
+        assert_eq!(precedence(&TokenType::Assignment), None);
+    }
+
+    #[test]
+    fn index_and_slice() {
+        assert_ast(
+            "-f(x)[i + 1][2:]",
+            "(- (Slice (Index (Call f (Var x)) (+ (Var i) (IntLit 1))) (IntLit 2) _))",
+        );
+        assert_ast(
+            "(s)[:-1] * s[:]",
+            "(* (Slice (Group (Var s)) _ (- (IntLit 1))) (Slice (Var s) _ _))",
+        );
+        let (ast, root) = parse("s[1:2] ").unwrap();
+        assert_eq!(ast.span(root), Span::new(0, 6));
+        let error = parse("s[1:2").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "ParseError at end of file: Expected ']', but 'EOF' was found. [E0026]"
+        );
+        assert!(matches!(
+            parse("s[] "),
+            Err(ParseError::MissingExpression(_))
+        ));
+    }
+
+    #[test]
+    fn membership() {
+        assert_ast(
+            "x + 1 in a == !(\"b\" in s)",
+            "(== (in (+ (Var x) (IntLit 1)) (Var a)) (! (Group (in (StrLit b) (Var s)))))",
+        );
+    }
+
+    #[test]
+    fn type_tests() {
+        assert_ast(
+            "-x is Integer == y + 1 is Boolean",
+            "(== (is (- (Var x)) Integer) (is (+ (Var y) (IntLit 1)) Boolean))",
+        );
+        assert_ast(
+            "x < 1 is Boolean is Boolean",
+            "(is (is (< (Var x) (IntLit 1)) Boolean) Boolean)",
+        );
+        let (ast, root) = parse("s[0] is String ").unwrap();
+        assert_eq!(ast.span(root), Span::new(0, 14));
+        let error = parse("x is 1").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "ParseError at line 1: Expected a type name, but '1' was found. [E0028]"
+        );
+    }
+
+    #[test]
+    fn pipes() {
+        assert_ast(
+            "x + 1 |> f |> g(2, y) == 3",
+            "(== (Call g (Call f (+ (Var x) (IntLit 1))) (IntLit 2) (Var y)) (IntLit 3))",
+        );
+        assert_ast(
+            "y = (x |> f()) * 2",
+            "(= y (* (Group (Call f (Var x))) (IntLit 2)))",
+        );
+        assert_ast("-x |> f", "(Call f (- (Var x)))");
+        let (ast, root) = parse(" x |> g(1) ").unwrap();
+        assert_eq!(ast.span(root), Span::new(1, 10));
+        let error = parse("x |> 1").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "ParseError at line 1: Expected a function name, but '1' was found. [E0034]"
+        );
+    }
+
+    #[test]
+    fn tuples() {
+        assert_ast("a, b = b, a", "(= (a b) (Tuple (Var b) (Var a)))");
+        assert_ast("x = 1, 2", "(= x (Tuple (IntLit 1) (IntLit 2)))");
+        assert_ast(
+            "((1,), (2, 3))[0]",
+            "(Index (Tuple (Tuple (IntLit 1)) (Tuple (IntLit 2) (IntLit 3))) (IntLit 0))",
+        );
+        assert_ast("f(x, y)", "(Call f (Var x) (Var y))");
+        assert!(matches!(
+            parse("a, b[0] = 1, 2"),
+            Err(ParseError::InvalidAssignmentTarget(_))
+        ));
+    }
+
     #[test]
     fn operator_precedence_with_grouping() {
         assert_ast(
@@ -233,10 +1089,91 @@ mod tests {
         )
     }
 
+    #[test]
+    fn parse_with_recovery() {
+        let input = "x = 1;\ny = (x +;\n;\nprint(x) z;\n-x";
+        let (program, errors) = parse_program_with_recovery(input, &ParseOptions::default());
+        let trees: Vec<String> = program
+            .statements
+            .iter()
+            .map(|&s| ast_printer::print_ast(&program.ast, s))
+            .collect();
+        assert_eq!(
+            trees,
+            [
+                "(= x (IntLit 1))",
+                "(Error y = (x +)",
+                "(Error )",
+                "(Error print(x) z)",
+                "(- (Var x))"
+            ]
+        );
+        let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            errors,
+            [
+                "ParseError at line 2: Expected expression, but ';' was found. [E0002]",
+                "ParseError at line 3: Expected expression, but ';' was found. [E0002]",
+                "ParseError at line 4: Expected ';', but 'z' was found. [E0009]",
+            ]
+        );
+        // only the expressions of the statements are in the tree
+        assert_eq!(program.ast.len(), 8);
+        assert_eq!(program.ast.span(program.statements[1]), Span::new(7, 15));
+        assert_eq!(
+            crate::format_program(&program),
+            "x = 1;\ny = (x +;\n;\nprint(x) z;\n-x;\n"
+        );
+
+        let (program, errors) = parse_program_with_recovery("1 + 2", &ParseOptions::default());
+        assert!(errors.is_empty());
+        assert_eq!(program, parse_program("1 + 2").unwrap());
+    }
+
+    #[test]
+    fn parse_token_stream() {
+        let token = |token_type, lexeme: &str| Token::new(token_type, lexeme, 1);
+        let tokens = vec![
+            token(TokenType::Identifier, "x"),
+            token(TokenType::Assignment, "="),
+            token(TokenType::IntegerLiteral, "0b11"),
+            token(TokenType::Comment, "// three"),
+            token(TokenType::Semicolon, ";"),
+            token(TokenType::Minus, "-"),
+            token(TokenType::Identifier, "x"),
+        ];
+        let program = parse_tokens(tokens.into_iter()).unwrap();
+        let trees: Vec<String> = program
+            .statements
+            .iter()
+            .map(|&s| ast_printer::print_ast(&program.ast, s))
+            .collect();
+        assert_eq!(trees, ["(= x (IntLit 3))", "(- (Var x))"]);
+
+        let error = parse_tokens(Lexer::with_comments("1 // one\n2")).unwrap_err();
+        assert_eq!(error.code(), "E0009");
+        let error = parse_tokens(vec![token(TokenType::Plus, "+")].into_iter()).unwrap_err();
+        assert_eq!(error.code(), "E0002");
+        for invalid in [
+            token(TokenType::BooleanLiteral, "yes"),
+            token(TokenType::IntegerLiteral, "1a"),
+        ] {
+            let error = parse_tokens(vec![invalid].into_iter()).unwrap_err();
+            assert_eq!(error.code(), "E0036");
+        }
+        let error = parse_tokens(vec![token(TokenType::BooleanLiteral, "yes")].into_iter());
+        assert_eq!(
+            error.unwrap_err().message(),
+            "'yes' is not a valid Boolean literal."
+        );
+        let error = parse_tokens(vec![token(TokenType::IntegerLiteral, "3000000000")].into_iter());
+        assert_eq!(error.unwrap_err().code(), "E0018");
+    }
+
     #[test]
     #[should_panic(expected = "Error at line 1: Expected expression, but ';' was found")]
     fn missing_expression_error() {
-        if let Err(error) = parse(String::from("8 + ;")) {
+        if let Err(error) = parse("8 + ;") {
             panic!("{}", error);
         }
     }
@@ -244,13 +1181,183 @@ mod tests {
     #[test]
     #[should_panic(expected = "Error at end of file: Expected ')', but 'EOF' was found")]
     fn missing_closing_brace_error() {
-        if let Err(error) = parse(String::from("(8 + 7")) {
+        if let Err(error) = parse("(8 + 7") {
+            panic!("{}", error);
+        }
+    }
+
+    #[test]
+    fn error_codes() {
+        let error = parse("(8 + 7").unwrap_err();
+        assert_eq!(error.code(), "E0001");
+        let error = parse("8 + ;").unwrap_err();
+        assert_eq!(error.code(), "E0002");
+        let error = parse("0x8000_0000").unwrap_err();
+        assert_eq!(error.code(), "E0018");
+        let error = parse("2147483648").unwrap_err();
+        assert_eq!(error.code(), "E0018");
+        assert_eq!(
+            error.message(),
+            "Integer literal '2147483648' is too large, the maximum is 2147483647."
+        );
+    }
+
+    #[test]
+    fn parse_integer_radixes() {
+        assert_ast(
+            "0xff + 0o17 * 0b11",
+            "(+ (IntLit 255) (* (IntLit 15) (IntLit 3)))",
+        );
+        assert_ast("1_000", "(IntLit 1000)");
+    }
+
+    #[test]
+    fn parse_variable() {
+        assert_ast("x_1", "(Var x_1)");
+        assert_ast("x + 1", "(+ (Var x) (IntLit 1))");
+    }
+
+    #[test]
+    fn expression_spans() {
+        let input = "x = f(1, (2 + y))";
+        let (ast, root) = parse(input).unwrap();
+        let source = |id| {
+            let span = ast.span(id);
+            &input[span.start..span.end]
+        };
+        assert_eq!(source(root), input);
+        let call = ast[root].children()[0];
+        assert_eq!(source(call), "f(1, (2 + y))");
+        let arguments = ast[call].children();
+        assert_eq!(source(arguments[0]), "1");
+        assert_eq!(source(arguments[1]), "(2 + y)");
+        assert_eq!(source(ast[arguments[1]].children()[0]), "2 + y");
+    }
+
+    #[test]
+    fn parse_assignment() {
+        assert_ast("x = 3 * 2", "(= x (* (IntLit 3) (IntLit 2)))");
+        assert_ast("x = y = 3", "(= x (= y (IntLit 3)))");
+        assert_ast("x = y == 3", "(= x (== (Var y) (IntLit 3)))");
+    }
+
+    #[test]
+    fn nesting_limit() {
+        let options = ParseOptions {
+            max_depth: 3,
+            ..ParseOptions::default()
+        };
+        assert!(parse_with_options("(((1)))", &options).is_ok());
+        assert!(parse_with_options("!--1", &options).is_ok());
+        for input in &["((((1))))", "!--!1", "a = b = c = d = 1", "f(g(h(i(1))))"] {
+            let error = parse_with_options(input, &options).unwrap_err();
+            assert_eq!(error.code(), "E0014");
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "ParseError at line 1: Expression is nested too deeply. [E0014]")]
+    fn nesting_too_deep_error() {
+        let input = "(".repeat(100_000) + "1" + &")".repeat(100_000);
+        if let Err(error) = parse(&input) {
             panic!("{}", error);
         }
     }
 
+    #[test]
+    fn parse_deep_expressions() {
+        // the default limit is low enough to evaluate and print any expression that parses
+        let chain = vec!["1"; DEFAULT_MAX_DEPTH + 1].join(" + ");
+        let nested = "-(".repeat(DEFAULT_MAX_DEPTH / 2) + "1" + &")".repeat(DEFAULT_MAX_DEPTH / 2);
+        for input in [chain, nested] {
+            let (ast, root) = parse(&input).unwrap();
+            crate::check_warnings(&ast, root);
+            crate::evaluate(&ast, root).unwrap();
+            ast_printer::print_ast(&ast, root);
+        }
+    }
+
+    #[test]
+    fn parse_call() {
+        assert_ast("f()", "(Call f)");
+        assert_ast("f(1)", "(Call f (IntLit 1))");
+        assert_ast(
+            "f(x = 1, g(2) + 3)",
+            "(Call f (= x (IntLit 1)) (+ (Call g (IntLit 2)) (IntLit 3)))",
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Error at end of file: Expected ')', but 'EOF' was found.")]
+    fn missing_closing_brace_in_call_error() {
+        if let Err(error) = parse("f(1, 2") {
+            panic!("{}", error);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Error at line 1: Invalid assignment target, expected a variable.")]
+    fn invalid_assignment_target_error() {
+        if let Err(error) = parse("x + 1 = 3") {
+            panic!("{}", error);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Error at line 2: Expected end of input, but '2' was found.")]
+    fn trailing_tokens_error() {
+        if let Err(error) = parse("1 +\n 1 2") {
+            panic!("{}", error);
+        }
+    }
+
+    #[test]
+    fn parse_programs() {
+        assert_program("", &[]);
+        assert_program("x = 1", &["(= x (IntLit 1))"]);
+        assert_program("x = 1;", &["(= x (IntLit 1))"]);
+        assert_program(
+            "x = 1;\n x + 2;",
+            &["(= x (IntLit 1))", "(+ (Var x) (IntLit 2))"],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Error at line 2: Expected ';', but 'x' was found.")]
+    fn missing_semicolon_error() {
+        if let Err(error) = parse_program("x = 1\nx + 2") {
+            panic!("{}", error);
+        }
+    }
+
+    #[test]
+    fn invalid_token_errors() {
+        let cases = [
+            ("1 + #", "E0020", "Unexpected character '#'."),
+            ("x = 1 # one", "E0020", "Unexpected character '#'."),
+            ("f(1 @", "E0020", "Unexpected character '@'."),
+            ("x = 1; y = $", "E0020", "Unexpected character '$'."),
+            ("print(\"abc", "E0021", "Unterminated string."),
+        ];
+        for (input, code, message) in &cases {
+            let error = parse_program(input).unwrap_err();
+            assert_eq!(error.code(), *code, "{}", input);
+            assert_eq!(error.message(), *message, "{}", input);
+        }
+    }
+
+    fn assert_program(input: &str, expected: &[&str]) {
+        let program = parse_program(input).unwrap();
+        let printed: Vec<String> = program
+            .statements
+            .iter()
+            .map(|&statement| ast_printer::print_ast(&program.ast, statement))
+            .collect();
+        assert_eq!(printed, expected);
+    }
+
     fn assert_ast(input: &str, expected: &str) {
-        let ast = parse(String::from(input)).unwrap();
-        assert_eq!(ast_printer::print_ast(ast), expected);
+        let (ast, root) = parse(input).unwrap();
+        assert_eq!(ast_printer::print_ast(&ast, root), expected);
     }
 }
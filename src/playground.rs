@@ -0,0 +1,82 @@
+use crate::diagnostics::{check_warnings, Diagnostic};
+use crate::evaluation::{evaluate_program, Environment, EvalError, EvalOptions};
+use crate::json;
+use crate::parsing::{parse_program_with_options, ParseOptions};
+
+/// Runs a program within the limits of options and returns a JSON document, meant for
+/// web playgrounds and other tools. It has what the program printed as "output",
+/// its "warnings" and either "result", the value of its last expression (null for
+/// empty programs), "exit", the status code passed to exit(), or "errors".
+pub fn evaluate_to_json(source: &str, options: EvalOptions) -> String {
+    let parse_options = ParseOptions {
+        max_depth: options.max_depth,
+        ..ParseOptions::default()
+    };
+    let (mut environment, output) = Environment::unattended(options);
+    let mut warnings = Vec::new();
+    let outcome = match parse_program_with_options(source, &parse_options) {
+        Ok(program) => {
+            for &statement in &program.statements {
+                let found = check_warnings(&program.ast, statement);
+                warnings.extend(found.iter().map(Diagnostic::from));
+            }
+            match evaluate_program(&program, &mut environment) {
+                Ok(Some(value)) => format!(
+                    "\"result\":{{\"value\":{},\"type\":{},\"display\":{}}}",
+                    value.to_json(),
+                    json::quote(value.type_name()),
+                    json::quote(&value.to_string())
+                ),
+                Ok(None) => String::from("\"result\":null"),
+                Err(EvalError::Exit(_, code)) => format!("\"exit\":{}", code),
+                Err(error) => format!("\"errors\":{}", diagnostics_json(&[(&error).into()])),
+            }
+        }
+        Err(error) => format!("\"errors\":{}", diagnostics_json(&[(&error).into()])),
+    };
+    format!(
+        "{{{},\"warnings\":{},\"output\":{}}}",
+        outcome,
+        diagnostics_json(&warnings),
+        json::quote(&output.contents())
+    )
+}
+
+/// The diagnostics as a JSON array
+pub(crate) fn diagnostics_json(diagnostics: &[Diagnostic]) -> String {
+    let diagnostics: Vec<String> = diagnostics.iter().map(|d| d.to_json()).collect();
+    format!("[{}]", diagnostics.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evaluate() {
+        let options = EvalOptions::default;
+        assert_eq!(
+            evaluate_to_json(r#"print("hi"); "sapo""#, options()),
+            r#"{"result":{"value":"sapo","type":"String","display":"\"sapo\""},"warnings":[],"output":"hi\n"}"#
+        );
+        assert_eq!(
+            evaluate_to_json("", options()),
+            r#"{"result":null,"warnings":[],"output":""}"#
+        );
+        assert_eq!(
+            evaluate_to_json("!!true;\nexit(3)", options()),
+            r#"{"exit":3,"warnings":[{"severity":"warning","code":"W0001","message":"Double '!' has no effect.","span":{"line":1,"column":1}}],"output":""}"#
+        );
+        assert!(evaluate_to_json("x", options())
+            .starts_with(r#"{"errors":[{"severity":"error","code":"E0006""#));
+    }
+
+    #[test]
+    fn limits() {
+        let options = EvalOptions {
+            max_steps: Some(2),
+            ..EvalOptions::default()
+        };
+        assert!(evaluate_to_json("1 + 2", options).contains(r#""code":"E0015""#));
+    }
+}
@@ -0,0 +1,203 @@
+// Python module exposing the interpreter, build it with maturin:
+//
+//     maturin develop --no-default-features --features python
+//
+// and use it as
+//
+//     import sapo
+//     interpreter = sapo.Interpreter()
+//     interpreter.eval("x = 1 + 2")  # 3
+//
+// Interpreters running untrusted programs can limit them with
+//
+//     sapo.Interpreter(max_steps=10000, max_memory=1 << 20, timeout_ms=1000)
+
+use crate::evaluation::{EvalOptions, Object};
+use crate::interpreter::Interpreter;
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyTypeError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyList, PyString, PyTuple};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+create_exception!(
+    sapo,
+    SapoError,
+    PyException,
+    "Error parsing or evaluating a program"
+);
+
+/// Evaluates programs, variables persist between calls to eval
+#[pyclass(name = "Interpreter")]
+struct PyInterpreter {
+    interpreter: Mutex<Interpreter>,
+}
+
+#[pymethods]
+impl PyInterpreter {
+    /// Evaluations stop with a SapoError when they evaluate more than max_steps
+    /// expressions, use about more than max_memory bytes or take longer than timeout_ms
+    /// milliseconds
+    #[new]
+    #[pyo3(signature = (max_steps=None, max_memory=None, timeout_ms=None))]
+    fn new(max_steps: Option<u64>, max_memory: Option<usize>, timeout_ms: Option<u64>) -> Self {
+        let options = EvalOptions {
+            max_steps,
+            max_memory,
+            timeout: timeout_ms.map(Duration::from_millis),
+            ..EvalOptions::default()
+        };
+        PyInterpreter {
+            interpreter: Mutex::new(Interpreter::with_options(options)),
+        }
+    }
+
+    /// Returns the value of the last expression of the program as an int, bool, str or
+    /// list, None if the program is empty. Errors are raised as SapoError. Other Python
+    /// threads run while the program is evaluated.
+    fn eval<'py>(&self, py: Python<'py>, source: &str) -> PyResult<Option<Bound<'py, PyAny>>> {
+        let result = py.detach(|| self.lock().eval_str(source));
+        match result {
+            Ok(Some(value)) => Ok(Some(to_python(py, &value)?)),
+            Ok(None) => Ok(None),
+            Err(error) => Err(SapoError::new_err(error.to_string())),
+        }
+    }
+
+    /// Value of the variable name, None if it was never assigned
+    fn get_global<'py>(&self, py: Python<'py>, name: &str) -> PyResult<Option<Bound<'py, PyAny>>> {
+        let interpreter = self.lock();
+        interpreter
+            .get_global(name)
+            .map(|value| to_python(py, value))
+            .transpose()
+    }
+
+    fn set_global(&self, name: &str, value: &Bound<'_, PyAny>) -> PyResult<()> {
+        let value = from_python(value)?;
+        self.lock().set_global(name, value);
+        Ok(())
+    }
+}
+
+impl PyInterpreter {
+    /// A panic during an evaluation, raised as a PanicException, poisons the mutex.
+    /// The interpreter is still usable afterwards.
+    fn lock(&self) -> MutexGuard<'_, Interpreter> {
+        self.interpreter
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn to_python<'py>(py: Python<'py>, object: &Object) -> PyResult<Bound<'py, PyAny>> {
+    Ok(match object {
+        Object::Integer(value) => value.into_pyobject(py)?.into_any(),
+        Object::Boolean(value) => PyBool::new(py, *value).to_owned().into_any(),
+        Object::String(value) => PyString::new(py, value).into_any(),
+        Object::Array(values) => {
+            let values = values
+                .iter()
+                .map(|v| to_python(py, v))
+                .collect::<PyResult<Vec<_>>>()?;
+            PyList::new(py, values)?.into_any()
+        }
+        Object::Tuple(values) => {
+            let values = values
+                .iter()
+                .map(|v| to_python(py, v))
+                .collect::<PyResult<Vec<_>>>()?;
+            PyTuple::new(py, values)?.into_any()
+        }
+    })
+}
+
+fn from_python(value: &Bound<'_, PyAny>) -> PyResult<Object> {
+    // bool is a subclass of int in Python, so it has to be checked first
+    if let Ok(value) = value.cast::<PyBool>() {
+        return Ok(Object::Boolean(value.is_true()));
+    }
+    if let Ok(value) = value.extract::<i32>() {
+        return Ok(Object::Integer(value));
+    }
+    if let Ok(value) = value.cast::<PyString>() {
+        return Ok(Object::String(value.to_str()?.to_string()));
+    }
+    if let Ok(values) = value.cast::<PyList>() {
+        return values
+            .iter()
+            .map(|v| from_python(&v))
+            .collect::<PyResult<Vec<_>>>()
+            .map(Object::Array);
+    }
+    if let Ok(values) = value.cast::<PyTuple>() {
+        return values
+            .iter()
+            .map(|v| from_python(&v))
+            .collect::<PyResult<Vec<_>>>()
+            .map(Object::Tuple);
+    }
+    Err(PyTypeError::new_err(
+        "expected a 32 bit int, bool, str, list or tuple",
+    ))
+}
+
+#[pymodule]
+fn sapo(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyInterpreter>()?;
+    m.add("SapoError", m.py().get_type::<SapoError>())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_objects() {
+        Python::initialize();
+        Python::attach(|py| {
+            let object = Object::Array(vec![
+                Object::Integer(1),
+                Object::Boolean(true),
+                Object::String(String::from("sapo")),
+            ]);
+            let value = to_python(py, &object).unwrap();
+            assert_eq!(value.repr().unwrap().to_string(), "[1, True, 'sapo']");
+            assert_eq!(from_python(&value).unwrap(), object);
+            assert!(from_python(&1.5f64.into_pyobject(py).unwrap().into_any()).is_err());
+        });
+    }
+
+    #[test]
+    fn eval() {
+        Python::initialize();
+        Python::attach(|py| {
+            let interpreter = PyInterpreter::new(None, None, None);
+            let value = 2i32.into_pyobject(py).unwrap().into_any();
+            interpreter.set_global("x", &value).unwrap();
+            let result = interpreter.eval(py, "y = x * 2").unwrap().unwrap();
+            assert_eq!(result.extract::<i32>().unwrap(), 4);
+            let error = interpreter.eval(py, "z").unwrap_err();
+            assert!(error.is_instance_of::<SapoError>(py));
+        });
+    }
+
+    #[test]
+    fn limits() {
+        Python::initialize();
+        Python::attach(|py| {
+            let interpreter = PyInterpreter::new(Some(10), None, None);
+            let error = interpreter.eval(py, "1 + 2 + 3 + 4 + 5 + 6").unwrap_err();
+            assert!(error.to_string().contains("[E0015]"));
+            let poison = std::panic::catch_unwind(|| {
+                let _guard = interpreter.lock();
+                panic!("poisoned");
+            });
+            assert!(poison.is_err());
+            let result = interpreter.eval(py, "1").unwrap().unwrap();
+            assert_eq!(result.extract::<i32>().unwrap(), 1);
+        });
+    }
+}
@@ -0,0 +1,393 @@
+use super::{Capabilities, ErrorFormat};
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use sapo::token::TokenType;
+use sapo::{Diagnostic, Environment, EvalOptions, Lexer, Object, Severity};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, Instant};
+
+/// State of a REPL session
+struct Session<'a> {
+    environment: Environment,
+    /// Inputs that were evaluated without errors, written out by :save
+    inputs: Vec<String>,
+    error_format: &'a ErrorFormat,
+    color: bool,
+    /// Whether to print the tree of each input before evaluating it
+    show_ast: bool,
+    /// Status code passed to exit(), the REPL terminates once it is set
+    exit_code: Option<i32>,
+}
+
+/// Completes variables, keywords, functions and meta commands when pressing Tab
+struct SapoHelper {
+    /// Variables of the session, updated after every input
+    names: Vec<String>,
+}
+
+impl Completer for SapoHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = line[..pos]
+            .char_indices()
+            .rev()
+            .take_while(|&(_, c)| c.is_alphanumeric() || c == '_' || c == ':')
+            .last()
+            .map_or(pos, |(i, _)| i);
+        let word = &line[start..pos];
+        if word.is_empty() {
+            return Ok((pos, Vec::new()));
+        }
+        let mut candidates: Vec<String> = if word.starts_with(':') {
+            COMMANDS.iter().map(|c| format!(":{}", c)).collect()
+        } else {
+            self.names
+                .iter()
+                .cloned()
+                .chain(sapo::KEYWORDS.iter().map(|k| k.to_string()))
+                .chain(sapo::BUILTINS.iter().map(|b| b.to_string()))
+                .collect()
+        };
+        candidates.retain(|c| c.starts_with(word));
+        candidates.sort_unstable();
+        candidates.dedup();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for SapoHelper {
+    type Hint = String;
+}
+
+impl Highlighter for SapoHelper {}
+
+impl Validator for SapoHelper {}
+
+impl Helper for SapoHelper {}
+
+pub fn run(error_format: &ErrorFormat, color: bool, show_ast: bool, capabilities: Capabilities) {
+    println!("Sapo Programming Language 🐸");
+    let mut editor: Editor<SapoHelper, DefaultHistory> = match Editor::new() {
+        Ok(editor) => editor,
+        Err(error) => {
+            eprintln!("Could not start the REPL: {}", error);
+            process::exit(1);
+        }
+    };
+    let mut session = Session {
+        // the same lines are often evaluated again while experimenting
+        environment: Environment::with_options(EvalOptions {
+            memoize: true,
+            ..EvalOptions::default()
+        }),
+        inputs: Vec::new(),
+        error_format,
+        color,
+        show_ast: false,
+        exit_code: None,
+    };
+    capabilities.enable(&mut session.environment);
+    // Ctrl-C while evaluating cancels the evaluation instead of terminating the REPL,
+    // while reading a line it is handled by the editor
+    let cancellation = session.environment.cancellation_token().clone();
+    if let Err(error) = ctrlc::set_handler(move || cancellation.cancel()) {
+        eprintln!("Could not handle Ctrl-C: {}", error);
+    }
+    session.load_init_file();
+    // the startup file is evaluated silently
+    session.show_ast = show_ast;
+    editor.set_helper(Some(SapoHelper { names: Vec::new() }));
+    let history = history_path();
+    if let Some(path) = &history {
+        // there is no history the first time the REPL is started
+        let _ = editor.load_history(path);
+    }
+
+    while session.exit_code.is_none() {
+        let input = match editor.readline("🐸> ") {
+            Ok(input) => input,
+            // Ctrl-C discards the current line
+            Err(ReadlineError::Interrupted) => continue,
+            // Ctrl-D
+            Err(ReadlineError::Eof) => break,
+            Err(error) => {
+                eprintln!("{}", error);
+                break;
+            }
+        };
+        if input.trim().is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(input.as_str());
+
+        let input = input.trim();
+        if input == "exit" {
+            break;
+        }
+        match input.strip_prefix(':') {
+            Some(command) => {
+                if !session.run_command(command) {
+                    break;
+                }
+            }
+            None => {
+                if session.evaluate(input).is_some() {
+                    session.inputs.push(input.to_string());
+                }
+            }
+        }
+        if let Some(helper) = editor.helper_mut() {
+            helper.names = session
+                .environment
+                .names()
+                .iter()
+                .map(|n| n.to_string())
+                .collect();
+        }
+    }
+
+    if let Some(path) = &history {
+        if let Err(error) = editor.save_history(path) {
+            eprintln!("Could not save history to {}: {}", path.display(), error);
+        }
+    }
+    if let Some(code) = session.exit_code {
+        process::exit(code);
+    }
+}
+
+/// The REPL history is kept in ~/.sapo_history
+fn history_path() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| Path::new(&home).join(".sapo_history"))
+}
+
+const COMMANDS: [&str; 8] = [
+    "help", "ast", "tokens", "env", "load", "save", "time", "quit",
+];
+
+const HELP: &str = "Enter an expression to evaluate it, or one of the following commands:
+  :help         Print this help
+  :ast EXPR     Print the abstract syntax tree of EXPR
+  :tokens EXPR  Print the tokens of EXPR
+  :env          List the variables of the session
+  :load FILE    Evaluate FILE in the session
+  :save FILE    Write the inputs evaluated so far to FILE
+  :time EXPR    Evaluate EXPR and print how long parsing and evaluating took
+  :quit         Exit the REPL";
+
+/// Adds a ';' after the last expression of input if it has none, so that inputs can be
+/// concatenated. It goes before a trailing comment, which would otherwise swallow it.
+fn terminate(input: &str) -> String {
+    let last = Lexer::with_comments(input)
+        .filter(|token| !matches!(token.token_type, TokenType::Comment | TokenType::EOF))
+        .last();
+    match last {
+        Some(token) if token.token_type != TokenType::Semicolon => {
+            let end = token.span.end;
+            format!("{};{}", &input[..end], &input[end..])
+        }
+        _ => input.to_string(),
+    }
+}
+
+impl<'a> Session<'a> {
+    /// Evaluates a program and prints its result
+    /// Returns the time spent parsing and evaluating, or None if there were errors
+    fn evaluate(&mut self, input: &str) -> Option<(Duration, Duration)> {
+        let (result, parse_time, evaluation_time) = self.execute(input)?;
+        if let Some(result) = result {
+            let color = match result {
+                Object::Integer(_) => CYAN,
+                Object::Boolean(_) => MAGENTA,
+                Object::String(_) => GREEN,
+                Object::Array(_) | Object::Tuple(_) => RESET,
+            };
+            println!("{}", self.paint(&result.to_string(), color));
+            // '_' holds the result of the last line
+            self.environment.set("_", result);
+        }
+        Some((parse_time, evaluation_time))
+    }
+
+    /// Evaluates a program in the session without printing its result, only errors and warnings
+    fn execute(&mut self, input: &str) -> Option<(Option<Object>, Duration, Duration)> {
+        let start = Instant::now();
+        let program = sapo::parse_program(input);
+        let parse_time = start.elapsed();
+        let program = match program {
+            Ok(program) => program,
+            Err(error) => {
+                self.report(&error, input);
+                return None;
+            }
+        };
+        for &statement in &program.statements {
+            if self.show_ast {
+                print!("{}", sapo::print_ast_tree(&program.ast, statement));
+            }
+            for warning in sapo::check_warnings(&program.ast, statement) {
+                self.report(&warning, input);
+            }
+        }
+        self.environment.cancellation_token().reset();
+        let start = Instant::now();
+        let result = sapo::evaluate_program(&program, &mut self.environment);
+        let evaluation_time = start.elapsed();
+        match result {
+            Ok(result) => Some((result, parse_time, evaluation_time)),
+            Err(sapo::EvalError::Exit(_, code)) => {
+                self.exit_code = Some(code);
+                None
+            }
+            Err(error) => {
+                self.report(&error, input);
+                None
+            }
+        }
+    }
+
+    /// Evaluates the startup file, if there is one
+    fn load_init_file(&mut self) {
+        let path = match env::var_os("SAPO_INIT") {
+            Some(path) => PathBuf::from(path),
+            None => match env::var_os("HOME") {
+                Some(home) => Path::new(&home).join(".saporc"),
+                None => return,
+            },
+        };
+        match fs::read_to_string(&path) {
+            Ok(code) => {
+                self.execute(&code);
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+            Err(error) => println!("Could not load {}: {}", path.display(), error),
+        }
+    }
+
+    /// Runs a meta command (the input without the leading ':')
+    /// Returns false if the REPL should be exited
+    fn run_command(&mut self, command: &str) -> bool {
+        let (name, argument) = match command.find(char::is_whitespace) {
+            Some(i) => (&command[..i], command[i..].trim()),
+            None => (command, ""),
+        };
+        match name {
+            "help" => println!("{}", HELP),
+            "ast" => match sapo::parse(argument) {
+                Ok((ast, root)) => print!("{}", sapo::print_ast_tree(&ast, root)),
+                Err(error) => self.report(&error, argument),
+            },
+            "tokens" => {
+                for token in sapo::Lexer::new(argument) {
+                    super::print_token(&token);
+                }
+            }
+            "env" => {
+                for name in self.environment.names() {
+                    if let Some(value) = self.environment.get(name) {
+                        println!("{}: {} = {}", name, value.type_name(), value);
+                    }
+                }
+            }
+            "load" => match fs::read_to_string(argument) {
+                Ok(code) => {
+                    self.evaluate(&code);
+                }
+                Err(error) => println!("Could not load {}: {}", argument, error),
+            },
+            "save" => {
+                let script: String = self
+                    .inputs
+                    .iter()
+                    .map(|input| format!("{}\n", terminate(input)))
+                    .collect();
+                if let Err(error) = fs::write(argument, script) {
+                    println!("Could not save {}: {}", argument, error);
+                }
+            }
+            "time" => {
+                if let Some((parse_time, evaluation_time)) = self.evaluate(argument) {
+                    println!("parse: {:?}, evaluation: {:?}", parse_time, evaluation_time);
+                }
+            }
+            "quit" | "q" => return false,
+            _ => println!(
+                "Unknown command ':{}', type :help for a list of commands",
+                name
+            ),
+        }
+        true
+    }
+
+    /// Prints an error or warning, in human readable format the line of
+    /// source it refers to is printed below it
+    fn report<D>(&self, diagnostic: &D, source: &str)
+    where
+        D: fmt::Display,
+        for<'b> &'b D: Into<Diagnostic>,
+    {
+        let message = diagnostic.to_string();
+        let diagnostic: Diagnostic = diagnostic.into();
+        if let ErrorFormat::Json = self.error_format {
+            println!("{}", diagnostic.to_json());
+            return;
+        }
+        let color = match diagnostic.severity {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+        };
+        println!("{}", self.paint(&message, color));
+        let line = diagnostic.line.and_then(|n| {
+            source
+                .lines()
+                .nth((n as usize).saturating_sub(1))
+                .map(|l| (n, l))
+        });
+        if let Some((number, line)) = line {
+            println!("{}", self.paint(&format!("{:>4} | ", number), BLUE) + line);
+            if let Some(column) = diagnostic.column {
+                // tabs are kept so that the caret lines up with the line above
+                let indent: String = line
+                    .chars()
+                    .take(column - 1)
+                    .map(|c| if c == '\t' { '\t' } else { ' ' })
+                    .collect();
+                let caret = self.paint("^", color);
+                println!("{}{}{}", self.paint("     | ", BLUE), indent, caret);
+            }
+        }
+    }
+
+    /// Wraps text in the escape sequences of the given color, if enabled
+    fn paint(&self, text: &str, color: &str) -> String {
+        if self.color {
+            format!("{}{}{}", color, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const BLUE: &str = "\x1b[34m";
+const MAGENTA: &str = "\x1b[35m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
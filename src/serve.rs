@@ -0,0 +1,175 @@
+use sapo::EvalOptions;
+use std::io;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::panic;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Largest program accepted, in bytes
+const MAX_BODY_SIZE: usize = 1 << 20;
+
+/// Largest request line and headers accepted, in bytes
+const MAX_HEADER_SIZE: u64 = 16 << 10;
+
+/// Connections handled at the same time, the ones beyond it are answered with 503
+const MAX_CONNECTIONS: usize = 64;
+
+/// Connections that don't send their request in this time are closed
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Evaluates the programs POSTed to /eval, responding with the JSON document of
+/// sapo::evaluate_to_json. Each program is evaluated with the limits of options.
+pub fn run(address: &str, options: EvalOptions) {
+    let listener = match TcpListener::bind(address) {
+        Ok(listener) => listener,
+        Err(error) => {
+            eprintln!("Could not listen on {}: {}", address, error);
+            process::exit(1);
+        }
+    };
+    println!("Listening on http://{}, POST programs to /eval", address);
+    let connections = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let connection = match Connection::open(&connections) {
+                    Some(connection) => connection,
+                    None => {
+                        let body = error_json("Too many connections, try again later");
+                        if let Err(error) = write_response(&stream, 503, &body) {
+                            eprintln!("{}", error);
+                        }
+                        continue;
+                    }
+                };
+                let options = options.clone();
+                thread::spawn(move || {
+                    let _connection = connection;
+                    if let Err(error) = handle(stream, options) {
+                        eprintln!("{}", error);
+                    }
+                });
+            }
+            Err(error) => eprintln!("{}", error),
+        }
+    }
+}
+
+/// Counts a connection being handled until it is dropped
+struct Connection(Arc<AtomicUsize>);
+
+impl Connection {
+    /// None if there are already MAX_CONNECTIONS
+    fn open(connections: &Arc<AtomicUsize>) -> Option<Connection> {
+        if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            connections.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+        Some(Connection(Arc::clone(connections)))
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn handle(stream: TcpStream, options: EvalOptions) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(&stream);
+    // lines without a newline were cut by the limit or the client closed the connection
+    let mut head = reader.by_ref().take(MAX_HEADER_SIZE);
+    let mut request_line = String::new();
+    head.read_line(&mut request_line)?;
+    let mut complete = request_line.ends_with('\n');
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let path = parts.next().unwrap_or("");
+
+    let mut content_length = Some(0);
+    while complete {
+        let mut line = String::new();
+        head.read_line(&mut line)?;
+        complete = line.ends_with('\n');
+        let line = line.trim_end();
+        if complete && line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().ok();
+            }
+        }
+    }
+    if !complete {
+        if head.limit() > 0 {
+            return Ok(());
+        }
+        let body = error_json("The request line or headers are too large");
+        write_response(&stream, 431, &body)?;
+        // closing with unread input would reset the connection before the client
+        // reads the response
+        stream.shutdown(Shutdown::Write)?;
+        io::copy(&mut reader.take(MAX_HEADER_SIZE), &mut io::sink())?;
+        return Ok(());
+    }
+
+    let (status, body) = match (method, path, content_length) {
+        ("POST", "/eval", None) => (400, error_json("Invalid Content-Length")),
+        ("POST", "/eval", Some(length)) if length > MAX_BODY_SIZE => {
+            (413, error_json("The program is too large"))
+        }
+        ("POST", "/eval", Some(length)) => {
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body)?;
+            match String::from_utf8(body) {
+                Ok(source) => {
+                    match panic::catch_unwind(|| sapo::evaluate_to_json(&source, options)) {
+                        Ok(result) => (200, result),
+                        Err(_) => (500, error_json("The evaluation failed unexpectedly")),
+                    }
+                }
+                Err(_) => (400, error_json("The program is not valid UTF-8")),
+            }
+        }
+        (_, "/eval", _) => (405, error_json("Programs must be sent with POST")),
+        _ => (404, error_json("Not found, POST programs to /eval")),
+    };
+    write_response(&stream, status, &body)
+}
+
+fn write_response(mut stream: &TcpStream, status: u16, body: &str) -> io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Connection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+fn error_json(message: &str) -> String {
+    format!("{{\"error\":\"{}\"}}", message)
+}
@@ -0,0 +1,124 @@
+use sapo::{Environment, EvalError, EvalOptions};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// A test that didn't pass, with its output and the reason
+struct Failure {
+    name: String,
+    output: String,
+    error: String,
+}
+
+/// Runs every *_test.sapo file found in paths, or in the current directory if there
+/// are none. Each file is a test that passes if it runs until the end without errors,
+/// or calls exit(0). Exits with 1 if any test fails.
+pub fn run(paths: Vec<String>) {
+    let paths = if paths.is_empty() {
+        vec![String::from(".")]
+    } else {
+        paths
+    };
+    let mut files = Vec::new();
+    for path in &paths {
+        if let Err(error) = find_tests(Path::new(path), &mut files) {
+            eprintln!("{}: {}", path, error);
+            process::exit(1);
+        }
+    }
+    files.sort();
+
+    let mut failures = Vec::new();
+    for file in &files {
+        let name = file.display().to_string();
+        match run_test(file) {
+            Ok(()) => println!("test {} ... ok", name),
+            Err((output, error)) => {
+                println!("test {} ... FAILED", name);
+                failures.push(Failure {
+                    name,
+                    output,
+                    error,
+                });
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        println!("\nfailures:");
+        for failure in &failures {
+            println!("\n---- {} ----", failure.name);
+            print!("{}", failure.output);
+            println!("{}: {}", failure.name, failure.error);
+        }
+    }
+    println!(
+        "\ntest result: {}. {} passed; {} failed",
+        if failures.is_empty() { "ok" } else { "FAILED" },
+        files.len() - failures.len(),
+        failures.len()
+    );
+    if !failures.is_empty() {
+        process::exit(1);
+    }
+}
+
+/// Adds path to tests if it is a file, or the test files in it if it is a directory
+fn find_tests(path: &Path, tests: &mut Vec<PathBuf>) -> io::Result<()> {
+    if !path.is_dir() {
+        fs::metadata(path)?;
+        tests.push(path.to_path_buf());
+        return Ok(());
+    }
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        // file_type doesn't follow symlinks, so links to directories aren't searched
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            // hidden directories like .git and cargo's build directory have no tests
+            if !name.starts_with('.') && name != "target" {
+                find_tests(&entry.path(), tests)?;
+            }
+        } else if file_type.is_file() && name.ends_with("_test.sapo") {
+            tests.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
+/// Runs the test in file, on failure returns what it printed and the error
+fn run_test(file: &Path) -> Result<(), (String, String)> {
+    let code = fs::read_to_string(file).map_err(|e| (String::new(), e.to_string()))?;
+    let program = sapo::parse_program(&code).map_err(|e| (String::new(), e.to_string()))?;
+    // the output is only shown if the test fails
+    let (mut environment, output) = Environment::unattended(EvalOptions::default());
+    match sapo::evaluate_program(&program, &mut environment) {
+        Ok(_) | Err(EvalError::Exit(_, 0)) => Ok(()),
+        Err(error) => Err((output.contents(), error.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn skips_hidden_and_build_directories() {
+        let root = env::temp_dir().join(format!("sapo-find-tests-{}", process::id()));
+        for directory in ["src", ".git", "target"] {
+            fs::create_dir_all(root.join(directory)).unwrap();
+            fs::write(root.join(directory).join("a_test.sapo"), "1").unwrap();
+        }
+        fs::write(root.join("src/helper.sapo"), "1").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(root.join("src"), root.join("link")).unwrap();
+        let mut tests = Vec::new();
+        find_tests(&root, &mut tests).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(tests, [root.join("src/a_test.sapo")]);
+    }
+}
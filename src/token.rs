@@ -1,11 +1,18 @@
+use crate::json;
+use std::sync::Arc;
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum TokenType {
     // Single character tokens
     Semicolon,
+    Comma,
     LeftParen,
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
+    Colon,
     Bang,
 
     // Arithmetic operators
@@ -22,9 +29,15 @@ pub enum TokenType {
     GreaterEquals,
     Smaller,
     SmallerEquals,
+    /// |>, which passes its left operand to the call on its right
+    Pipe,
+    /// An operator defined by the application embedding sapo, see CustomOperator
+    Operator,
 
     // Keywords
     If,
+    In,
+    Is,
 
     Identifier,
     // Literals
@@ -33,19 +46,118 @@ pub enum TokenType {
     BooleanLiteral,
 
     // Special tokens
+    /// A "// ..." comment, only produced by Lexer::with_comments
+    Comment,
     InvalidToken,
     EOF,
 }
 
+impl TokenType {
+    /// The text of operators and punctuation, None for tokens whose text varies
+    pub fn symbol(&self) -> Option<&'static str> {
+        let symbol = match self {
+            TokenType::Semicolon => ";",
+            TokenType::Comma => ",",
+            TokenType::LeftParen => "(",
+            TokenType::RightParen => ")",
+            TokenType::LeftBrace => "{",
+            TokenType::RightBrace => "}",
+            TokenType::LeftBracket => "[",
+            TokenType::RightBracket => "]",
+            TokenType::Colon => ":",
+            TokenType::Bang => "!",
+            TokenType::Minus => "-",
+            TokenType::Plus => "+",
+            TokenType::Star => "*",
+            TokenType::Slash => "/",
+            TokenType::Assignment => "=",
+            TokenType::Equals => "==",
+            TokenType::BangEquals => "!=",
+            TokenType::Greater => ">",
+            TokenType::GreaterEquals => ">=",
+            TokenType::Smaller => "<",
+            TokenType::SmallerEquals => "<=",
+            TokenType::Pipe => "|>",
+            TokenType::If => "if",
+            TokenType::In => "in",
+            TokenType::Is => "is",
+            _ => return None,
+        };
+        Some(symbol)
+    }
+}
+
+/// Byte offsets of the start and the end (exclusive) of some text in the source
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+
+    /// The smallest span containing both spans
+    pub fn merge(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub struct Token {
     pub token_type: TokenType,
-    pub lexeme: String,
+    /// Shared between the tokens with the same text, so cloning a token doesn't allocate
+    pub lexeme: Arc<str>,
     pub line: i32,
+    /// Position of the first char of the token in its line, counted in chars from 1,
+    /// 0 for tokens that weren't read from a source
+    pub column: usize,
+    /// Set by the lexer, empty for tokens that weren't read from a source
+    pub span: Span,
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, lexeme: String, line: i32) -> Token {
-        Token { token_type, lexeme, line }
+    pub fn new(token_type: TokenType, lexeme: impl Into<Arc<str>>, line: i32) -> Token {
+        Token { token_type, lexeme: lexeme.into(), line, column: 0, span: Span::default() }
+    }
+
+    /// e.g. {"type":"IntegerLiteral","lexeme":"1","span":{"line":1,"column":1,"start":0,"end":1}}
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"type\":{},\"lexeme\":{},\"span\":{{\"line\":{},\"column\":{},\"start\":{},\"end\":{}}}}}",
+            json::quote(&format!("{:?}", self.token_type)),
+            json::quote(&self.lexeme),
+            self.line,
+            self.column,
+            self.span.start,
+            self.span.end
+        )
+    }
+}
+
+pub fn tokens_to_json(tokens: &[Token]) -> String {
+    let objects: Vec<String> = tokens.iter().map(|t| t.to_json()).collect();
+    format!("[{}]", objects.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_to_json_array() {
+        let tokens = vec![
+            Token::new(TokenType::Identifier, String::from("x"), 1),
+            Token::new(TokenType::StringLiteral, String::from("a\"b"), 2),
+        ];
+        assert_eq!(
+            tokens_to_json(&tokens),
+            concat!(
+                r#"[{"type":"Identifier","lexeme":"x","span":{"line":1,"column":0,"start":0,"end":0}},"#,
+                r#"{"type":"StringLiteral","lexeme":"a\"b","span":{"line":2,"column":0,"start":0,"end":0}}]"#
+            )
+        );
     }
 }
@@ -0,0 +1,60 @@
+use crate::diagnostics::check;
+use crate::evaluation::EvalOptions;
+use crate::parsing::parse_program;
+use crate::playground::{diagnostics_json, evaluate_to_json};
+use wasm_bindgen::prelude::*;
+
+/// Parses a program and returns a JSON document, either {"ast": [...]} with the
+/// tree of each expression or {"errors": [...]} with the diagnostic of the error
+#[wasm_bindgen]
+pub fn parse(source: &str) -> String {
+    match parse_program(source) {
+        Ok(program) => format!("{{\"ast\":{}}}", program.to_json()),
+        Err(error) => format!("{{\"errors\":{}}}", diagnostics_json(&[(&error).into()])),
+    }
+}
+
+/// Runs a program and returns a JSON document with its result, see evaluate_to_json
+#[wasm_bindgen]
+pub fn evaluate(source: &str) -> String {
+    evaluate_to_json(source, EvalOptions::default())
+}
+
+/// Errors and warnings of a program without running it, as a JSON array
+#[wasm_bindgen]
+pub fn diagnostics(source: &str) -> String {
+    diagnostics_json(&check(source))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_to_json() {
+        assert_eq!(
+            parse("x"),
+            r#"{"ast":[{"type":"Variable","name":"x","line":1,"span":{"start":0,"end":1}}]}"#
+        );
+        assert_eq!(
+            parse("(1"),
+            r#"{"errors":[{"severity":"error","code":"E0001","message":"Expected ')', but 'EOF' was found.","span":null}]}"#
+        );
+    }
+
+    #[test]
+    fn evaluate_program() {
+        assert_eq!(
+            evaluate("1"),
+            r#"{"result":{"value":1,"type":"Integer","display":"1"},"warnings":[],"output":""}"#
+        );
+    }
+
+    #[test]
+    fn diagnostics_to_json() {
+        assert_eq!(
+            diagnostics("!!true"),
+            r#"[{"severity":"warning","code":"W0001","message":"Double '!' has no effect.","span":{"line":1,"column":1}}]"#
+        );
+    }
+}
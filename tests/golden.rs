@@ -0,0 +1,67 @@
+// Runs every tests/programs/*.sapo and compares what it printed, its warnings and its
+// result or error with the sibling .expected file. Run with SAPO_BLESS=1 to (re)write
+// the .expected files after checking that the new output is right.
+
+use sapo::{check_warnings, evaluate_program, parse_program, Environment, EvalError, EvalOptions};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What running the program produces like `sapo run`: its warnings, then its output,
+/// then the value of its last expression, the status passed to exit() or the error
+fn run(source: &str) -> String {
+    let program = match parse_program(source) {
+        Ok(program) => program,
+        Err(error) => return format!("{}\n", error),
+    };
+    let mut warnings = String::new();
+    for &statement in &program.statements {
+        for warning in check_warnings(&program.ast, statement) {
+            warnings.push_str(&format!("{}\n", warning));
+        }
+    }
+    let (mut environment, output) = Environment::unattended(EvalOptions::default());
+    let outcome = match evaluate_program(&program, &mut environment) {
+        Ok(Some(value)) => format!("{}\n", value),
+        Ok(None) => String::new(),
+        Err(EvalError::Exit(_, code)) => format!("exit({})\n", code),
+        Err(error) => format!("{}\n", error),
+    };
+    warnings + &output.contents() + &outcome
+}
+
+fn programs() -> Vec<PathBuf> {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/programs");
+    let mut programs: Vec<PathBuf> = fs::read_dir(directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == "sapo"))
+        .collect();
+    programs.sort();
+    programs
+}
+
+#[test]
+fn golden_programs() {
+    let bless = env::var_os("SAPO_BLESS").is_some();
+    let mut failures = Vec::new();
+    for program in programs() {
+        let actual = run(&fs::read_to_string(&program).unwrap());
+        let expected_path = program.with_extension("expected");
+        if bless {
+            fs::write(&expected_path, &actual).unwrap();
+            continue;
+        }
+        match fs::read_to_string(&expected_path) {
+            Ok(expected) if expected == actual => {}
+            Ok(expected) => failures.push(format!(
+                "{}\n--- expected\n{}--- actual\n{}",
+                program.display(),
+                expected,
+                actual
+            )),
+            Err(_) => failures.push(format!("{}: missing .expected file", program.display())),
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}